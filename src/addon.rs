@@ -0,0 +1,347 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use crate::manifest::EntryHasher;
+use crate::sha::Sha256;
+use crate::{
+    AddonInfo, AddonMetadata, AddonTag, AddonType, Builder, EntryKind, GMAFile, GmaArchive,
+    GmaError, ManifestEntry, MountCheck, MountIssue, MountReport, ReadOptions, ReadWarning,
+    Severity, WORKSHOP_MAX_SIZE, check_type_and_tags, read_with,
+};
+
+/// High-level view of an addon: its metadata, files and content hashes.
+///
+/// Load one from an existing `.gma`, inspect or edit it, then turn it back into
+/// a [`Builder`], write it out directly, or [`publish`](Self::publish) it.
+#[derive(Clone, Debug, Default)]
+pub struct Addon {
+    archive: GmaArchive,
+    metadata: AddonMetadata,
+    fingerprints: Vec<ManifestEntry>,
+}
+
+impl Addon {
+    #[inline]
+    pub fn load<R: Read>(reader: R) -> Result<Self, GmaError> {
+        Self::load_with(reader, &Self::default_options())
+    }
+
+    /// Load with custom options. Entry hashes are computed after loading if
+    /// [`ReadOptions::set_entry_hashes`] is off.
+    pub fn load_with<R: Read>(reader: R, opts: &ReadOptions) -> Result<Self, GmaError> {
+        let archive = read_with(reader, opts)?;
+        let metadata = archive.info().metadata();
+        // gmad packs the type and tags into the description, but an addon.json
+        // left in the archive still says what the author meant
+        #[cfg(feature = "json")]
+        let metadata = match archive.addon_json() {
+            Some(json) if metadata.addon_type.is_empty() => AddonMetadata {
+                addon_type: json.addon_type.clone(),
+                tags: json.tags.clone(),
+                ..metadata
+            },
+            _ => metadata,
+        };
+        let fingerprints = if archive.manifest().len() == archive.len() {
+            archive.manifest().to_vec()
+        } else {
+            archive
+                .entries()
+                .iter()
+                .map(|f| {
+                    let mut hasher = EntryHasher::new(false);
+                    hasher.update(&f.content);
+                    hasher.finish(&f.name)
+                })
+                .collect()
+        };
+        Ok(Self {
+            archive,
+            metadata,
+            fingerprints,
+        })
+    }
+
+    pub fn load_path(path: impl AsRef<Path>) -> Result<Self, GmaError> {
+        let file = File::open(path)?;
+        let mut opts = Self::default_options();
+        opts.set_stream_len(file.metadata()?.len());
+        Self::load_with(file, &opts)
+    }
+
+    fn default_options() -> ReadOptions {
+        let mut opts = ReadOptions::default();
+        #[cfg(feature = "json")]
+        opts.set_parse_addon_json(true);
+        opts.set_entry_hashes(true);
+        opts
+    }
+
+    pub fn archive(&self) -> &GmaArchive {
+        &self.archive
+    }
+
+    pub fn title(&self) -> &str {
+        self.archive.name()
+    }
+
+    pub fn description(&self) -> &str {
+        self.archive.description()
+    }
+
+    pub fn author(&self) -> &str {
+        self.archive.author()
+    }
+
+    /// All header fields of the loaded archive.
+    pub fn info(&self) -> &AddonInfo {
+        self.archive.info()
+    }
+
+    pub fn files(&self) -> &[GMAFile] {
+        self.archive.entries()
+    }
+
+    /// The header fields with the description unpacked, taking the type and
+    /// tags from a packed `addon.json` if the description has none.
+    pub fn metadata(&self) -> &AddonMetadata {
+        &self.metadata
+    }
+
+    /// The addon type the files suggest, if they make it clear: a gamemode, a
+    /// map, a tool, weapons, entities or effects by their Lua folders, or
+    /// models and content without any Lua.
+    pub fn inferred_type(&self) -> Option<AddonType> {
+        let names: Vec<String> = self
+            .files()
+            .iter()
+            .map(|f| f.name.replace('\\', "/").to_ascii_lowercase())
+            .collect();
+        let any = |prefix: &str| names.iter().any(|n| n.starts_with(prefix));
+        let kinds: Vec<EntryKind> = names.iter().map(|n| EntryKind::of(n)).collect();
+        if kinds.contains(&EntryKind::Gamemode) {
+            return Some(AddonType::Gamemode);
+        }
+        if names
+            .iter()
+            .any(|n| n.starts_with("maps/") && n.ends_with(".bsp"))
+        {
+            return Some(AddonType::Map);
+        }
+        if any("lua/weapons/gmod_tool/stools/") {
+            return Some(AddonType::Tool);
+        }
+        if any("lua/weapons/") {
+            return Some(AddonType::Weapon);
+        }
+        if any("lua/entities/") {
+            return Some(AddonType::Entity);
+        }
+        if any("lua/effects/") {
+            return Some(AddonType::Effects);
+        }
+        if kinds.is_empty() || kinds.contains(&EntryKind::Lua) {
+            return None;
+        }
+        if kinds.contains(&EntryKind::Model) {
+            Some(AddonType::Model)
+        } else {
+            Some(AddonType::ServerContent)
+        }
+    }
+
+    /// The declared type and tags, checked against the Workshop's rules; if
+    /// no type is declared, the [`inferred_type`](Self::inferred_type) is used.
+    pub fn type_and_tags(&self) -> Result<(AddonType, Vec<AddonTag>), GmaError> {
+        match self.inferred_type() {
+            Some(inferred) if self.metadata.addon_type.is_empty() => {
+                check_type_and_tags(inferred.as_str(), &self.metadata.tags)
+            }
+            _ => self.metadata.workshop_type_and_tags(),
+        }
+    }
+
+    /// Whether the game should mount the files, within the Workshop's size limit
+    /// (see [`MountCheck`]).
+    pub fn validation(&self) -> MountReport {
+        let mut check = MountCheck::new();
+        check.set_max_size(WORKSHOP_MAX_SIZE);
+        check.check_files(self.files())
+    }
+
+    /// SHA-256 of every entry, in table order.
+    pub fn fingerprints(&self) -> &[ManifestEntry] {
+        &self.fingerprints
+    }
+
+    /// One SHA-256 over every entry's name and content hash, in table order:
+    /// equal for addons with the same files, whatever their header says.
+    pub fn fingerprint(&self) -> [u8; 32] {
+        let mut hash = Sha256::new();
+        for f in &self.fingerprints {
+            hash.update(f.name.as_bytes());
+            hash.update(&[0]);
+            hash.update(&f.sha256);
+        }
+        hash.finish()
+    }
+
+    /// The packed `addon.json`, if any.
+    #[cfg(feature = "json")]
+    pub fn addon_json(&self) -> Option<&crate::AddonJson> {
+        self.archive.addon_json()
+    }
+
+    /// Problems noticed while loading.
+    pub fn warnings(&self) -> &[ReadWarning] {
+        self.archive.warnings()
+    }
+
+    /// A [`Builder`] pre-populated with this addon's metadata and files.
+    ///
+    /// Keeps the original format and addon versions and required content; the
+    /// packer's id is available as
+    /// `info().steam_id64`.
+    pub fn to_builder(&self, steam_id64: i64) -> Builder {
+        let files = self.files();
+        let mut b = Builder::new_with_capacity(self.title(), steam_id64, files.len());
+        b.set_description(self.description());
+        b.set_author(self.author());
+        // Read versions are always writable
+        let _ = b.set_target_version(self.info().version);
+        b.set_addon_version(self.info().addon_version);
+        b.set_required_content(self.info().required_content.clone());
+        for f in files {
+            b.file_from_bytes(f.name.clone(), f.content.clone());
+        }
+        b
+    }
+
+    /// Rebuild the addon into `w`.
+    pub fn build_to<W: Write>(&self, steam_id64: i64, w: W) -> Result<(), GmaError> {
+        self.to_builder(steam_id64).write_to(w)
+    }
+
+    /// Rebuild the addon for a Workshop upload and write it to `path`
+    /// atomically (see [`Builder::write_to_path_atomic`]).
+    ///
+    /// The [`type_and_tags`](Self::type_and_tags) are written into the
+    /// description, so an inferred type is recorded. Fails without writing if
+    /// they aren't valid, if an entry is unsafe or not whitelisted (see
+    /// [`validation`](Self::validation)), or with [`GmaError::OverSizeLimit`]
+    /// over [`WORKSHOP_MAX_SIZE`].
+    pub fn publish(&self, steam_id64: i64, path: impl AsRef<Path>) -> Result<(), GmaError> {
+        let (addon_type, tags) = self.type_and_tags()?;
+        let report = self.validation();
+        let mut not_whitelisted = Vec::new();
+        for p in report.problems() {
+            let Some(name) = p.name.as_ref().filter(|_| p.severity == Severity::Error) else {
+                continue;
+            };
+            match p.issue {
+                MountIssue::UnsafePath => {
+                    let index = self.files().iter().position(|f| &f.name == name);
+                    return Err(GmaError::UnsafePath {
+                        name: name.clone(),
+                        index: index.unwrap_or_default(),
+                    });
+                }
+                MountIssue::NotWhitelisted => not_whitelisted.push(name.clone()),
+                _ => {}
+            }
+        }
+        if !not_whitelisted.is_empty() {
+            return Err(GmaError::NotWhitelisted(not_whitelisted));
+        }
+
+        let mut metadata = self.metadata.clone();
+        metadata.set_type_and_tags(addon_type, &tags);
+        let mut b = self.to_builder(steam_id64);
+        b.set_description(metadata.gma_description());
+        b.set_max_size(WORKSHOP_MAX_SIZE);
+        b.write_to_path_atomic(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addon(files: &[&str], description: &str) -> Addon {
+        let mut b = Builder::new("test", 1);
+        b.set_description(description);
+        for name in files {
+            b.file_from_bytes(*name, name.as_bytes().to_vec());
+        }
+        Addon::load(&b.write_to_vec().unwrap()[..]).unwrap()
+    }
+
+    #[test]
+    fn type_is_inferred_from_files() {
+        let cases: [(&[&str], Option<AddonType>); 5] = [
+            (
+                &["gamemodes/x/gamemode/init.lua"],
+                Some(AddonType::Gamemode),
+            ),
+            (
+                &["maps/gm_x.bsp", "lua/autorun/x.lua"],
+                Some(AddonType::Map),
+            ),
+            (&["lua/weapons/x/shared.lua"], Some(AddonType::Weapon)),
+            (&["models/x.mdl", "materials/x.vmt"], Some(AddonType::Model)),
+            (&["lua/autorun/x.lua"], None),
+        ];
+        for (files, expected) in cases {
+            assert_eq!(addon(files, "").inferred_type(), expected, "{files:?}");
+        }
+    }
+
+    #[test]
+    fn declared_type_wins_over_inferred() {
+        let declared = r#"{"description":"d","type":"tool","tags":["fun"]}"#;
+        let a = addon(&["lua/weapons/x/shared.lua"], declared);
+        assert_eq!(
+            a.type_and_tags().unwrap(),
+            (AddonType::Tool, vec![AddonTag::Fun])
+        );
+        let a = addon(&["lua/weapons/x/shared.lua"], "plain");
+        assert_eq!(a.type_and_tags().unwrap(), (AddonType::Weapon, vec![]));
+    }
+
+    #[test]
+    fn fingerprint_ignores_the_header() {
+        let a = addon(&["lua/autorun/a.lua", "lua/autorun/b.lua"], "one");
+        let b = addon(&["lua/autorun/a.lua", "lua/autorun/b.lua"], "two");
+        let c = addon(&["lua/autorun/a.lua"], "one");
+        assert_eq!(a.fingerprints().len(), 2);
+        assert_eq!(a.fingerprint(), b.fingerprint());
+        assert_ne!(a.fingerprint(), c.fingerprint());
+
+        // Hashed after loading when the reader didn't
+        let data = a.to_builder(1).write_to_vec().unwrap();
+        let plain = Addon::load_with(&data[..], &ReadOptions::default()).unwrap();
+        assert_eq!(plain.fingerprints(), a.fingerprints());
+    }
+
+    #[test]
+    fn publish_records_the_type_and_refuses_bad_files() {
+        let dir = std::env::temp_dir().join(format!("gma-lite-publish-{}", std::process::id()));
+        let path = dir.join("out.gma");
+
+        let bad = addon(&["lua/weapons/x/shared.lua", "readme.exe"], "");
+        assert!(!bad.validation().passes());
+        assert!(matches!(
+            bad.publish(1, &path),
+            Err(GmaError::NotWhitelisted(names)) if names == ["readme.exe"]
+        ));
+        assert!(!path.exists());
+
+        let good = addon(&["lua/weapons/x/shared.lua"], "");
+        good.publish(1, &path).unwrap();
+        let published = Addon::load_path(&path).unwrap();
+        assert_eq!(published.metadata().addon_type, "weapon");
+        assert_eq!(published.fingerprint(), good.fingerprint());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
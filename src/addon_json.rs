@@ -0,0 +1,256 @@
+use std::collections::BTreeMap;
+
+use crate::json::{self, Value};
+use crate::metadata;
+use crate::{AddonMetadata, GmaError, check_type_and_tags};
+
+/// Typed view of an `addon.json`, the project file gmad packs from.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct AddonJson {
+    pub title: String,
+    pub description: String,
+    /// The `"type"` field (`"gamemode"`, `"map"`, `"weapon"`, ...).
+    pub addon_type: String,
+    pub tags: Vec<String>,
+    /// Wildcard patterns of files to leave out when packing.
+    pub ignore: Vec<String>,
+    /// The `"workshopid"` of the published item, if the project records it.
+    pub workshop_id: Option<u64>,
+}
+
+impl AddonJson {
+    /// Parse the text of an `addon.json`. Missing fields are left empty.
+    pub fn parse(text: &str) -> Result<Self, GmaError> {
+        let root = json::parse(text).map_err(GmaError::InvalidAddonJson)?;
+        if !matches!(root, Value::Object(_)) {
+            return Err(GmaError::InvalidAddonJson("expected an object".into()));
+        }
+        Ok(Self {
+            title: string_field(&root, "title")?,
+            description: string_field(&root, "description")?,
+            addon_type: string_field(&root, "type")?,
+            tags: string_list(&root, "tags")?,
+            ignore: string_list(&root, "ignore")?,
+            workshop_id: workshop_id(&root)?,
+        })
+    }
+
+    /// Check what gmad checks before packing: a title, and a type and tags it
+    /// accepts (see [`check_type_and_tags`]).
+    pub fn validate(&self) -> Result<(), GmaError> {
+        if self.title.is_empty() {
+            return Err(GmaError::InvalidAddonJson("\"title\" is missing".into()));
+        }
+        check_type_and_tags(&self.addon_type, &self.tags)?;
+        Ok(())
+    }
+
+    /// The file's text: pretty JSON with keys sorted, leaving out an empty
+    /// description and a missing workshop id. An id too large for a JSON
+    /// number to hold exactly is written as a string. [`parse`](Self::parse)
+    /// reads it back unchanged.
+    pub fn to_json(&self) -> String {
+        let string = |s: &str| Value::String(s.to_owned());
+        let list = |items: &[String]| Value::Array(items.iter().map(|s| string(s)).collect());
+        let mut root = BTreeMap::new();
+        root.insert("title".to_owned(), string(&self.title));
+        if !self.description.is_empty() {
+            root.insert("description".to_owned(), string(&self.description));
+        }
+        root.insert("type".to_owned(), string(&self.addon_type));
+        root.insert("tags".to_owned(), list(&self.tags));
+        root.insert("ignore".to_owned(), list(&self.ignore));
+        if let Some(id) = self.workshop_id {
+            let id = if id < MAX_EXACT_ID {
+                Value::Number(id as f64)
+            } else {
+                Value::String(id.to_string())
+            };
+            root.insert("workshopid".to_owned(), id);
+        }
+        Value::Object(root).to_string()
+    }
+
+    /// The title, description, type and tags as [`AddonMetadata`]; the other
+    /// fields are left at their defaults.
+    pub fn metadata(&self) -> AddonMetadata {
+        AddonMetadata {
+            title: self.title.clone(),
+            description: self.description.clone(),
+            addon_type: self.addon_type.clone(),
+            tags: self.tags.clone(),
+            ..AddonMetadata::default()
+        }
+    }
+
+    /// A project file for `metadata`, with no ignores or workshop id.
+    pub fn from_metadata(metadata: &AddonMetadata) -> Self {
+        Self {
+            title: metadata.title.clone(),
+            description: metadata.description.clone(),
+            addon_type: metadata.addon_type.clone(),
+            tags: metadata.tags.clone(),
+            ..Self::default()
+        }
+    }
+
+    /// The archive description gmad writes for this project: a JSON object with
+    /// the description (`"Description"` if empty), type and tags, which the
+    /// game reads back.
+    pub fn gma_description(&self) -> String {
+        let description = match self.description.as_str() {
+            "" => "Description",
+            d => d,
+        };
+        metadata::gma_description(description, &self.addon_type, &self.tags)
+    }
+}
+
+fn string_field(root: &Value, key: &str) -> Result<String, GmaError> {
+    match root.get(key) {
+        None | Some(Value::Null) => Ok(String::new()),
+        Some(v) => v
+            .as_str()
+            .map(str::to_owned)
+            .ok_or_else(|| GmaError::InvalidAddonJson(format!("\"{key}\" must be a string"))),
+    }
+}
+
+/// Ids from here on don't survive a round trip through a JSON number (f64).
+const MAX_EXACT_ID: u64 = 1 << 53;
+
+/// `"workshopid"`, as a number or a numeric string.
+fn workshop_id(root: &Value) -> Result<Option<u64>, GmaError> {
+    let bad = || GmaError::InvalidAddonJson("\"workshopid\" must be a whole number".into());
+    match root.get("workshopid") {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Number(n)) if n.fract() == 0.0 && *n >= 0.0 && *n < MAX_EXACT_ID as f64 => {
+            Ok(Some(*n as u64))
+        }
+        Some(Value::String(s)) => s.parse().map(Some).map_err(|_| bad()),
+        Some(_) => Err(bad()),
+    }
+}
+
+fn string_list(root: &Value, key: &str) -> Result<Vec<String>, GmaError> {
+    let bad = || GmaError::InvalidAddonJson(format!("\"{key}\" must be an array of strings"));
+    match root.get(key) {
+        None | Some(Value::Null) => Ok(Vec::new()),
+        Some(v) => v
+            .as_array()
+            .ok_or_else(bad)?
+            .iter()
+            .map(|item| item.as_str().map(str::to_owned).ok_or_else(bad))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROJECT: &str = r#"{
+        "title": "My Tool",
+        "type": "tool",
+        "tags": ["fun", "build"],
+        "ignore": ["*.psd", "*.vcproj"],
+        "workshopid": 123456789
+    }"#;
+
+    #[test]
+    fn parses_and_validates() {
+        let json = AddonJson::parse(PROJECT).unwrap();
+        assert_eq!(json.title, "My Tool");
+        assert_eq!(json.description, "");
+        assert_eq!(json.addon_type, "tool");
+        assert_eq!(json.tags, ["fun", "build"]);
+        assert_eq!(json.ignore, ["*.psd", "*.vcproj"]);
+        assert_eq!(json.workshop_id, Some(123456789));
+        json.validate().unwrap();
+
+        let by_string = AddonJson::parse(r#"{"workshopid": "42"}"#).unwrap();
+        assert_eq!(by_string.workshop_id, Some(42));
+    }
+
+    #[test]
+    fn rejects_bad_fields() {
+        for text in [
+            "[]",
+            r#"{"title": 1}"#,
+            r#"{"tags": "fun"}"#,
+            r#"{"ignore": [1]}"#,
+            r#"{"workshopid": -1}"#,
+            r#"{"workshopid": 1.5}"#,
+            r#"{"workshopid": "abc"}"#,
+        ] {
+            assert!(
+                matches!(AddonJson::parse(text), Err(GmaError::InvalidAddonJson(_))),
+                "{text}"
+            );
+        }
+
+        let mut json = AddonJson::parse(PROJECT).unwrap();
+        json.tags.push("water".into());
+        assert!(json.validate().is_err());
+        json.title.clear();
+        assert!(matches!(
+            json.validate(),
+            Err(GmaError::InvalidAddonJson(_))
+        ));
+    }
+
+    #[test]
+    fn writes_back_unchanged() {
+        let json = AddonJson::parse(PROJECT).unwrap();
+        let text = json.to_json();
+        assert!(!text.contains("description"));
+        assert_eq!(AddonJson::parse(&text).unwrap(), json);
+
+        let m = json.metadata();
+        assert_eq!(m.title, "My Tool");
+        assert_eq!(m.addon_type, "tool");
+        let back = AddonJson::from_metadata(&m);
+        assert_eq!(back.workshop_id, None);
+        assert_eq!(back.tags, json.tags);
+    }
+
+    #[test]
+    fn gma_description_defaults_the_text() {
+        let json = AddonJson::parse(PROJECT).unwrap();
+        let parsed = crate::json::parse(&json.gma_description()).unwrap();
+        assert_eq!(
+            parsed.get("description").and_then(Value::as_str),
+            Some("Description")
+        );
+        assert_eq!(parsed.get("type").and_then(Value::as_str), Some("tool"));
+    }
+
+    #[test]
+    fn large_workshop_ids_round_trip() {
+        for id in [
+            0,
+            MAX_EXACT_ID - 1,
+            MAX_EXACT_ID,
+            MAX_EXACT_ID + 1,
+            u64::MAX,
+        ] {
+            let json = AddonJson {
+                title: "t".into(),
+                workshop_id: Some(id),
+                ..AddonJson::default()
+            };
+            let text = json.to_json();
+            assert_eq!(
+                AddonJson::parse(&text).unwrap().workshop_id,
+                Some(id),
+                "{text}"
+            );
+        }
+        let text = AddonJson {
+            workshop_id: Some(123),
+            ..AddonJson::default()
+        }
+        .to_json();
+        assert!(text.contains("\"workshopid\": 123\n"), "{text}");
+    }
+}
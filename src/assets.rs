@@ -0,0 +1,218 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::GMAFile;
+
+/// Kind of logical asset an [`Asset`] groups.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum AssetKind {
+    /// `.mdl` plus its `.vvd`, `.vtx` and optional `.phy` / `.ani`.
+    Model,
+    /// `.vmt` plus the `.vtf` textures it references.
+    Material,
+}
+
+/// Related entries that only work together, e.g. the files of one model.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Asset {
+    pub kind: AssetKind,
+    /// Shared path without extension, lowercased (e.g. `models/props/crate`).
+    pub name: String,
+    /// Archive entries belonging to the asset.
+    pub files: Vec<String>,
+    /// Files the asset needs that aren't in the archive.
+    ///
+    /// For materials these are referenced textures, which may still ship with
+    /// the game itself.
+    pub missing: Vec<String>,
+}
+
+impl Asset {
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+}
+
+/// Model file suffixes, longest first so `.dx90.vtx` wins over `.vtx`.
+const MODEL_SUFFIXES: &[&str] = &[
+    ".dx90.vtx",
+    ".dx80.vtx",
+    ".xbox.vtx",
+    ".sw.vtx",
+    ".mdl",
+    ".vvd",
+    ".vtx",
+    ".phy",
+    ".ani",
+];
+
+/// VMT parameters whose value names a texture.
+const TEXTURE_PARAMS: &[&str] = &[
+    "$basetexture",
+    "$basetexture2",
+    "$bumpmap",
+    "$bumpmap2",
+    "$normalmap",
+    "$detail",
+    "$envmapmask",
+    "$selfillummask",
+    "$phongexponenttexture",
+    "$phongwarptexture",
+    "$lightwarptexture",
+    "$blendmodulatetexture",
+    "$tintmasktexture",
+    "$ambientoccltexture",
+    "$corneatexture",
+    "$iris",
+];
+
+/// Group model and material files into logical assets.
+///
+/// Entries that belong to neither are left out. Assets are sorted by kind, then name.
+pub fn group_assets(entries: &[GMAFile]) -> Vec<Asset> {
+    // Normalized path → name as stored
+    let names: HashMap<String, &str> = entries
+        .iter()
+        .map(|e| (normalize(&e.name), e.name.as_str()))
+        .collect();
+
+    let mut models: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut assets = Vec::new();
+    for e in entries {
+        let name = normalize(&e.name);
+        if name.starts_with("models/") {
+            if let Some(suffix) = MODEL_SUFFIXES.iter().find(|s| name.ends_with(**s)) {
+                let base = name[..name.len() - suffix.len()].to_owned();
+                models.entry(base).or_default().push(e.name.clone());
+            }
+        } else if name.starts_with("materials/")
+            && let Some(base) = name.strip_suffix(".vmt")
+        {
+            let mut files = vec![e.name.clone()];
+            let mut missing = Vec::new();
+            for texture in vmt_textures(&String::from_utf8_lossy(&e.content)) {
+                let path = format!("materials/{texture}.vtf");
+                if let Some(&stored) = names.get(&path) {
+                    if !files.iter().any(|f| f == stored) {
+                        files.push(stored.to_owned());
+                    }
+                } else if !missing.contains(&path) {
+                    missing.push(path);
+                }
+            }
+            assets.push(Asset {
+                kind: AssetKind::Material,
+                name: base.to_owned(),
+                files,
+                missing,
+            });
+        }
+    }
+
+    for (base, files) in models {
+        let has = |suffix: &str| names.contains_key(&format!("{base}{suffix}"));
+        let mut missing = Vec::new();
+        for required in [".mdl", ".vvd"] {
+            if !has(required) {
+                missing.push(format!("{base}{required}"));
+            }
+        }
+        if ![".dx90.vtx", ".dx80.vtx", ".sw.vtx", ".xbox.vtx", ".vtx"]
+            .iter()
+            .any(|s| has(s))
+        {
+            missing.push(format!("{base}.dx90.vtx"));
+        }
+        assets.push(Asset {
+            kind: AssetKind::Model,
+            name: base,
+            files,
+            missing,
+        });
+    }
+
+    assets.sort_by(|a, b| (a.kind, &a.name).cmp(&(b.kind, &b.name)));
+    assets
+}
+
+fn normalize(name: &str) -> String {
+    name.replace('\\', "/").to_ascii_lowercase()
+}
+
+/// Texture paths (relative to `materials/`, no extension) referenced by a VMT.
+fn vmt_textures(vmt: &str) -> Vec<String> {
+    let tokens = kv_tokens(vmt);
+    let mut textures = Vec::new();
+    for pair in tokens.windows(2) {
+        let (key, value) = (&pair[0], &pair[1]);
+        if value == "{" || value == "}" {
+            continue;
+        }
+        if TEXTURE_PARAMS.iter().any(|p| key.eq_ignore_ascii_case(p)) {
+            let mut texture = normalize(value);
+            while texture.contains("//") {
+                texture = texture.replace("//", "/");
+            }
+            let texture = texture.trim_start_matches('/');
+            let texture = texture.strip_prefix("materials/").unwrap_or(texture);
+            let texture = texture.strip_suffix(".vtf").unwrap_or(texture);
+            if !texture.is_empty() {
+                textures.push(texture.to_owned());
+            }
+        }
+    }
+    textures
+}
+
+/// Split KeyValues text into tokens: quoted strings, bare words, `{` and `}`.
+fn kv_tokens(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '/' => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    // Line comment
+                    for c in chars.by_ref() {
+                        if c == '\n' {
+                            break;
+                        }
+                    }
+                } else {
+                    tokens.push("/".to_owned());
+                }
+            }
+            '{' | '}' => {
+                chars.next();
+                tokens.push(c.to_string());
+            }
+            '"' => {
+                chars.next();
+                let mut s = String::new();
+                for c in chars.by_ref() {
+                    if c == '"' {
+                        break;
+                    }
+                    s.push(c);
+                }
+                tokens.push(s);
+            }
+            _ => {
+                let mut s = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, '"' | '{' | '}') {
+                        break;
+                    }
+                    s.push(c);
+                    chars.next();
+                }
+                tokens.push(s);
+            }
+        }
+    }
+    tokens
+}
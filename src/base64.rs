@@ -0,0 +1,101 @@
+//! Standard base64 with padding, for byte fields in JSON.
+
+use alloc::string::String;
+#[cfg(any(feature = "serde", test))]
+use alloc::vec::Vec;
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub(crate) fn encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = u32::from_be_bytes([0, b[0], b[1], b[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// The bytes [`encode`] made `text` from, or `None` if it isn't padded base64.
+#[cfg(any(feature = "serde", test))]
+pub(crate) fn decode(text: &str) -> Option<Vec<u8>> {
+    let text = text.as_bytes();
+    if !text.len().is_multiple_of(4) {
+        return None;
+    }
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    for (i, quad) in text.chunks(4).enumerate() {
+        let last = i == text.len() / 4 - 1;
+        let pad = quad.iter().rev().take_while(|&&c| c == b'=').count();
+        if pad > 2 || (pad > 0 && !last) {
+            return None;
+        }
+        let mut n = 0u32;
+        for &c in &quad[..4 - pad] {
+            let digit = ALPHABET.iter().position(|&a| a == c)?;
+            n = n << 6 | digit as u32;
+        }
+        n <<= 6 * pad;
+        out.extend_from_slice(&n.to_be_bytes()[1..4 - pad]);
+    }
+    Some(out)
+}
+
+/// `#[serde(with = "crate::base64::as_base64")]`: bytes as a base64 string.
+#[cfg(feature = "serde")]
+pub(crate) mod as_base64 {
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    use ::serde::de::Error as _;
+    use ::serde::{Deserialize, Deserializer, Serializer};
+
+    pub(crate) fn serialize<S: Serializer>(
+        bytes: &impl AsRef<[u8]>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::encode(bytes.as_ref()))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>, C: From<Vec<u8>>>(
+        deserializer: D,
+    ) -> Result<C, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        super::decode(&text)
+            .map(C::from)
+            .ok_or_else(|| D::Error::custom("expected padded base64"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_vectors_round_trip() {
+        for (data, text) in [
+            (&b""[..], ""),
+            (b"f", "Zg=="),
+            (b"fo", "Zm8="),
+            (b"foo", "Zm9v"),
+            (b"foob", "Zm9vYg=="),
+            (b"\xff\x00\xfe", "/wD+"),
+        ] {
+            assert_eq!(encode(data), text);
+            assert_eq!(decode(text).as_deref(), Some(data));
+        }
+        for bad in ["Zg=", "Z===", "Zg==Zg==", "Zm9*", "===="] {
+            assert_eq!(decode(bad), None, "{bad}");
+        }
+    }
+}
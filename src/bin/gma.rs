@@ -0,0 +1,101 @@
+//! `gma`: a small reference CLI over the library.
+//!
+//! ```text
+//! gma extract <file.gma> [-o <dir>] [--only <pattern>]... [-j <threads>]
+//! ```
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use gma_lite::Extractor;
+
+const USAGE: &str = "\
+usage: gma extract <file.gma> [-o <dir>] [--only <pattern>]... [-j <threads>]
+
+  -o, --output <dir>    where to extract (default: the archive's name, without .gma)
+      --only <pattern>  only extract entries matching a gmad-style wildcard,
+                        e.g. 'lua/*'; may be repeated
+  -j, --threads <n>     worker threads, 0 for one per core (default: 1)";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("extract") => extract(&args[1..]),
+        Some("-h" | "--help") => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Some(other) => Err(Error::Usage(format!("unknown command: {other}"))),
+        None => Err(Error::Usage("no command given".into())),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(Error::Usage(why)) => {
+            eprintln!("gma: {why}\n\n{USAGE}");
+            ExitCode::from(2)
+        }
+        Err(Error::Failed(why)) => {
+            eprintln!("gma: {why}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+enum Error {
+    /// Bad arguments; the usage text is printed too.
+    Usage(String),
+    Failed(String),
+}
+
+fn extract(args: &[String]) -> Result<(), Error> {
+    let mut input = None;
+    let mut output = None;
+    let mut only = Vec::new();
+    let mut threads = 1;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| Error::Usage(format!("{arg} needs a value")))
+        };
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(PathBuf::from(value()?)),
+            "--only" => only.push(value()?.clone()),
+            "-j" | "--threads" => {
+                threads = value()?
+                    .parse()
+                    .map_err(|_| Error::Usage(format!("{arg} needs a number")))?;
+            }
+            flag if flag.starts_with('-') && flag != "-" => {
+                return Err(Error::Usage(format!("unknown option: {flag}")));
+            }
+            path if input.is_none() => input = Some(PathBuf::from(path)),
+            extra => return Err(Error::Usage(format!("unexpected argument: {extra}"))),
+        }
+    }
+    let input = input.ok_or_else(|| Error::Usage("no archive given".into()))?;
+    let output = output.unwrap_or_else(|| default_output(&input));
+
+    let mut extractor = Extractor::new();
+    extractor.set_threads(threads);
+    extractor.set_only(&only);
+    let written = extractor
+        .extract_file(&input, &output)
+        .map_err(|e| Error::Failed(format!("{}: {e}", input.display())))?;
+    println!(
+        "extracted {} file{} to {}",
+        written.len(),
+        if written.len() == 1 { "" } else { "s" },
+        output.display()
+    );
+    Ok(())
+}
+
+/// `addon.gma` extracts to `addon/`, next to where the command runs.
+fn default_output(input: &Path) -> PathBuf {
+    match input.file_stem() {
+        Some(stem) if input.extension().is_some() => PathBuf::from(stem),
+        _ => PathBuf::from(format!("{}_extracted", input.display())),
+    }
+}
@@ -0,0 +1,178 @@
+use std::io::{self, Read, Write};
+
+use crate::ManifestEntry;
+
+const MAGIC: &[u8; 4] = b"GMBF";
+
+/// Compact probabilistic index of one archive's entry names and content hashes.
+///
+/// Answers "might this archive contain X?" with no false negatives, and false
+/// positives at roughly the rate it was sized for, so large collections can be
+/// narrowed down without opening most archives. Names are matched ASCII
+/// case-insensitively.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArchiveFilter {
+    bits: Vec<u64>,
+    hashes: u32,
+}
+
+impl ArchiveFilter {
+    /// An empty filter sized for `items` insertions at `false_positive_rate`.
+    pub fn new(items: usize, false_positive_rate: f64) -> Self {
+        let n = items.max(1) as f64;
+        let p = false_positive_rate.clamp(1e-9, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+        let bits = (-n * p.ln() / (ln2 * ln2)).ceil().max(64.0);
+        let hashes = ((bits / n) * ln2).round().clamp(1.0, 16.0) as u32;
+        Self {
+            bits: vec![0; (bits as usize).div_ceil(64)],
+            hashes,
+        }
+    }
+
+    /// Index every entry's name and SHA-256 from a manifest.
+    pub fn from_manifest(manifest: &[ManifestEntry], false_positive_rate: f64) -> Self {
+        let mut filter = Self::new(manifest.len() * 2, false_positive_rate);
+        for e in manifest {
+            filter.insert_name(&e.name);
+            filter.insert_hash(&e.sha256);
+        }
+        filter
+    }
+
+    pub fn insert_name(&mut self, name: &str) {
+        self.insert(b'n', name.to_ascii_lowercase().as_bytes());
+    }
+
+    /// Add a content hash (any digest; SHA-256 for [`from_manifest`](Self::from_manifest)).
+    pub fn insert_hash(&mut self, hash: &[u8]) {
+        self.insert(b'h', hash);
+    }
+
+    pub fn may_contain_name(&self, name: &str) -> bool {
+        self.contains(b'n', name.to_ascii_lowercase().as_bytes())
+    }
+
+    pub fn may_contain_hash(&self, hash: &[u8]) -> bool {
+        self.contains(b'h', hash)
+    }
+
+    /// Serialize: a `GMBF` magic, the hash count, the word count, then the bit words.
+    pub fn write_to<W: Write>(&self, mut w: W) -> io::Result<()> {
+        w.write_all(MAGIC)?;
+        w.write_all(&self.hashes.to_le_bytes())?;
+        w.write_all(&(self.bits.len() as u64).to_le_bytes())?;
+        for word in &self.bits {
+            w.write_all(&word.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(mut r: R) -> io::Result<Self> {
+        let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+        let mut magic = [0u8; 4];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not an archive filter"));
+        }
+        let mut b4 = [0u8; 4];
+        r.read_exact(&mut b4)?;
+        let hashes = u32::from_le_bytes(b4);
+        let mut b8 = [0u8; 8];
+        r.read_exact(&mut b8)?;
+        let words = u64::from_le_bytes(b8);
+        if hashes == 0 || hashes > 16 || words == 0 {
+            return Err(invalid("corrupt archive filter"));
+        }
+        // Grow with the data actually present rather than trusting the count.
+        let mut bits = Vec::new();
+        for _ in 0..words {
+            r.read_exact(&mut b8)?;
+            bits.push(u64::from_le_bytes(b8));
+        }
+        Ok(Self { bits, hashes })
+    }
+
+    fn insert(&mut self, tag: u8, data: &[u8]) {
+        for bit in self.positions(tag, data) {
+            self.bits[bit / 64] |= 1 << (bit % 64);
+        }
+    }
+
+    fn contains(&self, tag: u8, data: &[u8]) -> bool {
+        self.positions(tag, data)
+            .all(|bit| self.bits[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+
+    /// Double hashing: bit i is `h1 + i * h2`.
+    fn positions(&self, tag: u8, data: &[u8]) -> impl Iterator<Item = usize> + use<> {
+        let h1 = fnv1a(0xcbf29ce484222325, tag, data);
+        let h2 = fnv1a(0x84222325cbf29ce4, tag, data) | 1;
+        let len = self.bits.len() as u64 * 64;
+        (0..self.hashes as u64).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % len) as usize)
+    }
+}
+
+fn fnv1a(seed: u64, tag: u8, data: &[u8]) -> u64 {
+    let mut h = seed;
+    for &b in std::iter::once(&tag).chain(data) {
+        h ^= b as u64;
+        h = h.wrapping_mul(0x100000001b3);
+    }
+    h
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha::Sha256;
+
+    fn manifest(count: usize) -> Vec<ManifestEntry> {
+        (0..count)
+            .map(|i| {
+                let name = format!("lua/autorun/file_{i}.lua");
+                let mut h = Sha256::new();
+                h.update(name.as_bytes());
+                ManifestEntry {
+                    sha256: h.finish(),
+                    size: name.len() as u64,
+                    name,
+                    sha1: None,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn no_false_negatives_across_persist_and_reload() {
+        let entries = manifest(2000);
+        let filter = ArchiveFilter::from_manifest(&entries, 0.01);
+        let mut bytes = Vec::new();
+        filter.write_to(&mut bytes).unwrap();
+        let reloaded = ArchiveFilter::read_from(&bytes[..]).unwrap();
+        assert_eq!(reloaded, filter);
+
+        for e in &entries {
+            assert!(reloaded.may_contain_name(&e.name), "{}", e.name);
+            assert!(reloaded.may_contain_name(&e.name.to_ascii_uppercase()));
+            assert!(reloaded.may_contain_hash(&e.sha256), "{}", e.name);
+        }
+        let false_positives = (0..2000)
+            .filter(|i| reloaded.may_contain_name(&format!("lua/other_{i}.lua")))
+            .count();
+        assert!(false_positives < 100, "{false_positives}");
+    }
+
+    #[test]
+    fn corrupt_filters_are_rejected() {
+        let mut bytes = Vec::new();
+        ArchiveFilter::new(10, 0.01).write_to(&mut bytes).unwrap();
+        assert!(ArchiveFilter::read_from(&bytes[..bytes.len() - 1]).is_err());
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'X';
+        assert!(ArchiveFilter::read_from(&bad_magic[..]).is_err());
+        let mut no_hashes = bytes;
+        no_hashes[4..8].fill(0);
+        assert!(ArchiveFilter::read_from(&no_hashes[..]).is_err());
+    }
+}
@@ -1,18 +1,99 @@
-use std::io::{self, BufWriter, Write};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::{GMAFile, GmaError, HEADER, VERSION};
+use crate::crc::{self, Crc32};
+use crate::licenses::{LICENSES_FILE, Licenses};
+use crate::parallel;
+use crate::rewrite::{self, RewriteRule};
+use crate::transaction;
+use crate::validate::{self, DuplicateContent, ValidationReport};
+use crate::{
+    AddonInfo, AddonMetadata, Cancelled, GMAFile, GmaError, HEADER, MIN_VERSION, ReadOptions,
+    SteamId, VERSION, is_whitelisted, same_path,
+};
+
+/// Largest archive the Garry's Mod Workshop accepts, to pass to
+/// [`Builder::set_max_size`]. Uploads over it are refused, so this is the
+/// limit to check against before writing.
+pub const WORKSHOP_MAX_SIZE: u64 = 4 << 30;
+
+/// Default for [`Builder::set_write_buffer`].
+const WRITE_BUFFER: usize = 64 * 1024;
+
+/// How many entries [`GmaError::OverSizeLimit`] lists.
+const LARGEST_REPORTED: usize = 5;
+
+/// Where an entry's content comes from, for [`Builder::add_entry`].
+pub enum EntrySource {
+    /// Content held in memory.
+    Bytes(Vec<u8>),
+    /// A file opened and streamed by every write, so its content is never held
+    /// in memory. The size is taken when the entry is added.
+    Path(PathBuf),
+    /// This many bytes streamed from a reader by the first write.
+    Reader(u64, Box<dyn Read + Send>),
+}
+
+/// How far a write has got, passed to the [`Builder::set_progress`] callback.
+#[derive(Clone, Copy, Debug)]
+pub struct WriteProgress<'a> {
+    /// Position of the entry being written, in write order.
+    pub index: usize,
+    pub name: &'a str,
+    /// Archive bytes written so far, header and table included.
+    pub written: u64,
+    /// Size of the whole archive.
+    pub total: u64,
+}
+
+type ProgressFn = Box<dyn Fn(&WriteProgress<'_>) + Send + Sync>;
+type ClockFn = Box<dyn Fn() -> u64 + Send + Sync>;
+
+/// Content read at write time rather than held in the entry.
+enum LazySource {
+    /// Taken by the first write.
+    Reader(Mutex<Option<Box<dyn Read + Send>>>),
+    /// Opened by every write.
+    Path(PathBuf),
+}
 
 /// Builder for writing `.gma` archives.
 ///
 /// Collects metadata + entries
 /// and writes them to a writer with `write_to`.
+///
+/// Setters and `add_*`/`file_from_*` methods return the builder (fallible ones
+/// inside their `Result`), so calls chain:
+/// `Builder::new(..).set_author(..).add_dir(..)?.write_to_path(..)`.
 pub struct Builder {
     name: String,
     steam_id64: i64,
     author: String,
     description: String,
     entries: Vec<GMAFile>,
+    /// Deferred content of each entry, by position; `None` if it's in `content`.
+    sources: Vec<Option<LazySource>>,
+    rewrites: Vec<RewriteRule>,
+    max_size: Option<u64>,
+    version: i8,
+    timestamp: Option<u64>,
+    addon_version: i32,
+    entry_crcs: bool,
+    archive_crc: bool,
+    deterministic: bool,
+    gmad_order: bool,
+    normalize_names: bool,
+    threads: usize,
+    required_content: Vec<String>,
+    progress: Option<ProgressFn>,
+    write_buffer: usize,
+    cancel: Option<Arc<AtomicBool>>,
+    clock: Option<ClockFn>,
 }
 
 impl Builder {
@@ -28,97 +109,1265 @@ impl Builder {
             author: "unknown".into(),
             description: String::new(),
             entries: Vec::with_capacity(capacity),
+            sources: Vec::with_capacity(capacity),
+            rewrites: Vec::new(),
+            max_size: None,
+            version: VERSION,
+            timestamp: None,
+            addon_version: 1,
+            entry_crcs: true,
+            archive_crc: false,
+            deterministic: false,
+            gmad_order: false,
+            normalize_names: false,
+            threads: 1,
+            required_content: Vec::new(),
+            progress: None,
+            write_buffer: WRITE_BUFFER,
+            cancel: None,
+            clock: None,
         }
     }
 
-    pub fn set_description(&mut self, desc: impl Into<String>) {
+    /// A builder holding `entries` under the header fields of `info`, e.g. a
+    /// [`GmaArchive`](crate::GmaArchive)'s `info()` and `into_entries()` for a
+    /// read, modify, write round trip.
+    ///
+    /// Keeps the format and addon versions (an out-of-range version falls back
+    /// to [`VERSION`]) and the required content, but not the timestamp. Each
+    /// entry's size is taken from its `content`.
+    pub fn from_entries(info: &AddonInfo, entries: Vec<GMAFile>) -> Self {
+        let mut b = Self::new_with_capacity(info.name.clone(), info.steam_id64, entries.len());
+        b.set_description(info.description.clone());
+        b.set_author(info.author.clone());
+        let _ = b.set_target_version(info.version);
+        b.set_addon_version(info.addon_version);
+        b.set_required_content(info.required_content.clone());
+        b.extend(entries);
+        b
+    }
+
+    /// Parse an existing archive into a builder, to edit and write it again.
+    ///
+    /// Like [`from_entries`](Self::from_entries), but the original timestamp is
+    /// kept too; [`clear_timestamp`](Self::clear_timestamp) stamps the rewrite
+    /// with the current time instead.
+    pub fn from_gma<R: Read>(reader: R) -> Result<Self, GmaError> {
+        let archive = crate::read_with(reader, &ReadOptions::default())?;
+        let info = archive.info().clone();
+        let mut b = Self::from_entries(&info, archive.into_entries());
+        b.set_timestamp(info.timestamp);
+        Ok(b)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The metadata this builder will write; `timestamp` is `None` unless one
+    /// was set.
+    pub fn metadata(&self) -> AddonMetadata {
+        let mut m = AddonMetadata {
+            title: self.name.clone(),
+            author: self.author.clone(),
+            steam_id64: self.steam_id64,
+            timestamp: self.timestamp,
+            version: self.version,
+            ..AddonMetadata::default()
+        };
+        m.set_gma_description(&self.description);
+        m
+    }
+
+    /// Replace the header fields with `metadata`; the description is written
+    /// as [`AddonMetadata::gma_description`]. Fails if the version is out of
+    /// range, changing nothing.
+    pub fn set_metadata(&mut self, metadata: &AddonMetadata) -> Result<&mut Self, GmaError> {
+        self.set_target_version(metadata.version)?;
+        self.name = metadata.title.clone();
+        self.description = metadata.gma_description();
+        self.author = metadata.author.clone();
+        self.steam_id64 = metadata.steam_id64;
+        self.timestamp = metadata.timestamp;
+        Ok(self)
+    }
+
+    /// The packer's id from [`new`](Self::new) or
+    /// [`set_steam_id`](Self::set_steam_id), to format as `STEAM_0:Y:Z` etc.
+    pub fn steam_id(&self) -> SteamId {
+        SteamId(self.steam_id64 as u64)
+    }
+
+    /// Replace the packer's id with one in any form [`SteamId::parse`] accepts,
+    /// e.g. `STEAM_0:1:1234`, `[U:1:2469]` or a profile link.
+    pub fn set_steam_id(&mut self, id: &str) -> Result<&mut Self, GmaError> {
+        self.steam_id64 = id.parse::<SteamId>()?.0 as i64;
+        Ok(self)
+    }
+
+    pub fn set_description(&mut self, desc: impl Into<String>) -> &mut Self {
         self.description = desc.into();
+        self
     }
 
-    pub fn set_author(&mut self, author: impl Into<String>) {
+    pub fn set_author(&mut self, author: impl Into<String>) -> &mut Self {
         self.author = author.into();
+        self
+    }
+
+    /// Refuse to write archives larger than `limit` bytes, e.g.
+    /// [`WORKSHOP_MAX_SIZE`].
+    ///
+    /// Checked against the projected size before anything is written, failing
+    /// with [`GmaError::OverSizeLimit`] and the largest entries, and again while
+    /// writing as a safety net.
+    pub fn set_max_size(&mut self, limit: u64) -> &mut Self {
+        self.max_size = Some(limit);
+        self
+    }
+
+    /// Format version to emit, [`MIN_VERSION`] to [`VERSION`] (the default).
+    ///
+    /// Meant for re-creating legacy archives faithfully and for test corpora for
+    /// other parsers; the game reads all of them. Versions 2 and 3 are laid out
+    /// the same, version 1 has no required-content list.
+    pub fn set_target_version(&mut self, version: i8) -> Result<&mut Self, GmaError> {
+        if !(MIN_VERSION..=VERSION).contains(&version) {
+            return Err(GmaError::InvalidVersion(version));
+        }
+        self.version = version;
+        Ok(self)
+    }
+
+    /// Header timestamp (Unix seconds) to write instead of the current time, so the
+    /// same inputs always produce the same bytes.
+    pub fn set_timestamp(&mut self, unix_secs: u64) -> &mut Self {
+        self.timestamp = Some(unix_secs);
+        self
+    }
+
+    /// Where the header timestamp (Unix seconds) comes from when none is set
+    /// with [`set_timestamp`](Self::set_timestamp), instead of the system clock;
+    /// called once per write.
+    pub fn set_clock(&mut self, clock: impl Fn() -> u64 + Send + Sync + 'static) -> &mut Self {
+        self.clock = Some(Box::new(clock));
+        self
+    }
+
+    /// The header's required-content list. Empty by default, as gmad writes it;
+    /// version 1 archives can't hold one, so writing them fails if it's set.
+    pub fn set_required_content(&mut self, items: Vec<String>) -> &mut Self {
+        self.required_content = items;
+        self
+    }
+
+    /// Go back to stamping the header with the time of each write.
+    pub fn clear_timestamp(&mut self) -> &mut Self {
+        self.timestamp = None;
+        self
+    }
+
+    /// The header's addon version field. The game ignores it; defaults to 1.
+    pub fn set_addon_version(&mut self, version: i32) -> &mut Self {
+        self.addon_version = version;
+        self
+    }
+
+    /// Compute each entry's CRC-32 for the metadata table, as gmad does. On by
+    /// default; turning it off writes 0s and skips a pass over the content.
+    ///
+    /// Entries from [`file_from_reader`](Self::file_from_reader) are only
+    /// checksummed by [`write_to_seekable`](Self::write_to_seekable);
+    /// [`EntrySource::Path`] files are read once more for it.
+    pub fn set_entry_crcs(&mut self, enabled: bool) -> &mut Self {
+        self.entry_crcs = enabled;
+        self
+    }
+
+    /// End the archive with a CRC-32 of everything before it, like gmad, instead
+    /// of a 0. Readers verify it before accepting it.
+    pub fn set_archive_crc(&mut self, enabled: bool) -> &mut Self {
+        self.archive_crc = enabled;
+        self
+    }
+
+    /// Make the output depend only on the builder's contents: the timestamp
+    /// defaults to 0 instead of the current time and entries are written in
+    /// [gmad order](Self::set_gmad_order), so adding the same files in any order
+    /// gives the same bytes. An explicit [`set_timestamp`](Self::set_timestamp)
+    /// still wins.
+    pub fn set_deterministic(&mut self, enabled: bool) -> &mut Self {
+        self.deterministic = enabled;
+        self
+    }
+
+    /// Write entries sorted the way gmad sorts them: by lowercased name, compared
+    /// byte by byte. Off by default, which keeps insertion order.
+    ///
+    /// Only the written order changes; the builder's entries stay as added.
+    pub fn set_gmad_order(&mut self, enabled: bool) -> &mut Self {
+        self.gmad_order = enabled;
+        self
+    }
+
+    /// Lowercase names and turn `\` into `/` as entries are added, giving the
+    /// form gmad writes and the game looks up. Applied before rewrite rules, so
+    /// those match the normalized name; affects files added after the call.
+    pub fn set_normalize_names(&mut self, enabled: bool) -> &mut Self {
+        self.normalize_names = enabled;
+        self
+    }
+
+    /// Number of worker threads used to checksum in-memory entries before the
+    /// (serial) write. `0` means one per available core. Defaults to 1.
+    pub fn set_threads(&mut self, threads: usize) -> &mut Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Size of the writes made to the output, 64 KiB by default. The header and
+    /// table go out as one buffer and small entries are batched up to this
+    /// size, so metadata-heavy addons don't pay a call per entry; entries
+    /// streamed from a reader or file are copied in chunks of it. Values under
+    /// 1 KiB are raised to that.
+    pub fn set_write_buffer(&mut self, bytes: usize) -> &mut Self {
+        self.write_buffer = bytes.max(1024);
+        self
+    }
+
+    /// Call `f` as each entry's content is written, and after every chunk (see
+    /// [`set_write_buffer`](Self::set_write_buffer)) of entries streamed from a
+    /// reader or file, e.g. to drive a progress bar.
+    /// Only the 4-byte trailer follows the last call.
+    pub fn set_progress(
+        &mut self,
+        f: impl Fn(&WriteProgress<'_>) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.progress = Some(Box::new(f));
+        self
+    }
+
+    /// Abort writes with [`GmaError::Cancelled`] once `flag` is set, e.g. from a
+    /// UI thread.
+    ///
+    /// Checked before each entry and each streamed chunk. What was written so far
+    /// is left in the writer, except that
+    /// [`write_to_path_atomic`](Self::write_to_path_atomic) removes its
+    /// temporary file and leaves the target untouched.
+    pub fn set_cancel_flag(&mut self, flag: Arc<AtomicBool>) -> &mut Self {
+        self.cancel = Some(flag);
+        self
     }
 
-    pub fn file_from_bytes(&mut self, name: impl Into<String>, bytes: Vec<u8>) {
-        let name = name.into();
-        let size = bytes.len() as i64;
+    /// Register a rule that renames entries as they are added.
+    ///
+    /// Rules are tried in registration order and the first match wins; they only
+    /// affect files added after the rule.
+    pub fn add_rewrite_rule(&mut self, rule: RewriteRule) -> &mut Self {
+        self.rewrites.push(rule);
+        self
+    }
+
+    pub fn file_from_bytes(&mut self, name: impl Into<String>, bytes: Vec<u8>) -> &mut Self {
+        let size = bytes.len() as u64;
+        self.push_entry(name.into(), size, bytes, None);
+        self
+    }
+
+    /// Add an entry of `size` bytes whose content is streamed from `reader`
+    /// during the write rather than buffered here.
+    ///
+    /// The reader is consumed by the first write; writing the builder again
+    /// fails. A reader ending before `size` bytes fails the write with
+    /// [`GmaError::TruncatedEntry`], one with more than `size` bytes with
+    /// [`GmaError::EntryOverrun`].
+    pub fn file_from_reader(
+        &mut self,
+        name: impl Into<String>,
+        size: u64,
+        reader: impl Read + Send + 'static,
+    ) -> &mut Self {
+        let source = Mutex::new(Some(Box::new(reader) as Box<dyn Read + Send>));
+        self.push_entry(
+            name.into(),
+            size,
+            Vec::new(),
+            Some(LazySource::Reader(source)),
+        );
+        self
+    }
+
+    /// Add an entry from any [`EntrySource`]. With [`EntrySource::Path`] only
+    /// the file's size is read now, so packing large content takes little
+    /// memory; the write fails if the file has changed size by then.
+    pub fn add_entry(
+        &mut self,
+        name: impl Into<String>,
+        source: EntrySource,
+    ) -> io::Result<&mut Self> {
+        Ok(match source {
+            EntrySource::Bytes(bytes) => self.file_from_bytes(name, bytes),
+            EntrySource::Reader(size, reader) => self.file_from_reader(name, size, reader),
+            EntrySource::Path(path) => {
+                let size = file_size(&path)?;
+                self.push_entry(name.into(), size, Vec::new(), Some(LazySource::Path(path)));
+                self
+            }
+        })
+    }
+
+    fn push_entry(
+        &mut self,
+        name: String,
+        size: u64,
+        content: Vec<u8>,
+        source: Option<LazySource>,
+    ) {
+        let name = if self.normalize_names {
+            name.replace('\\', "/").to_ascii_lowercase()
+        } else {
+            name
+        };
+        let name = rewrite::rewrite(&self.rewrites, name);
         self.entries.push(GMAFile {
+            name_bytes: name.clone().into_bytes(),
             name,
-            content: bytes,
+            content,
             size,
+            crc: 0,
+            offset: 0,
         });
+        self.sources.push(source);
     }
 
-    pub fn file_from_string(&mut self, name: impl Into<String>, content: impl Into<String>) {
+    pub fn file_from_string(
+        &mut self,
+        name: impl Into<String>,
+        content: impl Into<String>,
+    ) -> &mut Self {
         self.file_from_bytes(name, content.into().into_bytes());
+        self
+    }
+
+    /// Remove the entry called `name` (ASCII case-insensitively, with `\\` and
+    /// `/` alike, as the game looks files up); `false` if there was none.
+    pub fn remove_file(&mut self, name: &str) -> bool {
+        let Some(i) = self.position(name) else {
+            return false;
+        };
+        self.entries.remove(i);
+        self.sources.remove(i);
+        true
+    }
+
+    /// Swap in new content for the entry called `name`, keeping its name and
+    /// position; `false` if there is no such entry.
+    pub fn replace_file(&mut self, name: &str, bytes: Vec<u8>) -> bool {
+        let Some(i) = self.position(name) else {
+            return false;
+        };
+        let e = &mut self.entries[i];
+        e.size = bytes.len() as u64;
+        e.content = bytes;
+        self.sources[i] = None;
+        true
+    }
+
+    /// Rename an entry, keeping its position; the new name is used as given,
+    /// without normalization or rewrite rules. Fails with
+    /// [`GmaError::DuplicateEntry`] if another entry already has the new name;
+    /// `Ok(false)` if there is no entry called `from`.
+    pub fn rename_file(&mut self, from: &str, to: impl Into<String>) -> Result<bool, GmaError> {
+        let to = to.into();
+        let Some(i) = self.position(from) else {
+            return Ok(false);
+        };
+        if self.position(&to).is_some_and(|j| j != i) {
+            return Err(GmaError::DuplicateEntry(to));
+        }
+        let e = &mut self.entries[i];
+        e.name_bytes = to.clone().into_bytes();
+        e.name = to;
+        Ok(true)
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.entries.iter().position(|e| same_path(&e.name, name))
+    }
+
+    /// Read the file at `path` from disk and add it as `name`.
+    pub fn add_file_from_path(
+        &mut self,
+        name: impl Into<String>,
+        path: impl AsRef<Path>,
+    ) -> io::Result<&mut Self> {
+        let bytes = fs::read(path)?;
+        Ok(self.file_from_bytes(name, bytes))
+    }
+
+    /// Add each file as an [`EntrySource::Path`] entry; nothing is added if any
+    /// of them can't be looked up.
+    pub(crate) fn add_paths(&mut self, files: Vec<(String, PathBuf)>) -> io::Result<()> {
+        let mut sized = Vec::with_capacity(files.len());
+        for (name, path) in files {
+            let size = file_size(&path)?;
+            sized.push((name, size, path));
+        }
+        for (name, size, path) in sized {
+            self.push_entry(name, size, Vec::new(), Some(LazySource::Path(path)));
+        }
+        Ok(())
+    }
+
+    /// Add `licenses` as the [`LICENSES_FILE`] entry, after checking that every
+    /// path it lists matches a file added so far.
+    pub fn add_licenses(&mut self, licenses: &Licenses) -> Result<&mut Self, GmaError> {
+        if let Some(path) = licenses.unmatched_paths(&self.entries).first() {
+            return Err(GmaError::InvalidLicenses(format!(
+                "\"{path}\" matches no file in the addon"
+            )));
+        }
+        Ok(self.file_from_string(LICENSES_FILE, licenses.to_json()))
+    }
+
+    /// Dry run: check the metadata and entries added so far for every problem
+    /// that would fail the write or break the addon, without writing.
+    ///
+    /// Reports an empty archive, empty, duplicate (ignoring case and separators,
+    /// as the game looks them up), unsafe and non-whitelisted names, entries and
+    /// an archive over the [`set_max_size`](Self::set_max_size) limit, and NUL
+    /// bytes in header strings and names. Groups of entries with byte-identical
+    /// content (common with copy-pasted materials) are listed separately; they
+    /// mount fine but take space. Entries read at write time aren't compared.
+    pub fn validate(&self) -> ValidationReport {
+        let strings = [
+            ("name", self.name.as_str()),
+            ("description", self.description.as_str()),
+            ("author", self.author.as_str()),
+        ];
+        let required = self
+            .required_content
+            .iter()
+            .map(|s| ("required content", s.as_str()));
+        validate::check(
+            strings.into_iter().chain(required),
+            &self.entries,
+            self.max_size,
+            self.predicted_len(),
+            self.duplicate_contents(),
+        )
+    }
+
+    fn duplicate_contents(&self) -> Vec<DuplicateContent> {
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        let mut by_content: HashMap<&[u8], usize> = HashMap::new();
+        for (i, e) in self.entries.iter().enumerate() {
+            if self.sources[i].is_some() || e.content.is_empty() {
+                continue;
+            }
+            let group = *by_content.entry(&e.content).or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+            groups[group].push(i);
+        }
+        groups
+            .into_iter()
+            .filter(|g| g.len() > 1)
+            .map(|g| DuplicateContent {
+                size: self.entries[g[0]].content.len() as u64,
+                names: g
+                    .into_iter()
+                    .map(|i| self.entries[i].name.clone())
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Remove entries that repeat an earlier one under another spelling of the
+    /// same path (ASCII case, `\` or `/`) with byte-identical content, keeping
+    /// the first. Returns how many were removed; same-path entries with
+    /// different content are left for [`validate`](Self::validate) to report.
+    pub fn drop_duplicate_spellings(&mut self) -> usize {
+        let mut first: HashMap<String, usize> = HashMap::new();
+        let mut keep = Vec::with_capacity(self.entries.len());
+        for (i, e) in self.entries.iter().enumerate() {
+            let key = e.name.replace('\\', "/").to_ascii_lowercase();
+            let drop = match first.get(&key) {
+                Some(&j) => {
+                    self.sources[i].is_none()
+                        && self.sources[j].is_none()
+                        && e.content == self.entries[j].content
+                }
+                None => {
+                    first.insert(key, i);
+                    false
+                }
+            };
+            keep.push(!drop);
+        }
+        let before = self.entries.len();
+        let mut keep_iter = keep.iter();
+        self.entries.retain(|_| *keep_iter.next().unwrap());
+        let mut keep_iter = keep.iter();
+        self.sources.retain(|_| *keep_iter.next().unwrap());
+        before - self.entries.len()
+    }
+
+    /// Names of the entries added so far that gmad's whitelist refuses (see
+    /// [`GMAD_WHITELIST`](crate::GMAD_WHITELIST)), in insertion order. Case and
+    /// `\` separators are ignored, as in [`MountCheck`](crate::MountCheck).
+    pub fn validate_whitelist(&self) -> Vec<&str> {
+        self.entries
+            .iter()
+            .map(|e| e.name.as_str())
+            .filter(|name| !is_whitelisted(&name.replace('\\', "/").to_ascii_lowercase()))
+            .collect()
+    }
+
+    /// Read files from disk on up to `threads` workers (`0` = one per core) and add
+    /// them in the order given, so the archive doesn't depend on scheduling.
+    ///
+    /// Nothing is added if any file fails to read.
+    pub fn add_files_parallel<N, P>(
+        &mut self,
+        files: impl IntoIterator<Item = (N, P)>,
+        threads: usize,
+    ) -> io::Result<&mut Self>
+    where
+        N: Into<String>,
+        P: AsRef<Path> + Sync,
+    {
+        let (names, paths): (Vec<N>, Vec<P>) = files.into_iter().unzip();
+        let contents = parallel::map_indexed(paths.len(), threads, |i| fs::read(&paths[i]));
+        let contents = contents.into_iter().collect::<io::Result<Vec<_>>>()?;
+        self.entries.reserve(names.len());
+        self.sources.reserve(names.len());
+        for (name, bytes) in names.into_iter().zip(contents) {
+            self.file_from_bytes(name, bytes);
+        }
+        Ok(self)
+    }
+
+    /// Exact number of bytes [`write_to`](Self::write_to) will produce, from the
+    /// header, the metadata table and every entry's (declared) size. Nothing is
+    /// read or checksummed.
+    pub fn predicted_len(&self) -> u64 {
+        // Index, name, size, crc per entry; then the 0 terminator
+        let table: u64 = self.entries.iter().map(table_entry_len).sum::<u64>() + 4;
+        let content: u64 = self.entries.iter().map(|e| e.size).sum();
+        // Trailing marker after the content
+        self.table_offset() + table + content + 4
+    }
+
+    /// Offset of the metadata table from the start of the archive.
+    fn table_offset(&self) -> u64 {
+        // Magic, version, steam id, timestamp, required content (v2+)
+        let required = match self.version {
+            1 => 0,
+            _ => {
+                self.required_content
+                    .iter()
+                    .map(|s| cstr_len(s))
+                    .sum::<u64>()
+                    + 1
+            }
+        };
+        let header = 4 + 1 + 8 + 8 + required;
+        let strings = cstr_len(&self.name) + cstr_len(&self.description) + cstr_len(&self.author);
+        // Addon version before the table
+        header + strings + 4
     }
 
     /// Write the archive to a writer.
-    pub fn write_to<W: Write>(&self, mut w: W) -> Result<(), GmaError> {
-        let mut bw = BufWriter::new(&mut w);
+    pub fn write_to<W: Write>(&self, w: W) -> Result<(), GmaError> {
+        self.write_checked(w, self.unix_time(), false).map(drop)
+    }
+
+    /// The archive in memory, allocated once at its
+    /// [predicted size](Self::predicted_len). Written as by
+    /// [`write_to_seekable`](Self::write_to_seekable), so streamed entries get
+    /// their CRCs too.
+    pub fn write_to_vec(&self) -> Result<Vec<u8>, GmaError> {
+        let mut out = self.output_buffer()?;
+        self.write_to_seekable(io::Cursor::new(&mut out))?;
+        Ok(out)
+    }
+
+    /// An empty buffer with room for the whole archive, or an
+    /// [`OutOfMemory`](io::ErrorKind::OutOfMemory) error if that can't be had.
+    fn output_buffer(&self) -> Result<Vec<u8>, GmaError> {
+        let len = usize::try_from(self.predicted_len()).unwrap_or(usize::MAX);
+        let mut out = Vec::new();
+        out.try_reserve_exact(len)
+            .map_err(|_| io::Error::from(io::ErrorKind::OutOfMemory))?;
+        Ok(out)
+    }
+
+    /// Write the archive to a file at `path`, creating missing parent directories
+    /// and replacing any existing file, then fsync it.
+    pub fn write_to_path(&self, path: impl AsRef<Path>) -> Result<(), GmaError> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = fs::File::create(path)?;
+        self.write_to(&mut file)?;
+        file.sync_all()?;
+        Ok(())
+    }
+
+    /// Like [`write_to_path`](Self::write_to_path), but write to a temporary
+    /// file in the same directory and rename it over `path` once it's synced,
+    /// so readers only ever see the old file or the complete new one. The
+    /// temporary file is removed if writing fails.
+    pub fn write_to_path_atomic(&self, path: impl AsRef<Path>) -> Result<(), GmaError> {
+        let path = path.as_ref();
+        let Some(file_name) = path.file_name() else {
+            return Err(
+                io::Error::new(io::ErrorKind::InvalidInput, "path has no file name").into(),
+            );
+        };
+        let dir = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        fs::create_dir_all(dir)?;
+        // Unique per call, so concurrent writes to the same path in one
+        // process don't share a temporary file
+        static NEXT_TMP: AtomicU64 = AtomicU64::new(0);
+        let tmp = dir.join(format!(
+            ".{}.{}.{}.tmp",
+            file_name.to_string_lossy(),
+            std::process::id(),
+            NEXT_TMP.fetch_add(1, Ordering::Relaxed)
+        ));
+        // Never write through a file that's already there, e.g. a planted symlink
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&tmp)?;
+        let res = (|| {
+            self.write_to(&mut file)?;
+            file.sync_all()?;
+            fs::rename(&tmp, path)?;
+            transaction::sync_dir(dir)?;
+            Ok(())
+        })();
+        if res.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+        res
+    }
+
+    /// Write the archive LZMA-compressed (see [`compress_lzma`](crate::compress_lzma)),
+    /// ready for a Workshop upload. The archive is built in memory first.
+    #[cfg(feature = "lzma")]
+    pub fn write_to_lzma<W: Write>(&self, w: W) -> Result<(), GmaError> {
+        let mut archive = self.output_buffer()?;
+        self.write_to(&mut archive)?;
+        crate::compress_lzma(&archive, w)?;
+        Ok(())
+    }
+
+    /// Like [`write_to`](Self::write_to), but CRC-32s every entry (streamed ones
+    /// included, whatever [`set_entry_crcs`](Self::set_entry_crcs) says) as its
+    /// content is written, then seeks back over the metadata table to fill the
+    /// checksums in.
+    ///
+    /// The archive is written starting at the current position; on success the
+    /// writer is left at its end.
+    pub fn write_to_seekable<W: Write + Seek>(&self, mut w: W) -> Result<(), GmaError> {
+        let start = w.stream_position()?;
+        let timestamp = self.unix_time();
+        let crcs = self.write_checked(&mut w, timestamp, true)?;
+        let end = w.stream_position()?;
+
+        let mut pos = start + self.table_offset();
+        for (e, crc) in self.ordered().zip(&crcs) {
+            // The CRC is the last field of the entry's table record.
+            pos += table_entry_len(e);
+            w.seek(SeekFrom::Start(pos - 4))?;
+            w.write_all(&crc.to_le_bytes())?;
+        }
+        if self.archive_crc {
+            // Patching the table changed bytes the trailer covers
+            w.seek(SeekFrom::Start(end - 4))?;
+            w.write_all(&self.archive_crc_of(timestamp, &crcs, &crcs)?.to_le_bytes())?;
+        }
+        w.seek(SeekFrom::Start(end))?;
+        w.flush()?;
+        Ok(())
+    }
+
+    /// Write with the size limit applied; returns the content CRCs (in written order) if `hash` is set.
+    fn write_checked<W: Write>(
+        &self,
+        w: W,
+        timestamp: u64,
+        hash: bool,
+    ) -> Result<Vec<u32>, GmaError> {
+        // File indices are u32 and 0 ends the table
+        if self.entries.len() > u32::MAX as usize {
+            return Err(GmaError::TooManyEntries {
+                limit: u32::MAX as usize,
+            });
+        }
+        let Some(limit) = self.max_size else {
+            return self.write_archive(w, timestamp, hash);
+        };
+        let size = self.predicted_len();
+        if size > limit {
+            return Err(GmaError::OverSizeLimit {
+                size,
+                limit,
+                largest: self.largest_entries(LARGEST_REPORTED),
+            });
+        }
+
+        let mut guard = SizeGuard {
+            inner: w,
+            written: 0,
+            limit,
+        };
+        let res = self.write_archive(&mut guard, timestamp, hash);
+        if guard.written > limit {
+            return Err(GmaError::ArchiveTooLarge {
+                size: guard.written,
+                limit,
+            });
+        }
+        res
+    }
+
+    fn check_cancel(&self) -> io::Result<()> {
+        match &self.cancel {
+            Some(flag) if flag.load(Ordering::Relaxed) => Err(io::Error::other(Cancelled)),
+            _ => Ok(()),
+        }
+    }
+
+    /// The `n` biggest entries as name and size, largest first.
+    fn largest_entries(&self, n: usize) -> Vec<(String, u64)> {
+        let mut sizes: Vec<(&str, u64)> = self
+            .entries
+            .iter()
+            .map(|e| (e.name.as_str(), e.size))
+            .collect();
+        sizes.sort_by_key(|&(_, size)| std::cmp::Reverse(size));
+        sizes
+            .into_iter()
+            .take(n)
+            .map(|(name, size)| (name.to_owned(), size))
+            .collect()
+    }
+
+    fn unix_time(&self) -> u64 {
+        if self.deterministic {
+            return self.timestamp.unwrap_or(0);
+        }
+        self.timestamp.unwrap_or_else(|| match &self.clock {
+            Some(clock) => clock(),
+            // A clock set before 1970 stamps 0 rather than failing the write
+            None => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+        })
+    }
+
+    /// Positions of the entries in the order they're written.
+    fn order(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.entries.len()).collect();
+        if self.deterministic || self.gmad_order {
+            order.sort_by_cached_key(|&i| self.entries[i].name.to_ascii_lowercase());
+        }
+        order
+    }
+
+    fn ordered(&self) -> impl Iterator<Item = &GMAFile> {
+        self.order().into_iter().map(|i| &self.entries[i])
+    }
+
+    /// Header and metadata table (with its terminator), listing `crcs` in
+    /// written order.
+    fn head(&self, timestamp: u64, crcs: &[u32]) -> Result<Vec<u8>, GmaError> {
+        let header = Header {
+            version: self.version,
+            steam_id64: self.steam_id64,
+            timestamp,
+            required_content: &self.required_content,
+            name: &self.name,
+            description: &self.description,
+            author: &self.author,
+            addon_version: self.addon_version,
+        };
+        let entries = self.ordered().zip(crcs);
+        header.encode(entries.map(|(e, crc)| (e.name.as_str(), e.size, *crc)))
+    }
+
+    /// The trailer gmad appends: CRC-32 of the head and every entry's content.
+    fn archive_crc_of(
+        &self,
+        timestamp: u64,
+        table_crcs: &[u32],
+        content_crcs: &[u32],
+    ) -> Result<u32, GmaError> {
+        let mut crc = crc32(&self.head(timestamp, table_crcs)?);
+        for (e, c) in self.ordered().zip(content_crcs) {
+            crc = crc::combine(crc, *c, e.size);
+        }
+        Ok(crc)
+    }
+
+    fn write_archive<W: Write>(
+        &self,
+        mut w: W,
+        timestamp: u64,
+        hash: bool,
+    ) -> Result<Vec<u32>, GmaError> {
+        let mut bw = BufWriter::with_capacity(self.write_buffer, &mut w);
+        // The archive CRC is assembled from the entries'
+        let hash = hash || self.archive_crc;
+
+        let order = self.order();
+
+        // CRCs of buffered content and files are known before the table is written
+        let buffered_crcs = parallel::map_indexed(order.len(), self.threads, |pos| {
+            let i = order[pos];
+            match &self.sources[i] {
+                _ if !(self.entry_crcs || hash) => Ok(0),
+                None => Ok(crc32(&self.entries[i].content)),
+                Some(LazySource::Path(path)) => file_crc(path, || self.check_cancel()),
+                Some(LazySource::Reader(_)) => Ok(0),
+            }
+        });
+        let buffered_crcs = buffered_crcs.into_iter().collect::<io::Result<Vec<_>>>()?;
+        let table_crcs = if self.entry_crcs {
+            buffered_crcs.clone()
+        } else {
+            vec![0; self.entries.len()]
+        };
+        let head = self.head(timestamp, &table_crcs)?;
+        bw.write_all(&head)?;
+
+        // File contents
+        let total = self.predicted_len();
+        let mut written = head.len() as u64;
+        let mut crcs = Vec::with_capacity(if hash { self.entries.len() } else { 0 });
+        for (pos, &i) in order.iter().enumerate() {
+            let e = &self.entries[i];
+            self.check_cancel()?;
+            let mut crc = Crc32::new();
+            let report = |written| {
+                if let Some(progress) = &self.progress {
+                    progress(&WriteProgress {
+                        index: pos,
+                        name: &e.name,
+                        written,
+                        total,
+                    });
+                }
+            };
+            match &self.sources[i] {
+                None => {
+                    bw.write_all(&e.content)?;
+                    written += e.content.len() as u64;
+                    report(written);
+                    if hash {
+                        crcs.push(buffered_crcs[pos]);
+                    }
+                    continue;
+                }
+                Some(source) => {
+                    let expected = e.size;
+                    let mut reader = open_source(source, &e.name, expected)?;
+                    let read = copy_content(
+                        &mut reader,
+                        &mut bw,
+                        self.write_buffer,
+                        expected,
+                        hash.then_some(&mut crc),
+                        |n| {
+                            written += n;
+                            report(written);
+                            self.check_cancel()
+                        },
+                    )?;
+                    if read != expected {
+                        return Err(GmaError::TruncatedEntry {
+                            name: e.name.clone(),
+                            read,
+                            expected,
+                        });
+                    }
+                    if has_more(&mut reader)? {
+                        return Err(GmaError::EntryOverrun {
+                            name: e.name.clone(),
+                            declared: expected,
+                        });
+                    }
+                }
+            }
+            if hash {
+                crcs.push(crc.finish());
+            }
+        }
+
+        // End of file marker, or the archive CRC in its place
+        let trailer = if self.archive_crc {
+            self.archive_crc_of(timestamp, &table_crcs, &crcs)?
+        } else {
+            0
+        };
+        bw.write_all(&trailer.to_le_bytes())?;
+
+        bw.flush()?;
+        Ok(crcs)
+    }
+}
+
+/// An unnamed builder (steam id 0) holding `files`.
+impl From<Vec<GMAFile>> for Builder {
+    fn from(files: Vec<GMAFile>) -> Self {
+        files.into_iter().collect()
+    }
+}
+
+/// An unnamed builder (steam id 0) holding the files, e.g. a filtered
+/// `archive.into_entries()`.
+impl<C: Into<Vec<u8>>> FromIterator<GMAFile<C>> for Builder {
+    fn from_iter<I: IntoIterator<Item = GMAFile<C>>>(files: I) -> Self {
+        let mut b = Builder::new(String::new(), 0);
+        b.extend(files);
+        b
+    }
+}
+
+/// Add each file as by [`Builder::file_from_bytes`], sized by its `content`.
+/// Borrowed content (e.g. from [`SliceArchive::files`](crate::SliceArchive::files))
+/// is copied.
+impl<C: Into<Vec<u8>>> Extend<GMAFile<C>> for Builder {
+    fn extend<I: IntoIterator<Item = GMAFile<C>>>(&mut self, files: I) {
+        let files = files.into_iter();
+        self.entries.reserve(files.size_hint().0);
+        self.sources.reserve(files.size_hint().0);
+        for f in files {
+            self.file_from_bytes(f.name, f.content.into());
+        }
+    }
+}
+
+/// Header fields as they are written.
+pub(crate) struct Header<'a> {
+    pub(crate) version: i8,
+    pub(crate) steam_id64: i64,
+    pub(crate) timestamp: u64,
+    pub(crate) required_content: &'a [String],
+    pub(crate) name: &'a str,
+    pub(crate) description: &'a str,
+    pub(crate) author: &'a str,
+    pub(crate) addon_version: i32,
+}
+
+impl Header<'_> {
+    /// The header followed by the metadata table (with its terminator) for
+    /// `entries`, given as name, size and CRC.
+    pub(crate) fn encode<'e>(
+        &self,
+        entries: impl Iterator<Item = (&'e str, u64, u32)>,
+    ) -> Result<Vec<u8>, GmaError> {
+        let mut out = Vec::new();
 
         // Header
-        bw.write_all(HEADER)?;
+        out.write_all(HEADER)?;
 
         // Version
-        bw.write_all(&VERSION.to_le_bytes())?;
+        out.write_all(&self.version.to_le_bytes())?;
 
         // SteamID64
-        bw.write_all(&self.steam_id64.to_le_bytes())?;
+        out.write_all(&self.steam_id64.to_le_bytes())?;
 
         // Timestamp
-        let unix_time = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        bw.write_all(&unix_time.to_le_bytes())?;
+        out.write_all(&self.timestamp.to_le_bytes())?;
 
-        // Required content (unused)
-        bw.write_all(&[0u8])?;
+        // Required content, ended by an empty string (not in version 1)
+        if self.version > 1 {
+            for item in self.required_content {
+                if item.is_empty() {
+                    return Err(invalid_input("required content can't be an empty string"));
+                }
+                write_cstring(&mut out, "required content", item)?;
+            }
+            out.write_all(&[0u8])?;
+        } else if !self.required_content.is_empty() {
+            return Err(invalid_input("version 1 archives have no required content"));
+        }
 
         // Addon strings
-        write_cstring(&mut bw, &self.name)?;
-        write_cstring(&mut bw, &self.description)?;
-        write_cstring(&mut bw, &self.author)?;
+        write_cstring(&mut out, "name", self.name)?;
+        write_cstring(&mut out, "description", self.description)?;
+        write_cstring(&mut out, "author", self.author)?;
 
-        // Version (unused, int32 = 1)
-        bw.write_all(&1i32.to_le_bytes())?;
+        // Addon version (unused by the game)
+        out.write_all(&self.addon_version.to_le_bytes())?;
 
         // Metadata for each file entry
-        for (i, e) in self.entries.iter().enumerate() {
+        for (i, (name, size, crc)) in entries.enumerate() {
             // File index (1-based)
-            bw.write_all(&(i as u32 + 1).to_le_bytes())?;
+            out.write_all(&(i as u32 + 1).to_le_bytes())?;
             // Name
-            write_cstring(&mut bw, &e.name)?;
+            write_cstring(&mut out, "entry name", name)?;
             // Size (int64)
-            bw.write_all(&(e.content.len() as i64).to_le_bytes())?;
-            // CRC (unused, write 0)
-            bw.write_all(&0u32.to_le_bytes())?;
+            out.write_all(&(size as i64).to_le_bytes())?;
+            // CRC (0 if disabled or not known yet)
+            out.write_all(&crc.to_le_bytes())?;
         }
 
         // End of metadata
-        bw.write_all(&0u32.to_le_bytes())?;
+        out.write_all(&0u32.to_le_bytes())?;
+        Ok(out)
+    }
+}
 
-        // File contents
-        for e in &self.entries {
-            bw.write_all(&e.content)?;
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(data);
+    crc.finish()
+}
+
+fn file_size(path: &Path) -> io::Result<u64> {
+    let meta = fs::metadata(path)?;
+    if !meta.is_file() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("not a file: {}", path.display()),
+        ));
+    }
+    Ok(meta.len())
+}
+
+fn file_crc(path: &Path, check_cancel: impl Fn() -> io::Result<()>) -> io::Result<u32> {
+    let mut crc = Crc32::new();
+    copy_content(
+        &mut fs::File::open(path)?,
+        &mut io::sink(),
+        WRITE_BUFFER,
+        u64::MAX,
+        Some(&mut crc),
+        |_| check_cancel(),
+    )?;
+    Ok(crc.finish())
+}
+
+/// The reader for a deferred entry's content, checking that a file still has
+/// the size it was added with.
+fn open_source(source: &LazySource, name: &str, size: u64) -> io::Result<Box<dyn Read + Send>> {
+    match source {
+        LazySource::Reader(reader) => reader
+            .lock()
+            .unwrap()
+            .take()
+            .ok_or_else(|| io::Error::other(format!("content of {name} was already written"))),
+        LazySource::Path(path) => {
+            let file = fs::File::open(path)?;
+            if file.metadata()?.len() != size {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{} changed size since it was added", path.display()),
+                ));
+            }
+            Ok(Box::new(file))
         }
+    }
+}
 
-        // End of file marker
-        bw.write_all(&0u32.to_le_bytes())?;
+/// Copy up to `size` bytes in chunks of `chunk`, feeding them to `crc` on the
+/// way and calling `on_chunk` with each one's length; returns the count.
+fn copy_content<R: Read, W: Write>(
+    r: &mut R,
+    w: &mut W,
+    chunk: usize,
+    size: u64,
+    mut crc: Option<&mut Crc32>,
+    mut on_chunk: impl FnMut(u64) -> io::Result<()>,
+) -> io::Result<u64> {
+    let mut r = r.take(size);
+    let mut buf = vec![0u8; chunk];
+    let mut copied = 0;
+    loop {
+        let n = match r.read(&mut buf) {
+            Ok(0) => return Ok(copied),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        w.write_all(&buf[..n])?;
+        if let Some(crc) = crc.as_deref_mut() {
+            crc.update(&buf[..n]);
+        }
+        copied += n as u64;
+        on_chunk(n as u64)?;
+    }
+}
 
-        bw.flush()?;
-        Ok(())
+/// Whether `r` has anything left, consuming at most one byte.
+fn has_more<R: Read>(r: &mut R) -> io::Result<bool> {
+    let mut probe = [0u8; 1];
+    loop {
+        match r.read(&mut probe) {
+            Ok(n) => return Ok(n > 0),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
     }
 }
 
-fn write_cstring<W: Write>(mut w: W, s: &str) -> Result<(), GmaError> {
+#[inline]
+fn cstr_len(s: &str) -> u64 {
+    s.len() as u64 + 1
+}
+
+/// Bytes taken by one entry's record in the metadata table.
+#[inline]
+fn table_entry_len(e: &GMAFile) -> u64 {
+    // Index, name, size, crc
+    4 + cstr_len(&e.name) + 8 + 4
+}
+
+/// Fails writes once more than `limit` bytes would have gone through.
+struct SizeGuard<W> {
+    inner: W,
+    written: u64,
+    limit: u64,
+}
+
+impl<W: Write> Write for SizeGuard<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written + buf.len() as u64 > self.limit {
+            self.written += buf.len() as u64;
+            return Err(io::Error::other("archive size limit exceeded"));
+        }
+        let n = self.inner.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn invalid_input(msg: &str) -> GmaError {
+    io::Error::new(io::ErrorKind::InvalidInput, msg).into()
+}
+
+fn write_cstring<W: Write>(mut w: W, field: &'static str, s: &str) -> Result<(), GmaError> {
     if s.bytes().any(|b| b == 0) {
-        return Err(
-            io::Error::new(io::ErrorKind::InvalidInput, "string contains null byte").into(),
-        );
+        return Err(GmaError::NulInString { field });
     }
     w.write_all(s.as_bytes())?;
     w.write_all(&[0u8])?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn streamed_entry_must_match_its_size() {
+        let mut b = Builder::new("t", 1);
+        b.file_from_reader("lua/b.lua", 3, Cursor::new(b"abc".to_vec()));
+        let out = b.write_to_vec().unwrap();
+        assert_eq!(crate::read(&out[..]).unwrap()[0].content, b"abc");
+
+        let mut b = Builder::new("t", 1);
+        b.set_max_size(1000)
+            .file_from_reader("lua/b.lua", 3, Cursor::new(b"abcdef".to_vec()));
+        let err = b.write_to_vec().unwrap_err();
+        assert!(matches!(err, GmaError::EntryOverrun { declared: 3, .. }));
+
+        let mut b = Builder::new("t", 1);
+        b.file_from_reader("lua/b.lua", 3, Cursor::new(b"ab".to_vec()));
+        let err = b.write_to_vec().unwrap_err();
+        assert!(matches!(
+            err,
+            GmaError::TruncatedEntry {
+                read: 2,
+                expected: 3,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn seekable_write_patches_entry_and_archive_crcs() {
+        let mut b = Builder::new("t", 1);
+        b.set_archive_crc(true)
+            .set_entry_crcs(false)
+            .file_from_bytes("lua/a.lua", b"print(1)".to_vec())
+            .file_from_reader("lua/b.lua", 8, Cursor::new(b"print(2)".to_vec()));
+
+        // Written after some unrelated bytes, which the patches must skip
+        let mut out = Cursor::new(b"junk".to_vec());
+        out.seek(SeekFrom::End(0)).unwrap();
+        b.write_to_seekable(&mut out).unwrap();
+        let out = out.into_inner();
+        let gma = &out[4..];
+
+        let mut opts = crate::ReadOptions::default();
+        opts.set_hash_archive(true);
+        opts.set_verify_crcs(true);
+        let archive = crate::read_with(gma, &opts).unwrap();
+        assert_eq!(archive.entries()[0].crc, crc32(b"print(1)"));
+        assert_eq!(archive.entries()[1].crc, crc32(b"print(2)"));
+
+        let (body, trailer) = gma.split_at(gma.len() - 4);
+        assert_eq!(trailer, crc32(body).to_le_bytes());
+        assert_eq!(archive.archive_crc(), Some(crc32(body)));
+    }
+
+    #[test]
+    fn concurrent_atomic_writes_use_separate_temp_files() {
+        let dir = std::env::temp_dir().join(format!("gma-lite-atomic-{}", std::process::id()));
+        let path = dir.join("out.gma");
+        std::thread::scope(|scope| {
+            for i in 0..8u8 {
+                let path = &path;
+                scope.spawn(move || {
+                    let mut b = Builder::new("t", 1);
+                    b.file_from_bytes("lua/a.lua", vec![i; 100_000]);
+                    b.write_to_path_atomic(path).unwrap();
+                });
+            }
+        });
+        let files = crate::read(fs::File::open(&path).unwrap()).unwrap();
+        assert_eq!(files[0].content.len(), 100_000);
+        let leftovers: Vec<_> = fs::read_dir(&dir).unwrap().collect();
+        assert_eq!(leftovers.len(), 1, "{leftovers:?}");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn edits_find_names_like_the_game() {
+        let mut b = Builder::new("t", 1);
+        b.file_from_bytes("lua/a.lua", b"1".to_vec())
+            .file_from_bytes("lua/b.lua", b"2".to_vec())
+            .file_from_bytes("lua/c.lua", b"3".to_vec());
+        assert!(b.remove_file("LUA\\A.lua"));
+        assert!(b.replace_file("lua\\b.lua", b"two".to_vec()));
+        assert!(matches!(
+            b.rename_file("lua/c.lua", "LUA\\B.LUA"),
+            Err(GmaError::DuplicateEntry(_))
+        ));
+        assert!(b.rename_file("lua\\c.lua", "lua/C.lua").unwrap());
+        let files = crate::read(&b.write_to_vec().unwrap()[..]).unwrap();
+        let names: Vec<_> = files
+            .iter()
+            .map(|f| (f.name.as_str(), &f.content[..]))
+            .collect();
+        assert_eq!(names, [("lua/b.lua", &b"two"[..]), ("lua/C.lua", b"3")]);
+    }
+}
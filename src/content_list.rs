@@ -0,0 +1,117 @@
+use std::io::{self, Write};
+
+use crate::Builder;
+
+/// One addon a server should mount.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContentItem {
+    pub title: String,
+    /// Archive file name, as placed in the server's `garrysmod/addons/`.
+    pub file: String,
+    /// Workshop item id, once the addon has been published.
+    pub workshop_id: Option<u64>,
+}
+
+/// Server content list: the set of built archives (and their Workshop ids)
+/// a dedicated server needs to mount.
+///
+/// Renders to the `workshop.vdf` KeyValues format hosting panels read, and to
+/// a `resource.AddWorkshop` Lua file so clients download the content too.
+#[derive(Clone, Debug, Default)]
+pub struct ContentList {
+    items: Vec<ContentItem>,
+}
+
+impl ContentList {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(
+        &mut self,
+        title: impl Into<String>,
+        file: impl Into<String>,
+        workshop_id: Option<u64>,
+    ) {
+        self.items.push(ContentItem {
+            title: title.into(),
+            file: file.into(),
+            workshop_id,
+        });
+    }
+
+    /// Record an archive built from `builder` and written to `file`.
+    pub fn add_built(
+        &mut self,
+        builder: &Builder,
+        file: impl Into<String>,
+        workshop_id: Option<u64>,
+    ) {
+        self.add(builder.name(), file, workshop_id);
+    }
+
+    pub fn items(&self) -> &[ContentItem] {
+        &self.items
+    }
+
+    /// Write a `workshop.vdf`:
+    /// published items under `"WorkshopItems"`, id → title, and
+    /// unpublished ones under `"LocalAddons"`, file → title.
+    pub fn write_workshop_vdf<W: Write>(&self, mut w: W) -> io::Result<()> {
+        writeln!(w, "\"WorkshopItems\"")?;
+        writeln!(w, "{{")?;
+        for item in &self.items {
+            if let Some(id) = item.workshop_id {
+                writeln!(w, "\t\"{id}\"\t\"{}\"", vdf_escape(&item.title))?;
+            }
+        }
+        writeln!(w, "}}")?;
+
+        writeln!(w, "\"LocalAddons\"")?;
+        writeln!(w, "{{")?;
+        for item in self.items.iter().filter(|i| i.workshop_id.is_none()) {
+            writeln!(
+                w,
+                "\t\"{}\"\t\"{}\"",
+                vdf_escape(&item.file),
+                vdf_escape(&item.title)
+            )?;
+        }
+        writeln!(w, "}}")?;
+        Ok(())
+    }
+
+    /// Write a `lua/autorun/server/workshop.lua` that makes clients download
+    /// every published item.
+    pub fn write_resource_lua<W: Write>(&self, mut w: W) -> io::Result<()> {
+        for item in &self.items {
+            if let Some(id) = item.workshop_id {
+                writeln!(
+                    w,
+                    "resource.AddWorkshop(\"{id}\") -- {}",
+                    lua_comment(&item.title)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn vdf_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn lua_comment(s: &str) -> String {
+    s.replace(['\r', '\n'], " ")
+}
@@ -0,0 +1,119 @@
+//! CRC-32 (IEEE 802.3), the checksum gmad uses for entries and archives.
+
+/// The reflected IEEE polynomial.
+const POLY: u32 = 0xEDB8_8320;
+
+const TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 { POLY ^ (c >> 1) } else { c >> 1 };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+};
+
+/// Incremental CRC-32 hasher.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Crc32(u32);
+
+impl Crc32 {
+    #[inline]
+    pub(crate) fn new() -> Self {
+        Self(!0)
+    }
+
+    pub(crate) fn update(&mut self, bytes: &[u8]) {
+        let mut c = self.0;
+        for &b in bytes {
+            c = TABLE[((c ^ b as u32) & 0xFF) as usize] ^ (c >> 8);
+        }
+        self.0 = c;
+    }
+
+    #[inline]
+    pub(crate) fn finish(self) -> u32 {
+        !self.0
+    }
+}
+
+/// CRC-32 of `a ++ b` from `crc(a)`, `crc(b)` and `b`'s length, without
+/// re-reading either (zlib's `crc32_combine`).
+#[cfg(feature = "std")]
+pub(crate) fn combine(crc_a: u32, crc_b: u32, len_b: u64) -> u32 {
+    mul_mod_p(x_pow_8n(len_b), crc_a) ^ crc_b
+}
+
+/// `a * b` modulo the CRC polynomial, in its bit-reflected representation.
+#[cfg(feature = "std")]
+fn mul_mod_p(a: u32, mut b: u32) -> u32 {
+    let mut m = 1u32 << 31;
+    let mut p = 0;
+    while m != 0 {
+        if a & m != 0 {
+            p ^= b;
+        }
+        m >>= 1;
+        b = if b & 1 != 0 { (b >> 1) ^ POLY } else { b >> 1 };
+    }
+    p
+}
+
+/// `x^(8n)` modulo the CRC polynomial: the effect of appending `n` zero bytes.
+#[cfg(feature = "std")]
+fn x_pow_8n(mut n: u64) -> u32 {
+    // x^(2^k) for k = 3 (one byte), squared at each step
+    let mut sq = 1u32 << (31 - 8);
+    let mut p = 1u32 << 31;
+    while n != 0 {
+        if n & 1 != 0 {
+            p = mul_mod_p(sq, p);
+        }
+        sq = mul_mod_p(sq, sq);
+        n >>= 1;
+    }
+    p
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn crc32(bytes: &[u8]) -> u32 {
+        let mut c = Crc32::new();
+        c.update(bytes);
+        c.finish()
+    }
+
+    #[test]
+    fn check_value() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(
+            crc32(b"The quick brown fox jumps over the lazy dog"),
+            0x414F_A339
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn combine_matches_hashing_the_concatenation() {
+        use alloc::vec::Vec;
+
+        let data: Vec<u8> = (0..1000u32).map(|i| (i * 31 % 251) as u8).collect();
+        for split in [0, 1, 7, 64, 500, 999, 1000] {
+            let (a, b) = data.split_at(split);
+            assert_eq!(
+                combine(crc32(a), crc32(b), b.len() as u64),
+                crc32(&data),
+                "split at {split}"
+            );
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use std::fs;
+use std::path::Path;
+
+use crate::{AddonJson, Builder, DirOptions, GmaError};
+
+/// The project file at the root of an addon folder.
+const ADDON_JSON: &str = "addon.json";
+
+impl Builder {
+    /// A builder for the addon folder `dir`, set up the way `gmad create` packs
+    /// it.
+    ///
+    /// `addon.json` supplies the title and the description, type and tags (as
+    /// gmad's JSON description); its `ignore` patterns apply on top of gmad's
+    /// defaults, and the file itself is left out. Like gmad, names are
+    /// lowercased, entries sorted, checksummed and followed by the archive CRC,
+    /// the author is `"Author Name"` and the steam id 0.
+    ///
+    /// Fails if [`AddonJson::validate`] does, and with
+    /// [`GmaError::NotWhitelisted`] listing every file gmad would refuse.
+    pub fn from_addon_dir(dir: impl AsRef<Path>) -> Result<Self, GmaError> {
+        let dir = dir.as_ref();
+        let json = AddonJson::parse(&fs::read_to_string(dir.join(ADDON_JSON))?)?;
+        json.validate()?;
+
+        let mut options = DirOptions::new();
+        options.add_addon_json(&json);
+
+        let mut b = Builder::new(json.title.clone(), 0);
+        b.set_description(json.gma_description());
+        b.set_author("Author Name");
+        b.set_normalize_names(true);
+        b.set_gmad_order(true);
+        b.set_archive_crc(true);
+        b.add_dir(dir, &options)?;
+
+        let refused = b.validate_whitelist();
+        if !refused.is_empty() {
+            let refused = refused.into_iter().map(str::to_owned).collect();
+            return Err(GmaError::NotWhitelisted(refused));
+        }
+        Ok(b)
+    }
+}
+
+/// Pack the addon folder `dir` into `out`, like `gmad create -folder dir -out out`
+/// (see [`Builder::from_addon_dir`]). `out` is replaced atomically.
+pub fn create_from_addon_dir(dir: impl AsRef<Path>, out: impl AsRef<Path>) -> Result<(), GmaError> {
+    Builder::from_addon_dir(dir)?.write_to_path_atomic(out)
+}
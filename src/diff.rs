@@ -0,0 +1,388 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::GMAFile;
+use crate::sha::{self, Sha256};
+
+/// Lines of context around each change in [`Change::Text`] hunks.
+const CONTEXT: usize = 3;
+
+/// Beyond this many line pairs, a changed text entry is diffed as one
+/// replace-everything hunk instead of being aligned.
+const MAX_ALIGN_CELLS: usize = 1 << 22;
+
+/// Extensions diffed line by line (when both sides are valid UTF-8).
+const TEXT_EXTENSIONS: &[&str] = &[
+    "lua",
+    "txt",
+    "vmt",
+    "json",
+    "cfg",
+    "properties",
+    "vdf",
+    "res",
+    "md",
+    "ini",
+    "xml",
+];
+
+/// What changed between two versions of an addon. See [`diff_entries`].
+///
+/// Displays as a unified diff.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct ArchiveDiff {
+    /// Changed entries only, sorted by name.
+    pub changes: Vec<EntryDiff>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EntryDiff {
+    pub name: String,
+    pub change: Change,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change {
+    Added {
+        size: u64,
+    },
+    Removed {
+        size: u64,
+    },
+    /// Line-level changes of a text entry.
+    Text {
+        hunks: Vec<Hunk>,
+    },
+    /// A changed binary entry, summarized.
+    Binary {
+        old_size: u64,
+        new_size: u64,
+        old_sha256: [u8; 32],
+        new_sha256: [u8; 32],
+    },
+}
+
+/// A run of changes with surrounding context; line numbers are 1-based.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hunk {
+    pub old_start: usize,
+    pub old_len: usize,
+    pub new_start: usize,
+    pub new_len: usize,
+    pub lines: Vec<DiffLine>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DiffLine {
+    Context(String),
+    Removed(String),
+    Added(String),
+}
+
+/// Compare two entry lists. Paths are matched ASCII case-insensitively, as the
+/// game mounts them; unchanged entries are left out.
+pub fn diff_entries(old: &[GMAFile], new: &[GMAFile]) -> ArchiveDiff {
+    let index = |files: &[GMAFile]| -> BTreeMap<String, usize> {
+        files
+            .iter()
+            .enumerate()
+            .map(|(i, f)| (f.name.to_ascii_lowercase(), i))
+            .collect()
+    };
+    let (old_ix, new_ix) = (index(old), index(new));
+
+    let mut changes = Vec::new();
+    for (key, &i) in &old_ix {
+        let o = &old[i];
+        let Some(&j) = new_ix.get(key) else {
+            changes.push(EntryDiff {
+                name: o.name.clone(),
+                change: Change::Removed {
+                    size: o.content.len() as u64,
+                },
+            });
+            continue;
+        };
+        let n = &new[j];
+        if o.content != n.content {
+            changes.push(EntryDiff {
+                name: n.name.clone(),
+                change: content_change(&n.name, &o.content, &n.content),
+            });
+        }
+    }
+    for (key, &j) in &new_ix {
+        if !old_ix.contains_key(key) {
+            changes.push(EntryDiff {
+                name: new[j].name.clone(),
+                change: Change::Added {
+                    size: new[j].content.len() as u64,
+                },
+            });
+        }
+    }
+    changes.sort_by(|a, b| {
+        a.name
+            .to_ascii_lowercase()
+            .cmp(&b.name.to_ascii_lowercase())
+    });
+    ArchiveDiff { changes }
+}
+
+fn content_change(name: &str, old: &[u8], new: &[u8]) -> Change {
+    let ext = name.rsplit_once('.').map(|(_, e)| e.to_ascii_lowercase());
+    let texty = ext.is_some_and(|e| TEXT_EXTENSIONS.contains(&e.as_str()));
+    if texty && let (Ok(a), Ok(b)) = (std::str::from_utf8(old), std::str::from_utf8(new)) {
+        return Change::Text {
+            hunks: diff_lines(a, b),
+        };
+    }
+    let hash = |data: &[u8]| {
+        let mut h = Sha256::new();
+        h.update(data);
+        h.finish()
+    };
+    Change::Binary {
+        old_size: old.len() as u64,
+        new_size: new.len() as u64,
+        old_sha256: hash(old),
+        new_sha256: hash(new),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Line diff via LCS over the part between the common prefix and suffix.
+fn diff_lines(old: &str, new: &str) -> Vec<Hunk> {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (am, bm) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut ops = vec![Op::Equal; prefix];
+    if am.len().saturating_mul(bm.len()) > MAX_ALIGN_CELLS {
+        ops.extend(std::iter::repeat_n(Op::Delete, am.len()));
+        ops.extend(std::iter::repeat_n(Op::Insert, bm.len()));
+    } else {
+        // lcs[i][j] = LCS length of am[i..] and bm[j..]
+        let w = bm.len() + 1;
+        let mut lcs = vec![0u32; (am.len() + 1) * w];
+        for i in (0..am.len()).rev() {
+            for j in (0..bm.len()).rev() {
+                lcs[i * w + j] = if am[i] == bm[j] {
+                    lcs[(i + 1) * w + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * w + j].max(lcs[i * w + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < am.len() || j < bm.len() {
+            if i < am.len() && j < bm.len() && am[i] == bm[j] {
+                ops.push(Op::Equal);
+                i += 1;
+                j += 1;
+            } else if i < am.len() && (j == bm.len() || lcs[(i + 1) * w + j] >= lcs[i * w + j + 1])
+            {
+                ops.push(Op::Delete);
+                i += 1;
+            } else {
+                ops.push(Op::Insert);
+                j += 1;
+            }
+        }
+    }
+    ops.extend(std::iter::repeat_n(Op::Equal, suffix));
+    hunks(&ops, &a, &b)
+}
+
+/// Group an edit script into hunks with [`CONTEXT`] lines around changes.
+fn hunks(ops: &[Op], a: &[&str], b: &[&str]) -> Vec<Hunk> {
+    // Line positions before each op
+    let mut pos = Vec::with_capacity(ops.len());
+    let (mut i, mut j) = (0, 0);
+    for op in ops {
+        pos.push((i, j));
+        match op {
+            Op::Equal => (i, j) = (i + 1, j + 1),
+            Op::Delete => i += 1,
+            Op::Insert => j += 1,
+        }
+    }
+
+    let mut out = Vec::new();
+    let mut k = 0;
+    while let Some(first) = ops[k..].iter().position(|op| *op != Op::Equal) {
+        let start = (k + first).saturating_sub(CONTEXT);
+        // Extend while the next change is within 2 * CONTEXT equal lines
+        let mut end = k + first;
+        let mut equal_run = 0;
+        let mut t = end;
+        while t < ops.len() {
+            if ops[t] == Op::Equal {
+                equal_run += 1;
+                if equal_run > 2 * CONTEXT {
+                    break;
+                }
+            } else {
+                equal_run = 0;
+                end = t;
+            }
+            t += 1;
+        }
+        let stop = (end + 1 + CONTEXT).min(ops.len());
+
+        let lines = (start..stop)
+            .map(|t| {
+                let (i, j) = pos[t];
+                match ops[t] {
+                    Op::Equal => DiffLine::Context(a[i].to_owned()),
+                    Op::Delete => DiffLine::Removed(a[i].to_owned()),
+                    Op::Insert => DiffLine::Added(b[j].to_owned()),
+                }
+            })
+            .collect::<Vec<_>>();
+        let old_len = lines
+            .iter()
+            .filter(|l| !matches!(l, DiffLine::Added(_)))
+            .count();
+        let new_len = lines
+            .iter()
+            .filter(|l| !matches!(l, DiffLine::Removed(_)))
+            .count();
+        let (i, j) = pos[start];
+        out.push(Hunk {
+            old_start: i + 1,
+            old_len,
+            new_start: j + 1,
+            new_len,
+            lines,
+        });
+        k = stop;
+    }
+    out
+}
+
+impl fmt::Display for ArchiveDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for e in &self.changes {
+            match &e.change {
+                Change::Added { size } => writeln!(f, "Added {} ({size} bytes)", e.name)?,
+                Change::Removed { size } => writeln!(f, "Removed {} ({size} bytes)", e.name)?,
+                Change::Binary {
+                    old_size,
+                    new_size,
+                    old_sha256,
+                    new_sha256,
+                } => writeln!(
+                    f,
+                    "Binary {} changed: {old_size} -> {new_size} bytes, sha256 {} -> {}",
+                    e.name,
+                    sha::hex(old_sha256),
+                    sha::hex(new_sha256)
+                )?,
+                Change::Text { hunks } => {
+                    writeln!(f, "--- a/{}", e.name)?;
+                    writeln!(f, "+++ b/{}", e.name)?;
+                    for h in hunks {
+                        writeln!(
+                            f,
+                            "@@ -{},{} +{},{} @@",
+                            h.old_start, h.old_len, h.new_start, h.new_len
+                        )?;
+                        for line in &h.lines {
+                            match line {
+                                DiffLine::Context(l) => writeln!(f, " {l}")?,
+                                DiffLine::Removed(l) => writeln!(f, "-{l}")?,
+                                DiffLine::Added(l) => writeln!(f, "+{l}")?,
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str, content: &[u8]) -> GMAFile {
+        GMAFile {
+            name: name.to_owned(),
+            size: content.len() as u64,
+            content: content.to_vec(),
+            ..GMAFile::default()
+        }
+    }
+
+    #[test]
+    fn text_entry_is_a_unified_diff() {
+        let old = [file("lua/x.lua", b"a\nb\nc\nd\ne\nf\ng\nh\ni\n")];
+        let new = [file("LUA/X.lua", b"a\nb\nc\nd\nE\nf\ng\nh\ni\n")];
+        let diff = diff_entries(&old, &new);
+        let [EntryDiff { change, .. }] = &diff.changes[..] else {
+            panic!("{diff:?}");
+        };
+        let Change::Text { hunks } = change else {
+            panic!("{change:?}");
+        };
+        assert_eq!(hunks.len(), 1);
+        assert_eq!(
+            diff.to_string(),
+            "--- a/LUA/X.lua\n\
+             +++ b/LUA/X.lua\n\
+             @@ -2,7 +2,7 @@\n \
+             b\n \
+             c\n \
+             d\n\
+             -e\n\
+             +E\n \
+             f\n \
+             g\n \
+             h\n"
+        );
+    }
+
+    #[test]
+    fn binary_entry_is_summarized() {
+        let old = [file("materials/x.png", b""), file("gone.txt", b"bye")];
+        let new = [file("materials/x.png", b"abc"), file("new.txt", b"hi")];
+        assert_eq!(
+            diff_entries(&old, &new).to_string(),
+            "Removed gone.txt (3 bytes)\n\
+             Binary materials/x.png changed: 0 -> 3 bytes, sha256 \
+             e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855 -> \
+             ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad\n\
+             Added new.txt (2 bytes)\n"
+        );
+    }
+
+    #[test]
+    fn invalid_utf8_text_is_binary() {
+        let old = [file("lua/x.lua", b"a\n")];
+        let new = [file("lua/x.lua", b"\xff\n")];
+        assert!(matches!(
+            diff_entries(&old, &new).changes[0].change,
+            Change::Binary {
+                old_size: 2,
+                new_size: 2,
+                ..
+            }
+        ));
+        assert_eq!(diff_entries(&old, &old), ArchiveDiff::default());
+    }
+}
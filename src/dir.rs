@@ -0,0 +1,238 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "json")]
+use crate::AddonJson;
+use crate::{Builder, IgnoreRules};
+
+/// Which files [`Builder::add_dir`] picks up.
+///
+/// Patterns are [`IgnoreRules`] wildcards, matched against the archive path
+/// (relative to the root, `/`-separated) or the file name.
+#[derive(Clone, Debug)]
+pub struct DirOptions {
+    include: IgnoreRules,
+    exclude: IgnoreRules,
+    lazy: bool,
+    /// Leave out the root's `addon.json`.
+    skip_addon_json: bool,
+}
+
+impl Default for DirOptions {
+    fn default() -> Self {
+        Self {
+            include: IgnoreRules::empty(),
+            exclude: IgnoreRules::gmad_default(),
+            lazy: false,
+            skip_addon_json: false,
+        }
+    }
+}
+
+impl DirOptions {
+    /// Every file except gmad's default ignores.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only add files matching at least one include pattern. With none, every
+    /// file not excluded is added.
+    pub fn add_include(&mut self, pattern: impl Into<String>) {
+        self.include.add(pattern);
+    }
+
+    /// Leave out files matching `pattern`, on top of the current exclusions.
+    pub fn add_exclude(&mut self, pattern: impl Into<String>) {
+        self.exclude.add(pattern);
+    }
+
+    /// Replace the exclusions, e.g. with [`IgnoreRules::empty`] to keep gmad's
+    /// default ignores in.
+    pub fn set_exclude(&mut self, rules: IgnoreRules) {
+        self.exclude = rules;
+    }
+
+    /// Add files as [`EntrySource::Path`](crate::EntrySource::Path) entries, read during the write instead
+    /// of up front, so the tree never has to fit in memory. Off by default.
+    pub fn set_lazy(&mut self, lazy: bool) {
+        self.lazy = lazy;
+    }
+
+    /// Also leave out what `addon.json` lists under `ignore`, and the
+    /// `addon.json` file itself. Only the one at the root is the project file;
+    /// an `addon.json` further down is packed like any other file.
+    #[cfg(feature = "json")]
+    pub fn add_addon_json(&mut self, json: &AddonJson) {
+        self.skip_addon_json = true;
+        self.exclude.extend(json.ignore.iter().cloned());
+    }
+
+    /// Why `path` is left out, or `None` if it's picked up.
+    fn exclusion(&self, path: &str) -> Option<ExclusionReason> {
+        if self.skip_addon_json && path.eq_ignore_ascii_case("addon.json") {
+            return Some(ExclusionReason::AddonJson);
+        }
+        if let Some(pattern) = self.exclude.matching_pattern(path) {
+            return Some(ExclusionReason::Excluded(pattern.to_owned()));
+        }
+        // "Ignored" by the include set means matched
+        if self.include.patterns().is_empty() || self.include.is_ignored(path) {
+            None
+        } else {
+            Some(ExclusionReason::NotIncluded)
+        }
+    }
+}
+
+/// A file under the root that [`Builder::add_dir_report`] passed over.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Exclusion {
+    /// Path relative to the root, `/`-separated.
+    pub path: String,
+    pub reason: ExclusionReason,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExclusionReason {
+    /// Matched this exclude pattern.
+    Excluded(String),
+    /// Matched none of the include patterns.
+    NotIncluded,
+    /// The project's own `addon.json` (see [`DirOptions::add_addon_json`]).
+    AddonJson,
+}
+
+impl fmt::Display for Exclusion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.reason {
+            ExclusionReason::Excluded(pattern) => {
+                write!(f, "{}: excluded by \"{pattern}\"", self.path)
+            }
+            ExclusionReason::NotIncluded => {
+                write!(f, "{}: matches no include pattern", self.path)
+            }
+            ExclusionReason::AddonJson => write!(f, "{}: the project file", self.path),
+        }
+    }
+}
+
+impl Builder {
+    /// Add every file under `root` that `options` selects, named by its path
+    /// relative to `root` with `/` separators, in sorted order.
+    ///
+    /// Symlinked directories aren't followed. Nothing is added if any file
+    /// fails to read (with [`DirOptions::set_lazy`], to be looked up).
+    pub fn add_dir(
+        &mut self,
+        root: impl AsRef<Path>,
+        options: &DirOptions,
+    ) -> io::Result<&mut Self> {
+        self.add_dir_report(root, options)?;
+        Ok(self)
+    }
+
+    /// Like [`add_dir`](Self::add_dir), returning the files left out and why,
+    /// in the same sorted order.
+    pub fn add_dir_report(
+        &mut self,
+        root: impl AsRef<Path>,
+        options: &DirOptions,
+    ) -> io::Result<Vec<Exclusion>> {
+        let mut files = Vec::new();
+        let mut excluded = Vec::new();
+        walk(
+            root.as_ref(),
+            String::new(),
+            options,
+            &mut files,
+            &mut excluded,
+        )?;
+        if options.lazy {
+            self.add_paths(files)?;
+        } else {
+            self.add_files_parallel(files, 1)?;
+        }
+        Ok(excluded)
+    }
+}
+
+fn walk(
+    dir: &Path,
+    prefix: String,
+    options: &DirOptions,
+    out: &mut Vec<(String, PathBuf)>,
+    excluded: &mut Vec<Exclusion>,
+) -> io::Result<()> {
+    let mut children = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    children.sort_by_key(|e| e.file_name());
+    for child in children {
+        let file_name = child.file_name();
+        let Some(file_name) = file_name.to_str() else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("non-UTF-8 file name: {}", child.path().display()),
+            ));
+        };
+        let name = format!("{prefix}{file_name}");
+        let kind = child.file_type()?;
+        if kind.is_dir() {
+            walk(&child.path(), format!("{name}/"), options, out, excluded)?;
+        } else if kind.is_file() || kind.is_symlink() && child.path().is_file() {
+            match options.exclusion(&name) {
+                None => out.push((name, child.path())),
+                Some(reason) => excluded.push(Exclusion { path: name, reason }),
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_root_addon_json_is_left_out() {
+        let root = std::env::temp_dir().join(format!("gma-lite-dir-{}", std::process::id()));
+        for (path, content) in [
+            ("addon.json", "{}"),
+            ("lua/addon.json", "{\"a\": 1}"),
+            ("lua/x.psd", ""),
+            ("lua/x.lua", "print(1)"),
+        ] {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        let json = AddonJson {
+            ignore: vec!["*.psd".into()],
+            ..AddonJson::default()
+        };
+        let mut options = DirOptions::new();
+        options.add_addon_json(&json);
+
+        let mut b = Builder::new("t", 1);
+        let excluded = b.add_dir_report(&root, &options).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        let files = crate::read(&b.write_to_vec().unwrap()[..]).unwrap();
+        let names: Vec<_> = files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["lua/addon.json", "lua/x.lua"]);
+        assert_eq!(
+            excluded,
+            [
+                Exclusion {
+                    path: "addon.json".into(),
+                    reason: ExclusionReason::AddonJson,
+                },
+                Exclusion {
+                    path: "lua/x.psd".into(),
+                    reason: ExclusionReason::Excluded("*.psd".into()),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,513 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use crate::limits::Limits;
+use crate::parallel;
+use crate::quota::{Quotas, Usage};
+use crate::wildcard;
+use crate::{EntryMeta, GmaError, GmaReader, is_safe_path, list_entries};
+
+/// How extraction treats path components Windows can't create: device names
+/// (`con`, `nul.lua`, `com1`, ...), trailing dots or spaces, and `<>"|?*` or
+/// control characters. Applied on every platform, so output doesn't depend on it.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum ReservedNames {
+    /// Fail with [`GmaError::ReservedName`].
+    #[default]
+    Error,
+    /// Rewrite the component: `_` after a device name (`con_.lua`), in place of
+    /// trailing dots/spaces and of invalid characters.
+    Escape,
+    /// Rewrite offending components with a custom function.
+    Rename(fn(&str) -> String),
+    /// Write names as they are.
+    Allow,
+}
+
+/// Writes archive entries out to a directory.
+///
+/// Every selected name is checked before the first file is written: unsafe
+/// paths and (by default) Windows-reserved names are errors, and so are two
+/// entries that would land on the same file on a case-insensitive filesystem.
+#[derive(Clone, Debug)]
+pub struct Extractor {
+    threads: usize,
+    limits: Option<Limits>,
+    reserved: ReservedNames,
+    quotas: Option<Quotas>,
+    /// Lowercased, `/`-separated patterns; empty selects everything.
+    only: Vec<String>,
+}
+
+impl Default for Extractor {
+    fn default() -> Self {
+        Self {
+            threads: 1,
+            limits: None,
+            reserved: ReservedNames::default(),
+            quotas: None,
+            only: Vec::new(),
+        }
+    }
+}
+
+impl Extractor {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of worker threads [`Extractor::extract_file`] uses.
+    ///
+    /// `0` means one per available core. Defaults to 1.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads;
+    }
+
+    /// Share `limits` with other extractions (and [`ExtractionPlan`](crate::ExtractionPlan)s)
+    /// to bound how many files and bytes are in use at once across all of them.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = Some(limits);
+    }
+
+    /// Choose how Windows-reserved names are handled. Defaults to [`ReservedNames::Error`].
+    pub fn set_reserved_names(&mut self, policy: ReservedNames) {
+        self.reserved = policy;
+    }
+
+    /// Enforce per-directory byte quotas. The archive's table is checked before
+    /// anything is written, failing with [`GmaError::QuotaExceeded`]; the same
+    /// breakdown is available up front from [`Usage::from_entries`].
+    pub fn set_quotas(&mut self, quotas: Quotas) {
+        self.quotas = Some(quotas);
+    }
+
+    /// Only extract entries matching one of `patterns`: gmad-style wildcards
+    /// (`*` crosses `/`, so `lua/*` or `lua/**` takes the whole tree; `?` is one
+    /// character), compared ASCII case-insensitively with `\\` read as `/`.
+    /// Returned paths and quotas cover only the selected entries.
+    pub fn set_only<S: AsRef<str>>(&mut self, patterns: &[S]) {
+        self.only = patterns
+            .iter()
+            .map(|p| p.as_ref().replace('\\', "/").to_ascii_lowercase())
+            .collect();
+    }
+
+    fn selects(&self, name: &str) -> bool {
+        if self.only.is_empty() {
+            return true;
+        }
+        let name = name.replace('\\', "/").to_ascii_lowercase();
+        self.only.iter().any(|p| wildcard::matches(p, &name))
+    }
+
+    /// Output path of every selected entry of `table`, `None` for the others.
+    /// Checking all names up front means a bad one fails the extraction
+    /// before anything is written.
+    ///
+    /// Two entries landing on the same file, even if only case-insensitively
+    /// (`lua/a.lua` and `LUA\\A.lua`), fail with [`GmaError::DuplicateEntry`]
+    /// for the later one instead of overwriting each other.
+    fn plan_paths(
+        &self,
+        dir: &Path,
+        table: &[EntryMeta],
+    ) -> Result<Vec<Option<PathBuf>>, GmaError> {
+        let mut seen = HashSet::new();
+        table
+            .iter()
+            .enumerate()
+            .map(|(i, e)| {
+                if !self.selects(&e.name) {
+                    return Ok(None);
+                }
+                let path = entry_path(dir, &e.name, i, self.reserved)?;
+                if !seen.insert(path.to_string_lossy().to_lowercase()) {
+                    return Err(GmaError::DuplicateEntry(e.name.clone()));
+                }
+                Ok(Some(path))
+            })
+            .collect()
+    }
+
+    fn check_quotas(&self, table: &[EntryMeta]) -> Result<(), GmaError> {
+        match &self.quotas {
+            Some(q) => q.check(&Usage::from_entries(
+                table.iter().filter(|e| self.selects(&e.name)),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Stream every entry of `reader` into `dir`. Returns the written paths.
+    pub fn extract<R: Read>(
+        &self,
+        reader: R,
+        dir: impl AsRef<Path>,
+    ) -> Result<Vec<PathBuf>, GmaError> {
+        let dir = dir.as_ref();
+        let mut gr = GmaReader::new(reader)?;
+        self.check_quotas(gr.entries())?;
+        let mut paths = self.plan_paths(dir, gr.entries())?.into_iter();
+        let mut written = Vec::with_capacity(gr.entries().len());
+        while let Some(mut entry) = gr.next_entry()? {
+            let Some(path) = paths.next().flatten() else {
+                continue;
+            };
+            let meta = entry.meta().clone();
+            let _file = self.limits.as_ref().map(Limits::acquire_file);
+            let _bytes = self.limits.as_ref().map(|l| l.acquire_bytes(meta.size));
+            write_entry(&path, &meta, &mut entry)?;
+            written.push(path);
+        }
+        Ok(written)
+    }
+
+    /// Extract the archive at `path` into `dir`.
+    ///
+    /// The metadata is parsed once, then entries are copied by up to
+    /// [`set_threads`](Extractor::set_threads) workers, each seeking in its own
+    /// handle to the file.
+    pub fn extract_file(
+        &self,
+        path: impl AsRef<Path>,
+        dir: impl AsRef<Path>,
+    ) -> Result<Vec<PathBuf>, GmaError> {
+        let path = path.as_ref();
+        let threads = parallel::thread_count(self.threads);
+        if threads <= 1 {
+            return self.extract(File::open(path)?, dir);
+        }
+        self.extract_parallel(|| File::open(path), dir)
+    }
+
+    /// Extract in parallel from any seekable source; `open` is called once per
+    /// worker to get an independent handle.
+    pub fn extract_parallel<F, R>(
+        &self,
+        open: F,
+        dir: impl AsRef<Path>,
+    ) -> Result<Vec<PathBuf>, GmaError>
+    where
+        F: Fn() -> io::Result<R> + Sync,
+        R: Read + Seek,
+    {
+        let dir = dir.as_ref();
+        let entries = list_entries(open()?)?;
+        self.check_quotas(&entries)?;
+        let planned = self.plan_paths(dir, &entries)?;
+        let (table, paths): (Vec<EntryMeta>, Vec<PathBuf>) = entries
+            .into_iter()
+            .zip(planned)
+            .filter_map(|(e, path)| Some((e, path?)))
+            .unzip();
+
+        let next = AtomicUsize::new(0);
+        let failure: Mutex<Option<GmaError>> = Mutex::new(None);
+        let work = |src: &mut R| -> Result<(), GmaError> {
+            loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let (Some(meta), Some(path)) = (table.get(i), paths.get(i)) else {
+                    return Ok(());
+                };
+                let _bytes = self.limits.as_ref().map(|l| l.acquire_bytes(meta.size));
+                copy_entry(src, meta, path)?;
+            }
+        };
+
+        thread::scope(|s| {
+            for _ in 0..parallel::thread_count(self.threads).min(table.len()) {
+                s.spawn(|| {
+                    // One file permit per worker covers its source handle and outputs.
+                    let _file = self.limits.as_ref().map(Limits::acquire_file);
+                    let res = open()
+                        .map_err(GmaError::from)
+                        .and_then(|mut src| work(&mut src));
+                    if let Err(e) = res {
+                        // Stop handing out work and keep the first error
+                        next.store(usize::MAX / 2, Ordering::Relaxed);
+                        failure.lock().unwrap().get_or_insert(e);
+                    }
+                });
+            }
+        });
+
+        match failure.into_inner().unwrap() {
+            Some(e) => Err(e),
+            None => Ok(paths),
+        }
+    }
+}
+
+pub(crate) fn copy_entry<R: Read + Seek>(
+    src: &mut R,
+    meta: &EntryMeta,
+    path: &Path,
+) -> Result<(), GmaError> {
+    src.seek(SeekFrom::Start(meta.offset))?;
+    write_entry(path, meta, src)
+}
+
+fn write_entry<R: Read>(path: &Path, meta: &EntryMeta, src: &mut R) -> Result<(), GmaError> {
+    let size = meta.size;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut out = BufWriter::new(File::create(path)?);
+    let copied = io::copy(&mut src.take(size), &mut out)?;
+    if copied != size {
+        return Err(meta.truncated(copied));
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Map entry `index`'s name onto `dir`, refusing anything that would escape it.
+pub(crate) fn entry_path(
+    dir: &Path,
+    name: &str,
+    index: usize,
+    reserved: ReservedNames,
+) -> Result<PathBuf, GmaError> {
+    if !is_safe_path(name) {
+        return Err(GmaError::UnsafePath {
+            name: name.to_owned(),
+            index,
+        });
+    }
+    let mut path = dir.to_path_buf();
+    for part in name.split(['/', '\\']) {
+        if matches!(part, "" | ".") {
+            continue;
+        }
+        if !is_reserved(part) {
+            path.push(part);
+            continue;
+        }
+        match reserved {
+            ReservedNames::Error => {
+                return Err(GmaError::ReservedName {
+                    name: name.to_owned(),
+                    index,
+                });
+            }
+            ReservedNames::Escape => path.push(escape_reserved(part)),
+            ReservedNames::Rename(rename) => path.push(rename(part)),
+            ReservedNames::Allow => path.push(part),
+        }
+    }
+    Ok(path)
+}
+
+const DEVICES: [&str; 22] = [
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8",
+    "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+fn is_device(part: &str) -> bool {
+    // `nul.lua` and `nul .txt` are the device too.
+    let stem = part.split('.').next().unwrap_or(part).trim_end_matches(' ');
+    DEVICES.iter().any(|d| stem.eq_ignore_ascii_case(d))
+}
+
+fn is_invalid_char(c: char) -> bool {
+    matches!(c, '<' | '>' | '"' | '|' | '?' | '*') || c.is_ascii_control()
+}
+
+/// Whether Windows would refuse (or silently alter) a path component.
+fn is_reserved(part: &str) -> bool {
+    is_device(part) || part.ends_with(['.', ' ']) || part.chars().any(is_invalid_char)
+}
+
+fn escape_reserved(part: &str) -> String {
+    let mut out: String = part
+        .chars()
+        .map(|c| if is_invalid_char(c) { '_' } else { c })
+        .collect();
+    let kept = out.trim_end_matches(['.', ' ']).len();
+    if kept < out.len() {
+        out.truncate(kept);
+        out.push('_');
+    }
+    if is_device(&out) {
+        let stem = out.find('.').unwrap_or(out.len());
+        out.insert(stem, '_');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Builder;
+
+    /// A fresh, empty directory under the system temp dir.
+    pub(crate) fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("gma-lite-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn archive(names: &[&str]) -> Vec<u8> {
+        let mut b = Builder::new("t", 1);
+        for name in names {
+            b.file_from_bytes(*name, name.as_bytes().to_vec());
+        }
+        b.write_to_vec().unwrap()
+    }
+
+    fn extract(ex: &Extractor, names: &[&str], dir: &Path) -> Result<Vec<PathBuf>, GmaError> {
+        ex.extract(&archive(names)[..], dir)
+    }
+
+    #[test]
+    fn reserved_names_are_errors_by_default() {
+        let dir = temp_dir("reserved");
+        for name in ["con", "lua/nul.lua", "aux/x.lua", "lua/a.", "lua/a "] {
+            let err = extract(&Extractor::new(), &["lua/first.lua", name], &dir).unwrap_err();
+            assert!(
+                matches!(&err, GmaError::ReservedName { name: n, index: 1 } if n == name),
+                "{name}: {err}"
+            );
+            // Nothing before the bad entry was written either
+            assert_eq!(fs::read_dir(&dir).unwrap().count(), 0, "{name}");
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reserved_names_can_be_escaped_or_renamed() {
+        let dir = temp_dir("escaped");
+        let names = ["con", "lua/nul.lua", "aux/x.lua", "lua/a.", "lua/b "];
+        let mut ex = Extractor::new();
+        ex.set_reserved_names(ReservedNames::Escape);
+        let written = extract(&ex, &names, &dir).unwrap();
+        let rel: Vec<_> = written
+            .iter()
+            .map(|p| {
+                p.strip_prefix(&dir)
+                    .unwrap()
+                    .to_str()
+                    .unwrap()
+                    .replace('\\', "/")
+            })
+            .collect();
+        assert_eq!(
+            rel,
+            ["con_", "lua/nul_.lua", "aux_/x.lua", "lua/a_", "lua/b_"]
+        );
+        assert_eq!(fs::read(dir.join("lua/nul_.lua")).unwrap(), b"lua/nul.lua");
+
+        ex.set_reserved_names(ReservedNames::Rename(|part| {
+            format!("reserved-{}", part.trim_end())
+        }));
+        let written = extract(&ex, &["aux/x.lua", "lua/b "], &dir).unwrap();
+        assert_eq!(written[0], dir.join("reserved-aux").join("x.lua"));
+        assert_eq!(written[1], dir.join("lua").join("reserved-b"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn unsafe_paths_are_rejected() {
+        let dir = temp_dir("unsafe");
+        for name in [
+            "../x",
+            "lua/../../x",
+            "/etc/x",
+            "\\x",
+            "C:\\x",
+            "c:/x",
+            "lua\\..\\..\\x",
+        ] {
+            let err = extract(&Extractor::new(), &["lua/first.lua", name], &dir).unwrap_err();
+            assert!(
+                matches!(&err, GmaError::UnsafePath { name: n, index: 1 } if n == name),
+                "{name}: {err}"
+            );
+            assert_eq!(fs::read_dir(&dir).unwrap().count(), 0, "{name}");
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn case_collisions_are_rejected() {
+        let dir = temp_dir("collide");
+        for other in ["LUA/A.lua", "lua\\a.lua", "lua/a.lua"] {
+            let err = extract(&Extractor::new(), &["lua/a.lua", other], &dir).unwrap_err();
+            assert!(
+                matches!(&err, GmaError::DuplicateEntry(n) if n == other),
+                "{other}: {err}"
+            );
+            assert_eq!(fs::read_dir(&dir).unwrap().count(), 0, "{other}");
+        }
+        // An unselected twin doesn't count
+        let mut ex = Extractor::new();
+        ex.set_only(&["x/*"]);
+        let written = extract(&ex, &["x/a.lua", "lua/a.lua", "LUA/A.lua"], &dir).unwrap();
+        assert_eq!(written, [dir.join("x").join("a.lua")]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Every file under `dir`, relative to it, with its content.
+    fn tree(dir: &Path) -> Vec<(PathBuf, Vec<u8>)> {
+        let mut out = Vec::new();
+        let mut pending = vec![dir.to_path_buf()];
+        while let Some(d) = pending.pop() {
+            for entry in fs::read_dir(d).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    pending.push(path);
+                } else {
+                    let content = fs::read(&path).unwrap();
+                    out.push((path.strip_prefix(dir).unwrap().to_path_buf(), content));
+                }
+            }
+        }
+        out.sort();
+        out
+    }
+
+    #[test]
+    fn parallel_and_sequential_extraction_match() {
+        let mut b = Builder::new("t", 1);
+        for i in 0..40u32 {
+            let name = format!("materials/{}/tex{i}.vtf", i % 5);
+            b.file_from_bytes(name, vec![i as u8; 1000 + i as usize * 37]);
+        }
+        b.file_from_bytes("lua/empty.lua", Vec::new());
+        let data = b.write_to_vec().unwrap();
+
+        let seq = temp_dir("sequential");
+        let par = temp_dir("parallel");
+        let mut ex = Extractor::new();
+        let expected = ex.extract(&data[..], &seq).unwrap();
+        ex.set_threads(4);
+        let got = ex
+            .extract_parallel(|| Ok(io::Cursor::new(&data[..])), &par)
+            .unwrap();
+        let strip = |paths: Vec<PathBuf>, dir: &Path| -> Vec<PathBuf> {
+            paths
+                .iter()
+                .map(|p| p.strip_prefix(dir).unwrap().to_path_buf())
+                .collect()
+        };
+        assert_eq!(strip(got, &par), strip(expected, &seq));
+        assert_eq!(tree(&par).len(), 41);
+        assert_eq!(tree(&par), tree(&seq));
+
+        let file = seq.join("archive.gma");
+        fs::write(&file, &data).unwrap();
+        let from_file = temp_dir("from-file");
+        ex.extract_file(&file, &from_file).unwrap();
+        assert_eq!(tree(&from_file), tree(&par));
+        for dir in [seq, par, from_file] {
+            fs::remove_dir_all(dir).unwrap();
+        }
+    }
+}
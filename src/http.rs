@@ -0,0 +1,437 @@
+//! Plain-`http://` range-request source for [`LazyArchive`](crate::LazyArchive).
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::ReadAt;
+
+/// Smallest range fetched per request; reads are served from the last block.
+const BLOCK_SIZE: usize = 64 * 1024;
+
+/// Default for [`HttpRangeSource::set_connect_timeout`].
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Default for [`HttpRangeSource::set_timeout`].
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A remote file accessed with HTTP `Range` requests.
+///
+/// Each uncached read issues one `GET` on a fresh connection. Only `http://`
+/// URLs are supported (no TLS). A server that doesn't answer in time fails the
+/// read instead of stalling it.
+#[derive(Debug)]
+pub struct HttpRangeSource {
+    /// Without brackets, for IPv6 addresses.
+    host: String,
+    port: u16,
+    path: String,
+    connect_timeout: Duration,
+    timeout: Duration,
+    block: Mutex<Option<(u64, Vec<u8>)>>,
+}
+
+impl HttpRangeSource {
+    pub fn new(url: &str) -> io::Result<Self> {
+        let rest = url.strip_prefix("http://").ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                "only http:// URLs are supported",
+            )
+        })?;
+        let (authority, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        let invalid = |why| io::Error::new(io::ErrorKind::InvalidInput, why);
+        // An IPv6 address is bracketed, as in `[::1]:8080`
+        let (host, port) = match authority.strip_prefix('[') {
+            Some(v6) => {
+                let (host, rest) = v6
+                    .split_once(']')
+                    .ok_or_else(|| invalid("unclosed [ in host"))?;
+                let port = match rest {
+                    "" => None,
+                    _ => Some(
+                        rest.strip_prefix(':')
+                            .ok_or_else(|| invalid("invalid port"))?,
+                    ),
+                };
+                (host, port)
+            }
+            None => match authority.rsplit_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (authority, None),
+            },
+        };
+        let port = match port {
+            Some(port) => port.parse().map_err(|_| invalid("invalid port"))?,
+            None => 80,
+        };
+        if host.is_empty() {
+            return Err(invalid("missing host"));
+        }
+        Ok(Self {
+            host: host.to_owned(),
+            port,
+            path: path.to_owned(),
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            timeout: DEFAULT_TIMEOUT,
+            block: Mutex::new(None),
+        })
+    }
+
+    /// How long to wait for each connection attempt. Defaults to 10 seconds.
+    pub fn set_connect_timeout(&mut self, timeout: Duration) {
+        self.connect_timeout = timeout;
+    }
+
+    /// How long a single read from or write to the server may block. Defaults
+    /// to 30 seconds.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Connect to the first address of the host that answers in time.
+    fn connect(&self) -> io::Result<TcpStream> {
+        let mut last_err = None;
+        for addr in (self.host.as_str(), self.port).to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, self.connect_timeout) {
+                Ok(stream) => {
+                    stream.set_read_timeout(Some(self.timeout))?;
+                    stream.set_write_timeout(Some(self.timeout))?;
+                    return Ok(stream);
+                }
+                Err(e) => last_err = Some(e),
+            }
+        }
+        Err(last_err
+            .unwrap_or_else(|| io::Error::new(io::ErrorKind::NotFound, "host has no addresses")))
+    }
+
+    /// The `Host` header: the host, bracketed if IPv6, and any port but 80.
+    fn host_header(&self) -> String {
+        let host = if self.host.contains(':') {
+            format!("[{}]", self.host)
+        } else {
+            self.host.clone()
+        };
+        match self.port {
+            80 => host,
+            port => format!("{host}:{port}"),
+        }
+    }
+
+    /// Fetch `len` bytes at `offset`. Returns fewer at end of file.
+    ///
+    /// The response's `Content-Range` must start at `offset` and stay within
+    /// the requested range; a body shorter than it fails with
+    /// [`UnexpectedEof`](io::ErrorKind::UnexpectedEof).
+    fn fetch(&self, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+        let mut stream = self.connect()?;
+        let last = offset + len as u64 - 1;
+        write!(
+            stream,
+            "GET {} HTTP/1.1\r\nHost: {}\r\nRange: bytes={offset}-{last}\r\nConnection: close\r\n\r\n",
+            self.path,
+            self.host_header()
+        )?;
+        stream.flush()?;
+
+        let mut r = BufReader::new(stream);
+        let mut line = String::new();
+        r.read_line(&mut line)?;
+        let status = line.split_whitespace().nth(1).unwrap_or_default();
+        match status {
+            "206" => {}
+            // Range starts past the end
+            "416" => return Ok(Vec::new()),
+            _ => {
+                return Err(io::Error::other(format!(
+                    "unexpected http status: {}",
+                    line.trim()
+                )));
+            }
+        }
+
+        let mut content_len = None;
+        let mut content_range = None;
+        loop {
+            line.clear();
+            if r.read_line(&mut line)? == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            let header = line.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((key, value)) = header.split_once(':') {
+                if key.eq_ignore_ascii_case("content-length") {
+                    content_len = value.trim().parse::<u64>().ok();
+                } else if key.eq_ignore_ascii_case("content-range") {
+                    content_range = Some(parse_content_range(value)?);
+                } else if key.eq_ignore_ascii_case("transfer-encoding") {
+                    return Err(io::Error::new(
+                        io::ErrorKind::Unsupported,
+                        "chunked range responses are not supported",
+                    ));
+                }
+            }
+        }
+
+        let bad = |why: String| io::Error::new(io::ErrorKind::InvalidData, why);
+        let Some((first, end)) = content_range else {
+            return Err(bad("range response without a Content-Range".into()));
+        };
+        if first != offset || end > last {
+            return Err(bad(format!(
+                "asked for bytes {offset}-{last}, got {first}-{end}"
+            )));
+        }
+        let n = end - first + 1;
+        if content_len.is_some_and(|len| len != n) {
+            return Err(bad(format!(
+                "Content-Length doesn't match the {n} byte Content-Range"
+            )));
+        }
+
+        let mut body = Vec::with_capacity(n as usize);
+        r.take(n).read_to_end(&mut body)?;
+        if (body.len() as u64) < n {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(body)
+    }
+}
+
+/// First and last byte of a `Content-Range: bytes first-last/total` value.
+fn parse_content_range(value: &str) -> io::Result<(u64, u64)> {
+    let range = value
+        .trim()
+        .strip_prefix("bytes ")
+        .and_then(|v| v.split_once('/'))
+        .and_then(|(range, _)| range.split_once('-'));
+    match range.map(|(a, b)| (a.parse::<u64>(), b.parse::<u64>())) {
+        Some((Ok(first), Ok(last))) if first <= last => Ok((first, last)),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid Content-Range: {}", value.trim()),
+        )),
+    }
+}
+
+impl ReadAt for HttpRangeSource {
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let mut block = self.block.lock().unwrap_or_else(|e| e.into_inner());
+        let cached = block
+            .as_ref()
+            .is_some_and(|(start, data)| offset >= *start && offset < start + data.len() as u64);
+        if !cached {
+            let data = self.fetch(offset, buf.len().max(BLOCK_SIZE))?;
+            if data.is_empty() {
+                return Ok(0);
+            }
+            *block = Some((offset, data));
+        }
+        let (start, data) = block.as_ref().expect("block was just filled");
+        let from = (offset - start) as usize;
+        let n = buf.len().min(data.len() - from);
+        buf[..n].copy_from_slice(&data[from..from + n]);
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::thread;
+
+    use super::*;
+    use crate::{Builder, LazyArchive};
+
+    /// Serve `data` on a local port, answering each request with `respond`,
+    /// which gets the request head and the bytes asked for.
+    fn serve(data: Vec<u8>, respond: fn(&str, &[u8], u64) -> Vec<u8>) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                let mut r = BufReader::new(&stream);
+                let mut head = String::new();
+                let mut line = String::new();
+                while r.read_line(&mut line).unwrap_or(0) > 2 {
+                    head.push_str(&line);
+                    line.clear();
+                }
+                let range = head
+                    .lines()
+                    .find_map(|l| l.strip_prefix("Range: bytes="))
+                    .and_then(|r| r.split_once('-'))
+                    .map(|(a, b)| (a.parse::<u64>().unwrap(), b.parse::<u64>().unwrap()));
+                let (first, last) = range.expect("a range request");
+                let end = data.len().min(last as usize + 1);
+                let body = data.get(first as usize..end).unwrap_or_default();
+                let _ = stream.write_all(&respond(&head, body, first));
+            }
+        });
+        port
+    }
+
+    fn partial(_head: &str, body: &[u8], first: u64) -> Vec<u8> {
+        if body.is_empty() {
+            return b"HTTP/1.1 416 Range Not Satisfiable\r\nContent-Length: 0\r\n\r\n".to_vec();
+        }
+        let last = first + body.len() as u64 - 1;
+        let mut out = format!(
+            "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {first}-{last}/*\r\nContent-Length: {}\r\n\r\n",
+            body.len()
+        )
+        .into_bytes();
+        out.extend_from_slice(body);
+        out
+    }
+
+    #[test]
+    fn parses_urls() {
+        let src = HttpRangeSource::new("http://example.com:8080/a/b.gma").unwrap();
+        assert_eq!((src.host.as_str(), src.port), ("example.com", 8080));
+        assert_eq!(src.path, "/a/b.gma");
+        let src = HttpRangeSource::new("http://example.com").unwrap();
+        assert_eq!((src.port, src.path.as_str()), (80, "/"));
+
+        assert!(HttpRangeSource::new("https://example.com/x").is_err());
+        assert!(HttpRangeSource::new("http://example.com:port/x").is_err());
+        assert!(HttpRangeSource::new("http:///x").is_err());
+    }
+
+    #[test]
+    fn parses_ipv6_authorities() {
+        let src = HttpRangeSource::new("http://[::1]:8080/x.gma").unwrap();
+        assert_eq!((src.host.as_str(), src.port), ("::1", 8080));
+        assert_eq!(src.host_header(), "[::1]:8080");
+        let src = HttpRangeSource::new("http://[2001:db8::2]/x").unwrap();
+        assert_eq!((src.host.as_str(), src.port), ("2001:db8::2", 80));
+        assert_eq!(src.host_header(), "[2001:db8::2]");
+
+        assert!(HttpRangeSource::new("http://[::1/x").is_err());
+        assert!(HttpRangeSource::new("http://[::1]8080/x").is_err());
+        assert!(HttpRangeSource::new("http://[]:80/x").is_err());
+    }
+
+    #[test]
+    fn host_header_carries_the_port() {
+        assert_eq!(
+            HttpRangeSource::new("http://example.com/x")
+                .unwrap()
+                .host_header(),
+            "example.com"
+        );
+        assert_eq!(
+            HttpRangeSource::new("http://example.com:8080/x")
+                .unwrap()
+                .host_header(),
+            "example.com:8080"
+        );
+
+        fn check_host(head: &str, body: &[u8], first: u64) -> Vec<u8> {
+            assert!(head.contains("\r\nHost: 127.0.0.1:"), "{head}");
+            partial(head, body, first)
+        }
+        let port = serve(vec![1, 2, 3], check_host);
+        let src = HttpRangeSource::new(&format!("http://127.0.0.1:{port}/")).unwrap();
+        let mut buf = [0u8; 3];
+        assert_eq!(src.read_at(0, &mut buf).unwrap(), 3);
+        assert_eq!(buf, [1, 2, 3]);
+    }
+
+    #[test]
+    fn checks_the_returned_range() {
+        fn shifted(_head: &str, body: &[u8], first: u64) -> Vec<u8> {
+            let last = first + body.len() as u64;
+            let mut out = format!(
+                "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {}-{last}/*\r\n\r\n",
+                first + 1
+            )
+            .into_bytes();
+            out.extend_from_slice(body);
+            out
+        }
+        let port = serve(vec![1, 2, 3], shifted);
+        let src = HttpRangeSource::new(&format!("http://127.0.0.1:{port}/")).unwrap();
+        let err = src.read_at(0, &mut [0u8; 3]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn short_body_is_unexpected_eof() {
+        fn short(head: &str, body: &[u8], first: u64) -> Vec<u8> {
+            let mut out = partial(head, body, first);
+            out.truncate(out.len() - 1);
+            out
+        }
+        let port = serve(vec![1, 2, 3], short);
+        let src = HttpRangeSource::new(&format!("http://127.0.0.1:{port}/")).unwrap();
+        let err = src.read_at(0, &mut [0u8; 3]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn reads_an_archive_over_ranges() {
+        let mut b = Builder::new("remote", 1);
+        b.file_from_bytes("lua/a.lua", b"print(1)".to_vec())
+            .file_from_bytes("lua/big.lua", vec![b'x'; 3 * BLOCK_SIZE]);
+        let data = b.write_to_vec().unwrap();
+        let port = serve(data, partial);
+
+        let src = HttpRangeSource::new(&format!("http://127.0.0.1:{port}/x.gma")).unwrap();
+        let archive = LazyArchive::open(src).unwrap();
+        assert_eq!(archive.name(), "remote");
+        let a = archive.find("lua/a.lua").unwrap();
+        assert_eq!(archive.read_entry(a).unwrap(), b"print(1)");
+        let big = archive.find("lua/big.lua").unwrap();
+        assert_eq!(archive.read_entry(big).unwrap(), vec![b'x'; 3 * BLOCK_SIZE]);
+
+        // Past the end
+        let mut buf = [0u8; 4];
+        let src = archive.into_inner();
+        assert_eq!(src.read_at(10 * BLOCK_SIZE as u64, &mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn rejects_servers_ignoring_ranges() {
+        fn whole(_head: &str, body: &[u8], _first: u64) -> Vec<u8> {
+            let mut out =
+                format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", body.len()).into_bytes();
+            out.extend_from_slice(body);
+            out
+        }
+        let port = serve(vec![1, 2, 3], whole);
+        let src = HttpRangeSource::new(&format!("http://127.0.0.1:{port}/")).unwrap();
+        assert!(src.read_at(0, &mut [0u8; 3]).is_err());
+    }
+
+    #[test]
+    fn silent_server_times_out() {
+        // Connections complete in the backlog, but nothing ever answers
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let mut src = HttpRangeSource::new(&format!("http://127.0.0.1:{port}/")).unwrap();
+        src.set_timeout(Duration::from_millis(100));
+        let started = std::time::Instant::now();
+        let err = src.read_at(0, &mut [0u8; 3]).unwrap_err();
+        assert!(
+            matches!(
+                err.kind(),
+                io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+            ),
+            "{err}"
+        );
+        assert!(started.elapsed() < Duration::from_secs(10));
+        drop(listener);
+    }
+}
@@ -0,0 +1,78 @@
+use crate::wildcard;
+
+/// Patterns gmad leaves out of every archive, regardless of addon.json.
+pub const GMAD_DEFAULT_IGNORES: &[&str] = &[
+    "*.psd",
+    "*.pdn",
+    "*.xcf",
+    "*.svn*",
+    ".git*",
+    "*.vcproj",
+    "*.sln",
+    ".ds_store",
+    "thumbs.db",
+    "desktop.ini",
+];
+
+/// Set of wildcard patterns deciding which files are left out when packing.
+///
+/// Patterns use gmad's syntax (`*` and `?`, `*` crossing `/`) and match
+/// case-insensitively against either the whole relative path or its file name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IgnoreRules {
+    patterns: Vec<String>,
+}
+
+impl Default for IgnoreRules {
+    /// Equivalent to [`IgnoreRules::gmad_default`].
+    fn default() -> Self {
+        Self::gmad_default()
+    }
+}
+
+impl IgnoreRules {
+    /// gmad's built-in ignores ([`GMAD_DEFAULT_IGNORES`]).
+    pub fn gmad_default() -> Self {
+        Self {
+            patterns: GMAD_DEFAULT_IGNORES.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+
+    /// No patterns: nothing is ignored.
+    pub fn empty() -> Self {
+        Self {
+            patterns: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, pattern: impl Into<String>) {
+        self.patterns.push(pattern.into().to_ascii_lowercase());
+    }
+
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    #[inline]
+    pub fn is_ignored(&self, path: &str) -> bool {
+        self.matching_pattern(path).is_some()
+    }
+
+    /// The first pattern that ignores `path`, if any.
+    pub fn matching_pattern(&self, path: &str) -> Option<&str> {
+        let path = path.replace('\\', "/").to_ascii_lowercase();
+        let file = path.rsplit('/').next().unwrap_or(&path);
+        self.patterns
+            .iter()
+            .find(|p| wildcard::matches(p, &path) || wildcard::matches(p, file))
+            .map(String::as_str)
+    }
+}
+
+impl<S: Into<String>> Extend<S> for IgnoreRules {
+    fn extend<I: IntoIterator<Item = S>>(&mut self, iter: I) {
+        for p in iter {
+            self.add(p);
+        }
+    }
+}
@@ -0,0 +1,36 @@
+/// Broad Garry's Mod content category of an entry, from its path.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum EntryKind {
+    Lua,
+    Material,
+    Model,
+    Sound,
+    Map,
+    Particle,
+    Resource,
+    Gamemode,
+    Other,
+}
+
+impl EntryKind {
+    /// Classify an archive path by its top-level folder (`lua/`, `materials/`, ...).
+    ///
+    /// Anything under `gamemodes/` is [`EntryKind::Gamemode`], whatever its type;
+    /// stray `.lua` files elsewhere still count as [`EntryKind::Lua`].
+    pub fn of(name: &str) -> Self {
+        let path = name.replace('\\', "/").to_ascii_lowercase();
+        let top = path.split('/').next().unwrap_or_default();
+        match top {
+            "gamemodes" => EntryKind::Gamemode,
+            "lua" => EntryKind::Lua,
+            "materials" => EntryKind::Material,
+            "models" => EntryKind::Model,
+            "sound" => EntryKind::Sound,
+            "maps" => EntryKind::Map,
+            "particles" => EntryKind::Particle,
+            "resource" => EntryKind::Resource,
+            _ if path.ends_with(".lua") => EntryKind::Lua,
+            _ => EntryKind::Other,
+        }
+    }
+}
@@ -2,10 +2,10 @@
 //!
 //! Format:
 //! - "GMAD" header (4 bytes)
-//! - VERSION (int8) == 3
+//! - VERSION (int8), 1 to 3
 //! - steam_id64 (little-endian i64) [ignored]
 //! - timestamp (little-endian u64) [ignored]
-//! - required content (u8 = 0) [ignored]
+//! - required content (C strings ended by an empty one; versions 2+) [ignored]
 //! - addon name (C string)
 //! - addon description (C string)
 //! - addon author (C string)
@@ -13,14 +13,34 @@
 //! - Repeated file metadata entries until idx == 0:
 //!     * idx (u32, 1-based; 0 terminates the list)
 //!     * name (C string)
-//!     * size (i64)
+//!     * size (i64; negative sizes are rejected, so the API uses `u64`)
 //!     * crc32 (u32) [ignored]
 //! - File contents, concatenated in metadata order
 //! - trailing u32 zero
 //!
+//! The header fields marked ignored are still surfaced on [`AddonInfo`].
+//! Versions 2 and 3 share this layout; version 1 only lacks the required content.
+//!
+//! Degenerate archives are valid: an empty entry table (the terminator right after
+//! the header) reads as an empty list, and empty name/description/author strings
+//! as `""`. [`Builder`] writes both, so placeholder addons round-trip.
+//!
+//! [`read`], [`GmaReader`] and [`Builder`] are the low-level primitives; [`Addon`] layers a
+//! load/edit/build workflow on top of them.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
 
-use std::fmt;
-use std::io::{self};
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Range;
+#[cfg(feature = "std")]
+use std::collections::{BTreeMap, HashMap};
+#[cfg(feature = "std")]
+use std::io;
 
 /// Magic header for GMA files.
 pub const HEADER: &[u8; 4] = b"GMAD";
@@ -28,34 +48,780 @@ pub const HEADER: &[u8; 4] = b"GMAD";
 /// File format version.
 pub const VERSION: i8 = 3;
 
+/// Oldest format version that can be read and written.
+pub const MIN_VERSION: i8 = 1;
+
+mod crc;
+
+#[cfg(any(feature = "std", feature = "serde"))]
+mod base64;
+
+mod slice;
+pub use slice::SliceArchive;
+#[cfg(feature = "std")]
+mod sha;
+
+#[cfg(feature = "std")]
 mod reader;
-pub use reader::read;
+#[cfg(feature = "std")]
+pub use reader::{
+    DeclaredSize, DuplicatePolicy, EntryAudit, EntryReader, GmaReader, NameDecoder, ReadEvent,
+    ReadEvents, ReadOptions, ReadWarning, TrailerPolicy, declared_size, decode_windows_1252,
+    list_entries, list_names, read, read_buffered, read_filtered, read_with,
+};
 
+#[cfg(feature = "std")]
 mod builder;
-pub use builder::Builder;
+#[cfg(feature = "std")]
+pub use builder::{Builder, EntrySource, WORKSHOP_MAX_SIZE, WriteProgress};
+
+#[cfg(feature = "std")]
+mod writer;
+#[cfg(feature = "std")]
+pub use writer::{EntryWriter, GmaWriter};
+
+#[cfg(feature = "lzma")]
+mod lzma;
+#[cfg(feature = "lzma")]
+pub use lzma::compress_lzma;
+
+#[cfg(feature = "std")]
+mod rewrite;
+#[cfg(feature = "std")]
+pub use rewrite::RewriteRule;
+
+#[cfg(feature = "std")]
+mod parallel;
+#[cfg(feature = "std")]
+mod wildcard;
+
+#[cfg(feature = "std")]
+mod sniff;
+#[cfg(feature = "std")]
+pub use sniff::{Format, is_gma, sniff, sniff_path};
+
+#[cfg(feature = "std")]
+mod read_at;
+#[cfg(feature = "std")]
+pub use read_at::{LazyArchive, ReadAt, ReadAtReader};
+
+#[cfg(feature = "http")]
+mod http;
+#[cfg(feature = "http")]
+pub use http::HttpRangeSource;
+
+#[cfg(feature = "std")]
+mod extract;
+#[cfg(feature = "std")]
+pub use extract::{Extractor, ReservedNames};
+
+#[cfg(feature = "std")]
+mod transaction;
+#[cfg(feature = "std")]
+pub use transaction::ArchiveTransaction;
+
+#[cfg(feature = "std")]
+mod session;
+#[cfg(feature = "std")]
+pub use session::GmaSession;
+
+#[cfg(feature = "std")]
+mod limits;
+#[cfg(feature = "std")]
+pub use limits::Limits;
+
+#[cfg(feature = "std")]
+mod quota;
+#[cfg(feature = "std")]
+pub use quota::{CategoryUsage, Quotas, Usage};
+
+#[cfg(feature = "std")]
+mod plan;
+#[cfg(feature = "std")]
+pub use plan::{ExtractionPlan, PlannedArchive};
+
+#[cfg(feature = "std")]
+mod json;
+
+#[cfg(feature = "std")]
+mod metadata;
+#[cfg(feature = "std")]
+pub use metadata::AddonMetadata;
+
+#[cfg(feature = "std")]
+mod listing;
+#[cfg(feature = "std")]
+pub use listing::listing_json;
+
+#[cfg(feature = "json")]
+mod addon_json;
+#[cfg(feature = "json")]
+pub use addon_json::AddonJson;
+
+#[cfg(feature = "std")]
+mod licenses;
+#[cfg(feature = "std")]
+pub use licenses::{LICENSES_FILE, LicenseEntry, Licenses};
+
+#[cfg(feature = "std")]
+mod assets;
+#[cfg(feature = "std")]
+pub use assets::{Asset, AssetKind, group_assets};
+
+#[cfg(feature = "std")]
+mod ignore;
+#[cfg(feature = "std")]
+pub use ignore::{GMAD_DEFAULT_IGNORES, IgnoreRules};
+
+#[cfg(feature = "std")]
+mod whitelist;
+#[cfg(feature = "std")]
+pub use whitelist::{GMAD_WHITELIST, is_whitelisted};
+
+#[cfg(feature = "std")]
+mod mount;
+#[cfg(feature = "std")]
+pub use mount::{MountCheck, MountIssue, MountProblem, MountReport, Severity};
+
+#[cfg(feature = "std")]
+mod validate;
+#[cfg(feature = "std")]
+pub use validate::{DuplicateContent, ValidationIssue, ValidationProblem, ValidationReport};
+
+#[cfg(feature = "std")]
+mod dir;
+#[cfg(feature = "std")]
+pub use dir::{DirOptions, Exclusion, ExclusionReason};
+
+#[cfg(feature = "json")]
+mod create;
+#[cfg(feature = "json")]
+pub use create::create_from_addon_dir;
+
+mod kind;
+pub use kind::EntryKind;
+
+mod steam_id;
+pub use steam_id::SteamId;
+
+mod workshop;
+pub use workshop::{AddonTag, AddonType, MAX_TAGS, check_type_and_tags};
+
+mod localize;
+
+#[cfg(feature = "std")]
+mod lua;
+#[cfg(feature = "std")]
+pub use lua::{LuaRealm, is_autorun, is_lua, lua_realm};
+
+#[cfg(feature = "std")]
+mod content_list;
+#[cfg(feature = "std")]
+pub use content_list::{ContentItem, ContentList};
+
+#[cfg(feature = "std")]
+mod manifest;
+#[cfg(feature = "std")]
+pub use manifest::ManifestEntry;
+
+#[cfg(feature = "std")]
+mod diff;
+#[cfg(feature = "std")]
+pub use diff::{ArchiveDiff, Change, DiffLine, EntryDiff, Hunk, diff_entries};
+
+#[cfg(feature = "std")]
+mod bloom;
+#[cfg(feature = "std")]
+pub use bloom::ArchiveFilter;
+
+#[cfg(feature = "std")]
+mod addon;
+#[cfg(feature = "std")]
+pub use addon::Addon;
+
+/// Whether an entry name stays inside the directory it is extracted to: it must
+/// be relative and non-empty, with no `..` components and no `:` (drive letters,
+/// alternate streams). Both `/` and `\\` count as separators.
+pub fn is_safe_path(name: &str) -> bool {
+    let mut parts = name.split(['/', '\\']);
+    !name.starts_with(['/', '\\'])
+        && parts.clone().any(|p| !p.is_empty() && p != ".")
+        && parts.all(|p| p != ".." && !p.contains(':'))
+}
+
+/// `b` as the game compares paths: ASCII lowercase, with `\\` read as `/`.
+#[cfg(feature = "std")]
+pub(crate) fn fold_path_byte(b: u8) -> u8 {
+    if b == b'\\' {
+        b'/'
+    } else {
+        b.to_ascii_lowercase()
+    }
+}
+
+/// Whether two entry names are the same file to the game (see [`fold_path_byte`]).
+#[cfg(feature = "std")]
+pub(crate) fn same_path(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .all(|(a, b)| fold_path_byte(a) == fold_path_byte(b))
+}
+
+/// Addon-level fields from the archive header.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct AddonInfo {
+    /// Format version from the header, [`MIN_VERSION`] to [`VERSION`].
+    pub version: i8,
+    pub name: String,
+    pub description: String,
+    pub author: String,
+    /// SteamID64 of the packer, as written by the tool that built the archive.
+    pub steam_id64: i64,
+    /// Unix time the archive was written.
+    pub timestamp: u64,
+    /// Required content: strings after the timestamp, ended by an empty one.
+    /// Empty in practice, and absent in version 1.
+    pub required_content: Vec<String>,
+    /// Addon version; the game doesn't use it.
+    pub addon_version: i32,
+}
 
 /// One entry (file) contained in a GMA.
+///
+/// The content is owned by default. It can be any `AsRef<[u8]>` (a
+/// `Cow<[u8]>`, say): [`SliceArchive::files`] hands out `GMAFile<&[u8]>`s
+/// borrowing the archive's bytes, and [`into_owned`](Self::into_owned) turns
+/// any content into a `Vec<u8>`.
+///
+/// With the `serde` feature, `name_bytes` and `content` are base64 strings.
 #[derive(Clone, Debug, PartialEq, Eq, Default)]
-pub struct GMAFile {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(serialize = "C: AsRef<[u8]>", deserialize = "C: From<Vec<u8>>"))
+)]
+pub struct GMAFile<C = Vec<u8>> {
     pub name: String,
-    pub content: Vec<u8>,
-    pub size: i64,
+    /// Raw bytes of `name` as stored in the archive.
+    #[cfg_attr(feature = "serde", serde(with = "crate::base64::as_base64"))]
+    pub name_bytes: Vec<u8>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::base64::as_base64"))]
+    pub content: C,
+    /// Size declared in the metadata table. Equal to the content's length,
+    /// except for an entry kept by [`ReadOptions::set_recover_truncated`].
+    pub size: u64,
+    /// CRC-32 declared in the metadata table; 0 if the packer left it out.
+    ///
+    /// Always 0 for entries added through [`Builder`], which computes its own.
+    pub crc: u32,
+    /// Absolute byte offset of the content within the archive it was read from.
+    ///
+    /// Always 0 for entries added through [`Builder`].
+    pub offset: u64,
+}
+
+impl<C: AsRef<[u8]>> GMAFile<C> {
+    #[inline]
+    pub fn kind(&self) -> EntryKind {
+        EntryKind::of(&self.name)
+    }
+
+    /// Byte range of the content within the source archive.
+    pub fn content_range(&self) -> Range<u64> {
+        self.offset..self.offset + self.size
+    }
+
+    /// CRC-32 of `content`, to compare with the declared [`crc`](Self::crc).
+    pub fn computed_crc(&self) -> u32 {
+        let mut crc = crc::Crc32::new();
+        crc.update(self.content.as_ref());
+        crc.finish()
+    }
+
+    /// Everything but the content.
+    pub fn meta(&self) -> EntryMeta {
+        EntryMeta {
+            name: self.name.clone(),
+            name_bytes: self.name_bytes.clone(),
+            size: self.size,
+            crc: self.crc,
+            offset: self.offset,
+        }
+    }
+
+    /// The same entry with its content copied into a `Vec<u8>`.
+    pub fn into_owned(self) -> GMAFile {
+        GMAFile {
+            content: self.content.as_ref().to_vec(),
+            name: self.name,
+            name_bytes: self.name_bytes,
+            size: self.size,
+            crc: self.crc,
+            offset: self.offset,
+        }
+    }
+}
+
+/// Metadata of one entry, without its content: a [`GMAFile`] minus `content`.
+/// See [`list_entries`].
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EntryMeta {
+    pub name: String,
+    /// Raw bytes of `name` as stored in the archive.
+    #[cfg_attr(feature = "serde", serde(with = "crate::base64::as_base64"))]
+    pub name_bytes: Vec<u8>,
+    pub size: u64,
+    /// CRC-32 declared in the metadata table.
+    pub crc: u32,
+    /// Absolute byte offset of the content within the archive.
+    pub offset: u64,
+}
+
+impl EntryMeta {
+    #[inline]
+    pub fn kind(&self) -> EntryKind {
+        EntryKind::of(&self.name)
+    }
+
+    /// This entry as a [`GMAFile`] holding `content`.
+    pub fn with_content<C>(self, content: C) -> GMAFile<C> {
+        GMAFile {
+            name: self.name,
+            name_bytes: self.name_bytes,
+            content,
+            size: self.size,
+            crc: self.crc,
+            offset: self.offset,
+        }
+    }
+
+    pub(crate) fn truncated(&self, read: u64) -> GmaError {
+        GmaError::TruncatedEntry {
+            name: self.name.clone(),
+            read,
+            expected: self.size,
+        }
+    }
+}
+
+/// A parsed archive, as returned by [`read_with`].
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, Default)]
+pub struct GmaArchive {
+    info: AddonInfo,
+    entries: Vec<GMAFile>,
+    warnings: Vec<ReadWarning>,
+    #[cfg(feature = "json")]
+    addon_json: Option<AddonJson>,
+    manifest: Vec<ManifestEntry>,
+    archive_crc: Option<u32>,
+    trailer: u32,
+    /// Lowercased, `/`-separated name to the first entry with it.
+    index: HashMap<String, usize>,
+}
+
+#[cfg(feature = "std")]
+impl GmaArchive {
+    pub fn name(&self) -> &str {
+        &self.info.name
+    }
+
+    pub fn description(&self) -> &str {
+        &self.info.description
+    }
+
+    pub fn author(&self) -> &str {
+        &self.info.author
+    }
+
+    /// Every header field, including the ones the game ignores.
+    pub fn info(&self) -> &AddonInfo {
+        &self.info
+    }
+
+    pub fn entries(&self) -> &[GMAFile] {
+        &self.entries
+    }
+
+    pub fn into_entries(self) -> Vec<GMAFile> {
+        self.entries
+    }
+
+    /// The entry at `path`, compared ASCII case-insensitively with `\\` and `/`
+    /// treated alike, as the game does. With duplicates, the first one.
+    pub fn get(&self, path: &str) -> Option<&GMAFile> {
+        let key = path.replace('\\', "/").to_ascii_lowercase();
+        self.index.get(&key).map(|&i| &self.entries[i])
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.get(path).is_some()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, GMAFile> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Content bytes across all entries.
+    pub fn total_size(&self) -> u64 {
+        self.entries.iter().map(|e| e.content.len() as u64).sum()
+    }
+
+    pub(crate) fn new(
+        info: AddonInfo,
+        entries: Vec<GMAFile>,
+        warnings: Vec<ReadWarning>,
+        manifest: Vec<ManifestEntry>,
+        archive_crc: Option<u32>,
+        trailer: u32,
+    ) -> Self {
+        let mut index = HashMap::with_capacity(entries.len());
+        for (i, e) in entries.iter().enumerate() {
+            index
+                .entry(e.name.replace('\\', "/").to_ascii_lowercase())
+                .or_insert(i);
+        }
+        Self {
+            info,
+            entries,
+            warnings,
+            #[cfg(feature = "json")]
+            addon_json: None,
+            manifest,
+            archive_crc,
+            trailer,
+            index,
+        }
+    }
+
+    /// Non-fatal problems noticed while reading.
+    pub fn warnings(&self) -> &[ReadWarning] {
+        &self.warnings
+    }
+
+    /// Entries grouped by [`EntryKind`].
+    pub fn by_kind(&self) -> BTreeMap<EntryKind, Vec<&GMAFile>> {
+        let mut groups: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for e in &self.entries {
+            groups.entry(e.kind()).or_default().push(e);
+        }
+        groups
+    }
+
+    /// Total content bytes per [`EntryKind`].
+    pub fn size_by_kind(&self) -> BTreeMap<EntryKind, u64> {
+        let mut sizes = BTreeMap::new();
+        for e in &self.entries {
+            *sizes.entry(e.kind()).or_default() += e.content.len() as u64;
+        }
+        sizes
+    }
+
+    /// All `.lua` entries.
+    pub fn lua_entries(&self) -> impl Iterator<Item = &GMAFile> {
+        self.entries.iter().filter(|e| is_lua(&e.name))
+    }
+
+    /// Lua entries the game runs automatically (`lua/autorun/...`).
+    pub fn autorun_entries(&self) -> impl Iterator<Item = &GMAFile> {
+        self.entries.iter().filter(|e| is_autorun(&e.name))
+    }
+
+    /// Lua entries that run in `realm`. See [`lua_realm`].
+    pub fn lua_entries_in(&self, realm: LuaRealm) -> impl Iterator<Item = &GMAFile> {
+        self.entries
+            .iter()
+            .filter(move |e| lua_realm(&e.name) == Some(realm))
+    }
+
+    /// What changed from `self` to `newer`. See [`diff_entries`].
+    pub fn diff(&self, newer: &GmaArchive) -> ArchiveDiff {
+        diff_entries(&self.entries, &newer.entries)
+    }
+
+    /// Entries grouped into models and materials. See [`group_assets`].
+    pub fn assets(&self) -> Vec<Asset> {
+        group_assets(&self.entries)
+    }
+
+    /// The embedded `addon.json`, if [`ReadOptions::set_parse_addon_json`] was on
+    /// and the archive has a valid one.
+    #[cfg(feature = "json")]
+    pub fn addon_json(&self) -> Option<&AddonJson> {
+        self.addon_json.as_ref()
+    }
+
+    /// Parse the archive's [`LICENSES_FILE`] entry, if it has one.
+    pub fn licenses(&self) -> Option<Result<Licenses, GmaError>> {
+        let entry = self.get(LICENSES_FILE)?;
+        Some(Licenses::parse(&String::from_utf8_lossy(&entry.content)))
+    }
+
+    /// Per-entry hashes, if [`ReadOptions::set_entry_hashes`] was on.
+    pub fn manifest(&self) -> &[ManifestEntry] {
+        &self.manifest
+    }
+
+    /// The trailing whole-archive CRC-32, if the archive ended with one (it
+    /// has been verified).
+    pub fn archive_crc(&self) -> Option<u32> {
+        self.archive_crc
+    }
+
+    /// The trailing marker: 0, or the verified whole-archive CRC.
+    pub fn trailer(&self) -> u32 {
+        self.trailer
+    }
 }
 
-/// Errors that can occur while reading a GMA.
+#[cfg(feature = "std")]
+impl<'a> IntoIterator for &'a GmaArchive {
+    type Item = &'a GMAFile;
+    type IntoIter = std::slice::Iter<'a, GMAFile>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
+
+#[cfg(feature = "std")]
+impl IntoIterator for GmaArchive {
+    type Item = GMAFile;
+    type IntoIter = std::vec::IntoIter<GMAFile>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+/// What a non-zero trailing marker most likely is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrailerKind {
+    /// CRC-32 of all preceding bytes, as appended by gmad.
+    ///
+    /// Only detected when the stream is hashed: always under
+    /// [`TrailerPolicy::Lenient`], and with [`ReadOptions::set_hash_archive`].
+    ArchiveCrc,
+    /// The "GMAD" magic of another archive concatenated after this one.
+    NextArchive,
+    /// Anything else.
+    Garbage,
+}
+
+impl fmt::Display for TrailerKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TrailerKind::ArchiveCrc => "archive crc",
+            TrailerKind::NextArchive => "start of another archive",
+            TrailerKind::Garbage => "garbage",
+        })
+    }
+}
+
+/// The header or metadata table field a [`GmaError::Parse`] happened in.
+/// Entry fields carry the entry's position in the table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseField {
+    Magic,
+    Version,
+    SteamId,
+    Timestamp,
+    RequiredContent,
+    Name,
+    Description,
+    Author,
+    AddonVersion,
+    FileIndex(usize),
+    EntryName(usize),
+    EntrySize(usize),
+    EntryCrc(usize),
+    TrailingMarker,
+}
+
+impl ParseField {
+    /// The entry's position in the metadata table, for entry fields.
+    pub fn index(self) -> Option<usize> {
+        match self {
+            ParseField::FileIndex(i)
+            | ParseField::EntryName(i)
+            | ParseField::EntrySize(i)
+            | ParseField::EntryCrc(i) => Some(i),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ParseField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseField::Magic => f.write_str("magic"),
+            ParseField::Version => f.write_str("version"),
+            ParseField::SteamId => f.write_str("steam id"),
+            ParseField::Timestamp => f.write_str("timestamp"),
+            ParseField::RequiredContent => f.write_str("required content"),
+            ParseField::Name => f.write_str("name"),
+            ParseField::Description => f.write_str("description"),
+            ParseField::Author => f.write_str("author"),
+            ParseField::AddonVersion => f.write_str("addon version"),
+            ParseField::FileIndex(i) => write!(f, "entry {i} file index"),
+            ParseField::EntryName(i) => write!(f, "entry {i} name"),
+            ParseField::EntrySize(i) => write!(f, "entry {i} size"),
+            ParseField::EntryCrc(i) => write!(f, "entry {i} crc"),
+            ParseField::TrailingMarker => f.write_str("trailing marker"),
+        }
+    }
+}
+
+/// Errors that can occur while reading or writing a GMA.
+///
+/// New variants may be added in minor releases; match on [`code`](Self::code)
+/// or keep a wildcard arm.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum GmaError {
+    #[cfg(feature = "std")]
     Io(io::Error),
     InvalidHeader([u8; 4]),
     InvalidVersion(i8),
     MissingNullTerminator, // for C-strings
     SizeOutOfRange(i64),
-    TrailingMarkerMismatch(u32),
+    TrailingMarkerMismatch {
+        found: u32,
+        kind: TrailerKind,
+    },
+    DuplicateEntry(String),
+    /// The stream ended inside an entry's content.
+    TruncatedEntry {
+        name: String,
+        read: u64,
+        expected: u64,
+    },
+    InvalidAddonJson(String),
+    /// `addon_licenses.json` is malformed, or doesn't match the packed files.
+    InvalidLicenses(String),
+    /// The archive would exceed the configured size limit.
+    ArchiveTooLarge {
+        size: u64,
+        limit: u64,
+    },
+    /// A [`Builder`]'s projected size is over its [`Builder::set_max_size`]
+    /// limit; `largest` lists the biggest entries (name and size), largest first.
+    OverSizeLimit {
+        size: u64,
+        limit: u64,
+        largest: Vec<(String, u64)>,
+    },
+    /// The metadata table lists more entries than [`ReadOptions::set_max_entries`] allows,
+    /// or a [`Builder`] more than its u32 file indices can number.
+    TooManyEntries {
+        limit: usize,
+    },
+    /// An entry declares more bytes than [`ReadOptions::set_max_entry_size`] allows.
+    EntryTooLarge {
+        name: String,
+        size: u64,
+        limit: u64,
+    },
+    /// An entry name runs past [`ReadOptions::set_max_name_len`] bytes without
+    /// its terminator. `index` is the entry's position in the metadata table.
+    NameTooLong {
+        index: usize,
+        limit: usize,
+    },
+    /// A header string runs past [`ReadOptions::set_max_header_string_len`] bytes
+    /// without its terminator.
+    HeaderStringTooLong {
+        field: ParseField,
+        limit: usize,
+    },
+    /// The header lists more required content than
+    /// [`ReadOptions::set_max_required_content`] allows.
+    TooManyRequiredContent {
+        limit: usize,
+    },
+    /// A metadata entry's file index broke the 1-based sequence
+    /// ([`ReadOptions::set_strict_indices`]).
+    InvalidFileIndex {
+        expected: u32,
+        got: u32,
+    },
+    /// An entry name would escape the output directory (see [`is_safe_path`]).
+    /// `index` is the entry's position in the metadata table.
+    UnsafePath {
+        name: String,
+        index: usize,
+    },
+    /// An entry name has a component Windows reserves (see [`ReservedNames`]).
+    ReservedName {
+        name: String,
+        index: usize,
+    },
+    /// Extraction would write more under a top-level directory than its [`Quotas`] limit.
+    QuotaExceeded {
+        category: String,
+        size: u64,
+        limit: u64,
+    },
+    /// Files gmad's whitelist refuses, found by [`create_from_addon_dir`].
+    NotWhitelisted(Vec<String>),
+    /// A header string or entry name to be written contains a NUL byte, which
+    /// would end it early; `field` is `"name"`, `"description"`, `"author"`,
+    /// `"required content"` or `"entry name"`.
+    NulInString {
+        field: &'static str,
+    },
+    /// Text that [`SteamId::parse`] doesn't recognize.
+    InvalidSteamId(String),
+    /// An addon type or tags the Workshop refuses (see [`check_type_and_tags`]).
+    InvalidMetadata(String),
+    /// Reading or writing was aborted through [`ReadOptions::set_cancel_flag`] or
+    /// [`Builder::set_cancel_flag`].
+    Cancelled,
+    /// The input ended early ([`SliceArchive`]; stream readers report `Io` instead).
+    UnexpectedEof,
+    /// A streamed or path-backed entry had more content than the size it was
+    /// added with; the write was aborted rather than cut it short.
+    EntryOverrun {
+        name: String,
+        declared: u64,
+    },
+    /// An entry's content doesn't match the CRC-32 declared in the metadata
+    /// table ([`ReadOptions::set_verify_crcs`]).
+    CrcMismatch {
+        name: String,
+        declared: u32,
+        actual: u32,
+    },
+    /// A header or metadata table field couldn't be read: the input ended
+    /// (`Io` or `UnexpectedEof`), a string had no terminator, or a size was
+    /// negative. `offset` is where the field starts in the archive.
+    Parse {
+        offset: u64,
+        field: ParseField,
+        source: Box<GmaError>,
+    },
+    /// An entry name isn't valid UTF-8 ([`ReadOptions::set_require_utf8_names`]).
+    /// `index` is the entry's position in the metadata table.
+    InvalidUtf8 {
+        name_bytes: Vec<u8>,
+        index: usize,
+    },
 }
 
 impl fmt::Display for GmaError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
+            #[cfg(feature = "std")]
             GmaError::Io(e) => write!(f, "io error: {e}"),
             GmaError::InvalidHeader(got) => {
                 write!(f, "invalid header: {:?}", String::from_utf8_lossy(got))
@@ -63,25 +829,223 @@ impl fmt::Display for GmaError {
             GmaError::InvalidVersion(v) => write!(f, "invalid version: {v}"),
             GmaError::MissingNullTerminator => write!(f, "missing null terminator in C string"),
             GmaError::SizeOutOfRange(sz) => write!(f, "negative or invalid size: {sz}"),
-            GmaError::TrailingMarkerMismatch(v) => {
-                write!(f, "expected trailing 0 u32 marker, got {v}")
+            GmaError::TrailingMarkerMismatch { found, kind } => {
+                write!(f, "expected trailing 0 u32 marker, got {found} ({kind})")
+            }
+            GmaError::DuplicateEntry(name) => write!(f, "duplicate entry: {name}"),
+            GmaError::TruncatedEntry {
+                name,
+                read,
+                expected,
+            } => write!(f, "entry {name} truncated: got {read} of {expected} bytes"),
+            GmaError::InvalidAddonJson(why) => write!(f, "invalid addon.json: {why}"),
+            GmaError::InvalidLicenses(why) => write!(f, "invalid addon_licenses.json: {why}"),
+            GmaError::ArchiveTooLarge { size, limit } => {
+                write!(f, "archive is {size} bytes, over the {limit} byte limit")
+            }
+            GmaError::OverSizeLimit {
+                size,
+                limit,
+                largest,
+            } => {
+                write!(
+                    f,
+                    "archive would be {size} bytes, over the {limit} byte limit"
+                )?;
+                for (i, (name, size)) in largest.iter().enumerate() {
+                    let sep = if i == 0 { "; largest entries: " } else { ", " };
+                    write!(f, "{sep}{name} ({size} bytes)")?;
+                }
+                Ok(())
+            }
+            GmaError::TooManyEntries { limit } => {
+                write!(f, "archive lists more than {limit} entries")
+            }
+            GmaError::EntryTooLarge { name, size, limit } => {
+                write!(
+                    f,
+                    "entry {name} is {size} bytes, over the {limit} byte limit"
+                )
+            }
+            GmaError::NameTooLong { index, limit } => {
+                write!(f, "name of entry #{index} is longer than {limit} bytes")
+            }
+            GmaError::HeaderStringTooLong { field, limit } => {
+                write!(f, "header {field} is longer than {limit} bytes")
+            }
+            GmaError::TooManyRequiredContent { limit } => {
+                write!(f, "header lists more than {limit} required content items")
+            }
+            GmaError::InvalidFileIndex { expected, got } => {
+                write!(f, "invalid file index: expected {expected}, got {got}")
+            }
+            GmaError::UnsafePath { name, index } => {
+                write!(f, "unsafe path for entry #{index}: {name}")
+            }
+            GmaError::ReservedName { name, index } => {
+                write!(f, "reserved file name for entry #{index}: {name}")
+            }
+            GmaError::QuotaExceeded {
+                category,
+                size,
+                limit,
+            } => write!(
+                f,
+                "{category}/ would take {size} bytes, over its {limit} byte quota"
+            ),
+            GmaError::NotWhitelisted(names) => {
+                write!(f, "not allowed in an addon: {}", names.join(", "))
             }
+            GmaError::NulInString { field } => write!(f, "NUL byte in the {field}"),
+            GmaError::InvalidSteamId(s) => write!(f, "not a Steam ID: {s}"),
+            GmaError::InvalidMetadata(why) => write!(f, "invalid addon metadata: {why}"),
+            GmaError::Cancelled => write!(f, "cancelled"),
+            GmaError::UnexpectedEof => write!(f, "unexpected end of input"),
+            GmaError::EntryOverrun { name, declared } => {
+                write!(
+                    f,
+                    "entry {name} is longer than its declared {declared} bytes"
+                )
+            }
+            GmaError::CrcMismatch {
+                name,
+                declared,
+                actual,
+            } => write!(
+                f,
+                "entry {name} fails its CRC check: declared {declared:08x}, got {actual:08x}"
+            ),
+            GmaError::InvalidUtf8 { name_bytes, index } => write!(
+                f,
+                "name of entry #{index} isn't UTF-8: {}",
+                String::from_utf8_lossy(name_bytes)
+            ),
+            GmaError::Parse {
+                offset,
+                field,
+                source,
+            } => write!(f, "{source} in the {field} at byte {offset}"),
         }
     }
 }
 
-impl std::error::Error for GmaError {
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        if let GmaError::Io(e) = self {
-            Some(e)
-        } else {
-            None
+impl core::error::Error for GmaError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            GmaError::Io(e) => Some(e),
+            GmaError::Parse { source, .. } => Some(source.as_ref()),
+            _ => None,
         }
     }
 }
 
+/// Broad class of a [`GmaError`], from [`GmaError::kind`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// The underlying reader or writer failed.
+    Io,
+    /// The input isn't a well-formed archive: bad magic, truncated, a bad CRC.
+    Corrupt,
+    /// A well-formed archive in a format version this crate can't read.
+    Unsupported,
+    /// A configured or format limit was exceeded.
+    Limit,
+    /// Aborted through a cancel flag.
+    Cancelled,
+    /// Refused names, metadata or arguments: duplicates, unsafe paths, bad
+    /// addon.json, and so on.
+    InvalidInput,
+}
+
+impl GmaError {
+    /// The broad class of this error, for mapping to statuses or retry policies
+    /// without matching every variant. An `Io` error hitting the end of input
+    /// while parsing counts as [`ErrorKind::Corrupt`].
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            #[cfg(feature = "std")]
+            GmaError::Io(_) => ErrorKind::Io,
+            GmaError::InvalidHeader(_)
+            | GmaError::MissingNullTerminator
+            | GmaError::SizeOutOfRange(_)
+            | GmaError::TrailingMarkerMismatch { .. }
+            | GmaError::TruncatedEntry { .. }
+            | GmaError::InvalidFileIndex { .. }
+            | GmaError::CrcMismatch { .. }
+            | GmaError::UnexpectedEof => ErrorKind::Corrupt,
+            GmaError::InvalidVersion(_) => ErrorKind::Unsupported,
+            GmaError::ArchiveTooLarge { .. }
+            | GmaError::OverSizeLimit { .. }
+            | GmaError::TooManyEntries { .. }
+            | GmaError::EntryTooLarge { .. }
+            | GmaError::NameTooLong { .. }
+            | GmaError::HeaderStringTooLong { .. }
+            | GmaError::TooManyRequiredContent { .. }
+            | GmaError::QuotaExceeded { .. } => ErrorKind::Limit,
+            GmaError::Cancelled => ErrorKind::Cancelled,
+            GmaError::DuplicateEntry(_)
+            | GmaError::InvalidAddonJson(_)
+            | GmaError::InvalidLicenses(_)
+            | GmaError::UnsafePath { .. }
+            | GmaError::ReservedName { .. }
+            | GmaError::NotWhitelisted(_)
+            | GmaError::NulInString { .. }
+            | GmaError::InvalidSteamId(_)
+            | GmaError::InvalidMetadata(_)
+            | GmaError::InvalidUtf8 { .. }
+            | GmaError::EntryOverrun { .. } => ErrorKind::InvalidInput,
+            GmaError::Parse { source, .. } => match source.kind() {
+                // Running out of input mid-field means the archive is cut short
+                ErrorKind::Io if source.is_eof() => ErrorKind::Corrupt,
+                kind => kind,
+            },
+        }
+    }
+
+    fn is_eof(&self) -> bool {
+        match self {
+            #[cfg(feature = "std")]
+            GmaError::Io(e) => e.kind() == io::ErrorKind::UnexpectedEof,
+            _ => false,
+        }
+    }
+
+    /// Wrap in [`GmaError::Parse`], unless this is a cancellation.
+    pub(crate) fn at(self, offset: u64, field: ParseField) -> Self {
+        match self {
+            GmaError::Cancelled => self,
+            source => GmaError::Parse {
+                offset,
+                field,
+                source: Box::new(source),
+            },
+        }
+    }
+}
+
+#[cfg(feature = "std")]
 impl From<io::Error> for GmaError {
     fn from(e: io::Error) -> Self {
+        if e.get_ref().is_some_and(|inner| inner.is::<Cancelled>()) {
+            return GmaError::Cancelled;
+        }
         GmaError::Io(e)
     }
 }
+
+/// Payload of the `io::Error` raised inside the reader or writer when a cancel
+/// flag trips, turned back into [`GmaError::Cancelled`] by the `From` conversion.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub(crate) struct Cancelled;
+
+#[cfg(feature = "std")]
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("cancelled")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Cancelled {}
@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+
+use crate::json::{self, Value};
+use crate::{GMAFile, GmaError, wildcard};
+
+/// Conventional entry name for [`Licenses`].
+pub const LICENSES_FILE: &str = "addon_licenses.json";
+
+/// Attribution for third-party content, stored in an `addon_licenses.json` entry:
+///
+/// ```json
+/// { "licenses": [ { "paths": ["models/props/*"], "license": "CC-BY-4.0",
+///                   "author": "someone", "source": "https://example.com" } ] }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct Licenses {
+    pub entries: Vec<LicenseEntry>,
+}
+
+/// One license covering the files matched by `paths`.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct LicenseEntry {
+    /// Entry paths or gmad-style wildcards, matched case-insensitively.
+    pub paths: Vec<String>,
+    /// License name or SPDX identifier.
+    pub license: String,
+    pub author: Option<String>,
+    /// Where the content came from.
+    pub source: Option<String>,
+}
+
+impl Licenses {
+    pub fn parse(text: &str) -> Result<Self, GmaError> {
+        let bad = |why: &str| GmaError::InvalidLicenses(why.to_owned());
+        let root = json::parse(text).map_err(GmaError::InvalidLicenses)?;
+        let list = root
+            .get("licenses")
+            .and_then(Value::as_array)
+            .ok_or_else(|| bad("expected a \"licenses\" array"))?;
+        let entries = list
+            .iter()
+            .map(|item| {
+                let paths = item
+                    .get("paths")
+                    .and_then(Value::as_array)
+                    .ok_or_else(|| bad("\"paths\" must be an array of strings"))?
+                    .iter()
+                    .map(|p| p.as_str().map(str::to_owned))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| bad("\"paths\" must be an array of strings"))?;
+                let license = item
+                    .get("license")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| bad("\"license\" must be a string"))?
+                    .to_owned();
+                let optional = |key: &str| match item.get(key) {
+                    None | Some(Value::Null) => Ok(None),
+                    Some(v) => v.as_str().map(|s| Some(s.to_owned())).ok_or_else(|| {
+                        GmaError::InvalidLicenses(format!("\"{key}\" must be a string"))
+                    }),
+                };
+                Ok(LicenseEntry {
+                    paths,
+                    license,
+                    author: optional("author")?,
+                    source: optional("source")?,
+                })
+            })
+            .collect::<Result<_, GmaError>>()?;
+        Ok(Self { entries })
+    }
+
+    /// The JSON text, suitable for an `addon_licenses.json` entry.
+    pub fn to_json(&self) -> String {
+        let string = |s: &str| Value::String(s.to_owned());
+        let entries = self
+            .entries
+            .iter()
+            .map(|e| {
+                let mut obj = BTreeMap::new();
+                obj.insert(
+                    "paths".to_owned(),
+                    Value::Array(e.paths.iter().map(|p| string(p)).collect()),
+                );
+                obj.insert("license".to_owned(), string(&e.license));
+                if let Some(author) = &e.author {
+                    obj.insert("author".to_owned(), string(author));
+                }
+                if let Some(source) = &e.source {
+                    obj.insert("source".to_owned(), string(source));
+                }
+                Value::Object(obj)
+            })
+            .collect();
+        let mut root = BTreeMap::new();
+        root.insert("licenses".to_owned(), Value::Array(entries));
+        format!("{}\n", Value::Object(root))
+    }
+
+    /// The license covering `name`: the first entry with a matching path.
+    pub fn license_for(&self, name: &str) -> Option<&LicenseEntry> {
+        let name = name.to_ascii_lowercase();
+        self.entries
+            .iter()
+            .find(|e| e.paths.iter().any(|p| path_matches(p, &name)))
+    }
+
+    /// Paths or patterns that match none of `files`, e.g. after an asset was
+    /// renamed or dropped.
+    pub fn unmatched_paths<'a>(&'a self, files: &[GMAFile]) -> Vec<&'a str> {
+        let names: Vec<String> = files.iter().map(|f| f.name.to_ascii_lowercase()).collect();
+        self.entries
+            .iter()
+            .flat_map(|e| &e.paths)
+            .filter(|p| !names.iter().any(|n| path_matches(p, n)))
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+fn path_matches(pattern: &str, lower_name: &str) -> bool {
+    wildcard::matches(&pattern.replace('\\', "/").to_ascii_lowercase(), lower_name)
+}
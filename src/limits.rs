@@ -0,0 +1,161 @@
+use std::sync::{Arc, Condvar, Mutex};
+
+/// Caps shared by every extraction that is handed a clone, so many concurrent
+/// operations together stay within a file-handle and memory budget.
+///
+/// Operations block until the budget frees up rather than failing.
+#[derive(Clone, Debug)]
+pub struct Limits {
+    shared: Arc<Shared>,
+}
+
+#[derive(Debug)]
+struct Shared {
+    max_files: usize,
+    max_bytes: u64,
+    used: Mutex<Used>,
+    freed: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct Used {
+    files: usize,
+    bytes: u64,
+    /// Most of each ever held at once.
+    #[cfg(test)]
+    peak: (usize, u64),
+}
+
+impl Limits {
+    /// At most `max_open_files` entries are being copied at once (each holding
+    /// its output file, and in parallel extraction a worker's source handle),
+    /// with at most `max_in_flight_bytes` of their content outstanding.
+    ///
+    /// An entry larger than the byte budget waits until it can have it all.
+    pub fn new(max_open_files: usize, max_in_flight_bytes: u64) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                max_files: max_open_files.max(1),
+                max_bytes: max_in_flight_bytes.max(1),
+                used: Mutex::new(Used::default()),
+                freed: Condvar::new(),
+            }),
+        }
+    }
+
+    pub fn max_open_files(&self) -> usize {
+        self.shared.max_files
+    }
+
+    pub fn max_in_flight_bytes(&self) -> u64 {
+        self.shared.max_bytes
+    }
+
+    pub(crate) fn acquire_file(&self) -> Permit<'_> {
+        self.acquire(1, 0)
+    }
+
+    pub(crate) fn acquire_bytes(&self, bytes: u64) -> Permit<'_> {
+        self.acquire(0, bytes.min(self.shared.max_bytes))
+    }
+
+    fn acquire(&self, files: usize, bytes: u64) -> Permit<'_> {
+        let s = &*self.shared;
+        let mut used = s.used.lock().unwrap();
+        while used.files + files > s.max_files || used.bytes + bytes > s.max_bytes {
+            used = s.freed.wait(used).unwrap();
+        }
+        used.files += files;
+        used.bytes += bytes;
+        #[cfg(test)]
+        {
+            used.peak.0 = used.peak.0.max(used.files);
+            used.peak.1 = used.peak.1.max(used.bytes);
+        }
+        Permit {
+            limits: self,
+            files,
+            bytes,
+        }
+    }
+}
+
+/// Share of a [`Limits`] budget, returned on drop.
+pub(crate) struct Permit<'a> {
+    limits: &'a Limits,
+    files: usize,
+    bytes: u64,
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        let s = &*self.limits.shared;
+        let mut used = s.used.lock().unwrap();
+        used.files -= self.files;
+        used.bytes -= self.bytes;
+        s.freed.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::{self, Cursor, Read, Seek, SeekFrom};
+    use std::thread;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{Builder, Extractor};
+
+    /// A source slow enough that workers overlap.
+    struct Slow(Cursor<Vec<u8>>);
+
+    impl Read for Slow {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            thread::sleep(Duration::from_millis(1));
+            self.0.read(buf)
+        }
+    }
+
+    impl Seek for Slow {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.0.seek(pos)
+        }
+    }
+
+    #[test]
+    fn extract_parallel_stays_within_the_caps() {
+        let mut b = Builder::new("t", 1);
+        for i in 0..32 {
+            b.file_from_bytes(format!("lua/f{i}.lua"), vec![b'x'; 100]);
+        }
+        let data = b.write_to_vec().unwrap();
+        let dir = std::env::temp_dir().join(format!("gma-lite-limits-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let limits = Limits::new(2, 150);
+        let mut ex = Extractor::new();
+        ex.set_threads(6);
+        ex.set_limits(limits.clone());
+        let written = ex
+            .extract_parallel(|| Ok(Slow(Cursor::new(data.clone()))), &dir)
+            .unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(written.len(), 32);
+
+        let used = limits.shared.used.lock().unwrap();
+        assert_eq!((used.files, used.bytes), (0, 0));
+        // Two workers ran at once, but never a third, nor a second entry's bytes
+        assert_eq!(used.peak, (2, 100));
+    }
+
+    #[test]
+    fn oversized_entries_take_the_whole_budget() {
+        let limits = Limits::new(1, 10);
+        let permit = limits.acquire_bytes(1000);
+        assert_eq!(permit.bytes, 10);
+        drop(permit);
+        let _file = limits.acquire_file();
+        assert_eq!(limits.shared.used.lock().unwrap().files, 1);
+    }
+}
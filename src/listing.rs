@@ -0,0 +1,213 @@
+use std::collections::BTreeMap;
+
+use crate::base64;
+use crate::json::{self, Value};
+use crate::{AddonInfo, AddonMetadata, EntryMeta, GMAFile, GmaError, MIN_VERSION, VERSION};
+
+/// A JSON listing of an archive: `"metadata"` as in [`AddonMetadata::to_json`]
+/// and `"entries"` as in [`EntryMeta::to_json`], without any content.
+pub fn listing_json(info: &AddonInfo, entries: &[EntryMeta]) -> String {
+    let mut root = BTreeMap::new();
+    root.insert("metadata".to_owned(), metadata_value(&info.metadata()));
+    root.insert(
+        "entries".to_owned(),
+        Value::Array(
+            entries
+                .iter()
+                .map(|e| entry_value(&e.name, e.size, e.crc, e.offset))
+                .collect(),
+        ),
+    );
+    format!("{}\n", Value::Object(root))
+}
+
+impl EntryMeta {
+    /// `{"name", "size", "crc", "offset"}` as JSON.
+    pub fn to_json(&self) -> String {
+        entry_value(&self.name, self.size, self.crc, self.offset).to_string()
+    }
+}
+
+impl<C: AsRef<[u8]>> GMAFile<C> {
+    /// Like [`EntryMeta::to_json`], plus the content as base64 under
+    /// `"content"` if `with_content` is set.
+    pub fn to_json(&self, with_content: bool) -> String {
+        let mut value = entry_value(&self.name, self.size, self.crc, self.offset);
+        if with_content && let Value::Object(map) = &mut value {
+            map.insert(
+                "content".to_owned(),
+                Value::String(base64::encode(self.content.as_ref())),
+            );
+        }
+        value.to_string()
+    }
+}
+
+impl AddonMetadata {
+    /// Every field as JSON. The steam id is a string, since JSON numbers can't
+    /// hold all 64-bit ids exactly; a missing timestamp is `null`.
+    pub fn to_json(&self) -> String {
+        metadata_value(self).to_string()
+    }
+
+    /// Read back [`to_json`](Self::to_json) output. Missing fields keep their
+    /// defaults; the steam id may also be a number. Fails on a negative
+    /// timestamp or a version outside [`MIN_VERSION`] to [`VERSION`].
+    pub fn from_json(text: &str) -> Result<Self, GmaError> {
+        let bad = |why: String| GmaError::InvalidMetadata(why);
+        let root = json::parse(text).map_err(bad)?;
+        if !matches!(root, Value::Object(_)) {
+            return Err(bad("expected an object".into()));
+        }
+        let string = |key: &str| match root.get(key) {
+            None | Some(Value::Null) => Ok(String::new()),
+            Some(Value::String(s)) => Ok(s.clone()),
+            Some(_) => Err(bad(format!("\"{key}\" must be a string"))),
+        };
+        let number = |key: &str| match root.get(key) {
+            None | Some(Value::Null) => Ok(None),
+            Some(Value::Number(n)) if n.fract() == 0.0 => Ok(Some(*n)),
+            Some(_) => Err(bad(format!("\"{key}\" must be a whole number"))),
+        };
+        let tags = match root.get("tags") {
+            None | Some(Value::Null) => Vec::new(),
+            Some(Value::Array(items)) => items
+                .iter()
+                .map(|t| t.as_str().map(str::to_owned))
+                .collect::<Option<_>>()
+                .ok_or_else(|| bad("\"tags\" must be an array of strings".into()))?,
+            Some(_) => return Err(bad("\"tags\" must be an array of strings".into())),
+        };
+        // A string id can't go through f64 without losing digits
+        let steam_id64 = match root.get("steam_id64") {
+            Some(Value::String(s)) => s
+                .parse()
+                .map_err(|_| bad("\"steam_id64\" must be a whole number".into()))?,
+            _ => number("steam_id64")?.map_or(0, |n| n as i64),
+        };
+        let timestamp = match number("timestamp")? {
+            Some(n) if n < 0.0 => return Err(bad("\"timestamp\" must not be negative".into())),
+            n => n.map(|n| n as u64),
+        };
+        let version = match number("version")? {
+            None => VERSION,
+            Some(n) if (f64::from(MIN_VERSION)..=f64::from(VERSION)).contains(&n) => n as i8,
+            Some(n) => {
+                return Err(bad(format!(
+                    "\"version\" is {n}, expected {MIN_VERSION} to {VERSION}"
+                )));
+            }
+        };
+        Ok(Self {
+            title: string("title")?,
+            description: string("description")?,
+            author: string("author")?,
+            addon_type: string("type")?,
+            tags,
+            steam_id64,
+            timestamp,
+            version,
+        })
+    }
+}
+
+fn entry_value(name: &str, size: u64, crc: u32, offset: u64) -> Value {
+    let mut map = BTreeMap::new();
+    map.insert("name".to_owned(), Value::String(name.to_owned()));
+    map.insert("size".to_owned(), Value::Number(size as f64));
+    map.insert("crc".to_owned(), Value::Number(crc as f64));
+    map.insert("offset".to_owned(), Value::Number(offset as f64));
+    Value::Object(map)
+}
+
+fn metadata_value(m: &AddonMetadata) -> Value {
+    let string = |s: &str| Value::String(s.to_owned());
+    let mut map = BTreeMap::new();
+    map.insert("title".to_owned(), string(&m.title));
+    map.insert("description".to_owned(), string(&m.description));
+    map.insert("author".to_owned(), string(&m.author));
+    map.insert("type".to_owned(), string(&m.addon_type));
+    map.insert(
+        "tags".to_owned(),
+        Value::Array(m.tags.iter().map(|t| string(t)).collect()),
+    );
+    map.insert("steam_id64".to_owned(), string(&m.steam_id64.to_string()));
+    map.insert(
+        "timestamp".to_owned(),
+        m.timestamp.map_or(Value::Null, |t| Value::Number(t as f64)),
+    );
+    map.insert("version".to_owned(), Value::Number(m.version as f64));
+    Value::Object(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metadata_round_trips() {
+        let m = AddonMetadata {
+            title: "t".into(),
+            description: "d \"quoted\"".into(),
+            author: "a".into(),
+            addon_type: "tool".into(),
+            tags: vec!["fun".into(), "build".into()],
+            steam_id64: 76561197960287930,
+            timestamp: Some(1_700_000_000),
+            version: 2,
+        };
+        assert_eq!(AddonMetadata::from_json(&m.to_json()).unwrap(), m);
+        assert_eq!(
+            AddonMetadata::from_json("{}").unwrap(),
+            AddonMetadata::default()
+        );
+    }
+
+    #[test]
+    fn out_of_range_numbers_are_errors() {
+        for text in [
+            r#"{"version": 4}"#,
+            r#"{"version": 0}"#,
+            r#"{"version": 300}"#,
+            r#"{"version": 1.5}"#,
+            r#"{"timestamp": -1}"#,
+        ] {
+            assert!(
+                matches!(
+                    AddonMetadata::from_json(text),
+                    Err(GmaError::InvalidMetadata(_))
+                ),
+                "{text}"
+            );
+        }
+        let m = AddonMetadata::from_json(r#"{"version": 1}"#).unwrap();
+        assert_eq!(m.version, 1);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde_writes_bytes_as_base64() {
+        let file = GMAFile {
+            name: "lua/a.lua".into(),
+            name_bytes: b"lua/a.lua".to_vec(),
+            content: b"print(1)".to_vec(),
+            size: 8,
+            crc: 1,
+            offset: 2,
+        };
+        let json = serde_json::to_string(&file).unwrap();
+        assert_eq!(
+            json,
+            r#"{"name":"lua/a.lua","name_bytes":"bHVhL2EubHVh","content":"cHJpbnQoMSk=","size":8,"crc":1,"offset":2}"#
+        );
+        assert_eq!(serde_json::from_str::<GMAFile>(&json).unwrap(), file);
+
+        let borrowed = file.meta().with_content(&file.content[..]);
+        assert_eq!(serde_json::to_string(&borrowed).unwrap(), json);
+        let meta = serde_json::to_string(&file.meta()).unwrap();
+        assert_eq!(
+            serde_json::from_str::<EntryMeta>(&meta).unwrap(),
+            file.meta()
+        );
+    }
+}
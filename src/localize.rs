@@ -0,0 +1,205 @@
+//! Stable error codes and machine-readable fields, for showing [`GmaError`]s in
+//! other languages.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::GmaError;
+
+impl GmaError {
+    /// A stable identifier for the variant, e.g. `"truncated_entry"`. Codes never
+    /// change once released, unlike the English [`Display`](core::fmt::Display) text.
+    pub fn code(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "std")]
+            GmaError::Io(_) => "io",
+            GmaError::InvalidHeader(_) => "invalid_header",
+            GmaError::InvalidVersion(_) => "invalid_version",
+            GmaError::MissingNullTerminator => "missing_null_terminator",
+            GmaError::SizeOutOfRange(_) => "size_out_of_range",
+            GmaError::TrailingMarkerMismatch { .. } => "trailing_marker_mismatch",
+            GmaError::DuplicateEntry(_) => "duplicate_entry",
+            GmaError::TruncatedEntry { .. } => "truncated_entry",
+            GmaError::InvalidAddonJson(_) => "invalid_addon_json",
+            GmaError::InvalidLicenses(_) => "invalid_licenses",
+            GmaError::ArchiveTooLarge { .. } => "archive_too_large",
+            GmaError::OverSizeLimit { .. } => "over_size_limit",
+            GmaError::TooManyEntries { .. } => "too_many_entries",
+            GmaError::EntryTooLarge { .. } => "entry_too_large",
+            GmaError::NameTooLong { .. } => "name_too_long",
+            GmaError::HeaderStringTooLong { .. } => "header_string_too_long",
+            GmaError::TooManyRequiredContent { .. } => "too_many_required_content",
+            GmaError::InvalidFileIndex { .. } => "invalid_file_index",
+            GmaError::UnsafePath { .. } => "unsafe_path",
+            GmaError::ReservedName { .. } => "reserved_name",
+            GmaError::QuotaExceeded { .. } => "quota_exceeded",
+            GmaError::NotWhitelisted(_) => "not_whitelisted",
+            GmaError::NulInString { .. } => "nul_in_string",
+            GmaError::InvalidSteamId(_) => "invalid_steam_id",
+            GmaError::InvalidMetadata(_) => "invalid_metadata",
+            GmaError::Cancelled => "cancelled",
+            GmaError::UnexpectedEof => "unexpected_eof",
+            GmaError::EntryOverrun { .. } => "entry_overrun",
+            GmaError::CrcMismatch { .. } => "crc_mismatch",
+            GmaError::InvalidUtf8 { .. } => "invalid_utf8",
+            GmaError::Parse { .. } => "parse",
+        }
+    }
+
+    /// The variant's data as `(field, value)` pairs, named as in the enum
+    /// (tuple variants use `value`, `detail` for free-form text).
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        match self {
+            #[cfg(feature = "std")]
+            GmaError::Io(e) => vec![("detail", e.to_string())],
+            GmaError::InvalidHeader(got) => {
+                vec![("value", String::from_utf8_lossy(got).into_owned())]
+            }
+            GmaError::InvalidVersion(v) => vec![("value", v.to_string())],
+            GmaError::MissingNullTerminator | GmaError::Cancelled | GmaError::UnexpectedEof => {
+                Vec::new()
+            }
+            GmaError::SizeOutOfRange(size) => vec![("value", size.to_string())],
+            GmaError::TrailingMarkerMismatch { found, kind } => {
+                vec![("found", found.to_string()), ("kind", kind.to_string())]
+            }
+            GmaError::DuplicateEntry(name) | GmaError::InvalidSteamId(name) => {
+                vec![("value", name.clone())]
+            }
+            GmaError::TruncatedEntry {
+                name,
+                read,
+                expected,
+            } => vec![
+                ("name", name.clone()),
+                ("read", read.to_string()),
+                ("expected", expected.to_string()),
+            ],
+            GmaError::InvalidAddonJson(why)
+            | GmaError::InvalidLicenses(why)
+            | GmaError::InvalidMetadata(why) => {
+                vec![("detail", why.clone())]
+            }
+            GmaError::ArchiveTooLarge { size, limit } => {
+                vec![("size", size.to_string()), ("limit", limit.to_string())]
+            }
+            GmaError::OverSizeLimit {
+                size,
+                limit,
+                largest,
+            } => {
+                let largest: Vec<String> = largest
+                    .iter()
+                    .map(|(name, size)| format!("{name} ({size})"))
+                    .collect();
+                vec![
+                    ("size", size.to_string()),
+                    ("limit", limit.to_string()),
+                    ("largest", largest.join(", ")),
+                ]
+            }
+            GmaError::TooManyEntries { limit } => vec![("limit", limit.to_string())],
+            GmaError::EntryTooLarge { name, size, limit } => vec![
+                ("name", name.clone()),
+                ("size", size.to_string()),
+                ("limit", limit.to_string()),
+            ],
+            GmaError::NameTooLong { index, limit } => {
+                vec![("index", index.to_string()), ("limit", limit.to_string())]
+            }
+            GmaError::HeaderStringTooLong { field, limit } => {
+                vec![("field", field.to_string()), ("limit", limit.to_string())]
+            }
+            GmaError::TooManyRequiredContent { limit } => vec![("limit", limit.to_string())],
+            GmaError::InvalidFileIndex { expected, got } => {
+                vec![("expected", expected.to_string()), ("got", got.to_string())]
+            }
+            GmaError::UnsafePath { name, index } | GmaError::ReservedName { name, index } => {
+                vec![("name", name.clone()), ("index", index.to_string())]
+            }
+            GmaError::NotWhitelisted(names) => vec![("value", names.join(", "))],
+            GmaError::NulInString { field } => vec![("field", field.to_string())],
+            GmaError::QuotaExceeded {
+                category,
+                size,
+                limit,
+            } => vec![
+                ("category", category.clone()),
+                ("size", size.to_string()),
+                ("limit", limit.to_string()),
+            ],
+            GmaError::EntryOverrun { name, declared } => {
+                vec![("name", name.clone()), ("declared", declared.to_string())]
+            }
+            GmaError::CrcMismatch {
+                name,
+                declared,
+                actual,
+            } => vec![
+                ("name", name.clone()),
+                ("declared", format!("{declared:08x}")),
+                ("actual", format!("{actual:08x}")),
+            ],
+            GmaError::InvalidUtf8 { name_bytes, index } => vec![
+                ("name", String::from_utf8_lossy(name_bytes).into_owned()),
+                ("index", index.to_string()),
+            ],
+            GmaError::Parse {
+                offset,
+                field,
+                source,
+            } => {
+                let mut fields = vec![("offset", offset.to_string()), ("field", field.to_string())];
+                if let Some(index) = field.index() {
+                    fields.push(("index", index.to_string()));
+                }
+                fields.push(("cause", source.code().to_string()));
+                fields.push(("detail", source.to_string()));
+                fields
+            }
+        }
+    }
+
+    /// Render the message with a caller-supplied template for this error's
+    /// [`code`](Self::code), substituting `{field}` placeholders from
+    /// [`fields`](Self::fields) (`{{` / `}}` for literal braces). Falls back to
+    /// the English text when `templates` has nothing for the code.
+    ///
+    /// E.g. `"Das Archiv hat mehr als {limit} Dateien"` for `"too_many_entries"`.
+    pub fn localize<'t>(&self, templates: impl Fn(&'static str) -> Option<&'t str>) -> String {
+        let Some(template) = templates(self.code()) else {
+            return self.to_string();
+        };
+        let fields = self.fields();
+        let mut out = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(i) = rest.find(['{', '}']) {
+            out.push_str(&rest[..i]);
+            let tail = &rest[i..];
+            if tail.starts_with("{{") || tail.starts_with("}}") {
+                out.push_str(&tail[..1]);
+                rest = &tail[2..];
+                continue;
+            }
+            let field = tail[1..]
+                .find('}')
+                .filter(|_| tail.starts_with('{'))
+                .map(|end| &tail[1..1 + end]);
+            match field.and_then(|name| fields.iter().find(|(f, _)| *f == name)) {
+                Some((name, value)) => {
+                    out.push_str(value);
+                    rest = &tail[name.len() + 2..];
+                }
+                // Unknown placeholders and stray braces are kept as written
+                None => {
+                    out.push(tail.as_bytes()[0] as char);
+                    rest = &tail[1..];
+                }
+            }
+        }
+        out.push_str(rest);
+        out
+    }
+}
@@ -0,0 +1,96 @@
+/// Where a Lua file runs, following Garry's Mod path conventions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LuaRealm {
+    /// Runs on both client and server.
+    Shared,
+    Client,
+    Server,
+    /// Only runs if other code includes it; realm depends on the includer.
+    Unknown,
+}
+
+/// Whether `name` is a Lua file (`.lua`, case-insensitive).
+#[inline]
+pub fn is_lua(name: &str) -> bool {
+    let name = name.as_bytes();
+    name.len() >= 4 && name[name.len() - 4..].eq_ignore_ascii_case(b".lua")
+}
+
+/// Whether `name` is auto-run by the game: a Lua file directly in `lua/autorun/`,
+/// `lua/autorun/client/` or `lua/autorun/server/`.
+pub fn is_autorun(name: &str) -> bool {
+    is_lua(name) && autorun_realm(&normalize(name)).is_some()
+}
+
+fn autorun_realm(path: &str) -> Option<LuaRealm> {
+    let rest = path.strip_prefix("lua/autorun/")?;
+    match rest.split_once('/') {
+        None => Some(LuaRealm::Shared),
+        Some(("client", file)) if !file.contains('/') => Some(LuaRealm::Client),
+        Some(("server", file)) if !file.contains('/') => Some(LuaRealm::Server),
+        Some(_) => None,
+    }
+}
+
+/// Classify a Lua file by realm; `None` if `name` isn't a Lua file.
+///
+/// Uses the engine's fixed locations (`lua/autorun/{client,server}/`,
+/// entity/weapon/gamemode `init.lua` / `cl_init.lua` / `shared.lua`, client-only
+/// folders such as `lua/vgui/`) and falls back to the common `cl_` / `sv_` /
+/// `sh_` file-name prefixes.
+pub fn lua_realm(name: &str) -> Option<LuaRealm> {
+    if !is_lua(name) {
+        return None;
+    }
+    let path = normalize(name);
+    let file = path.rsplit('/').next().unwrap_or(&path);
+
+    if let Some(realm) = autorun_realm(&path) {
+        return Some(realm);
+    }
+
+    const CLIENT_DIRS: &[&str] = &[
+        "lua/effects/",
+        "lua/vgui/",
+        "lua/postprocess/",
+        "lua/matproxy/",
+        "lua/skins/",
+    ];
+    if CLIENT_DIRS.iter().any(|d| path.starts_with(d)) {
+        return Some(LuaRealm::Client);
+    }
+    if path.starts_with("lua/weapons/gmod_tool/stools/") {
+        return Some(LuaRealm::Shared);
+    }
+
+    // Scripted entities/weapons and gamemodes load these by name.
+    let scripted = path.starts_with("lua/entities/")
+        || path.starts_with("lua/weapons/")
+        || (path.starts_with("gamemodes/") && path.contains("/gamemode/"));
+    if scripted {
+        match file {
+            "init.lua" => return Some(LuaRealm::Server),
+            "cl_init.lua" => return Some(LuaRealm::Client),
+            "shared.lua" => return Some(LuaRealm::Shared),
+            _ => {}
+        }
+        // Single-file entities/weapons (`lua/entities/foo.lua`) are shared.
+        if path.matches('/').count() == 2 && path.starts_with("lua/") {
+            return Some(LuaRealm::Shared);
+        }
+    }
+
+    Some(if file.starts_with("cl_") {
+        LuaRealm::Client
+    } else if file.starts_with("sv_") {
+        LuaRealm::Server
+    } else if file.starts_with("sh_") {
+        LuaRealm::Shared
+    } else {
+        LuaRealm::Unknown
+    })
+}
+
+fn normalize(name: &str) -> String {
+    name.replace('\\', "/").to_ascii_lowercase()
+}
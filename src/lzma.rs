@@ -0,0 +1,663 @@
+//! LZMA ("lzma alone" / `.lzma`) encoder, for the compressed archives the
+//! Workshop takes.
+//!
+//! A plain greedy encoder: hash-chain match finding, literals and simple
+//! matches (no repeat matches), default `lc=3 lp=0 pb=2` properties.
+
+use std::io::{self, Write};
+
+/// Dictionary size written in the header; distances never exceed it.
+const DICT_SIZE: u32 = 1 << 24;
+
+const LC: u32 = 3;
+const PB: u32 = 2;
+const POS_MASK: usize = (1 << PB) - 1;
+
+const MIN_MATCH: usize = 2;
+const MAX_MATCH: usize = 273;
+/// Matches shorter than this aren't looked for.
+const FIND_MIN: usize = 3;
+const CHAIN_DEPTH: usize = 24;
+const HASH_BITS: u32 = 20;
+
+const BIT_MODEL_TOTAL: u32 = 1 << 11;
+const MOVE_BITS: u32 = 5;
+const TOP: u32 = 1 << 24;
+
+const END_POS_MODEL: u32 = 14;
+const FULL_DISTANCES: usize = 1 << (END_POS_MODEL / 2);
+const ALIGN_BITS: u32 = 4;
+
+type Prob = u16;
+const PROB_INIT: Prob = (BIT_MODEL_TOTAL / 2) as Prob;
+
+/// Compress `data` into `w` as an `.lzma` stream with its size in the header.
+pub fn compress_lzma<W: Write>(data: &[u8], mut w: W) -> io::Result<()> {
+    let mut header = [0u8; 13];
+    header[0] = ((PB * 5) * 9 + LC) as u8;
+    header[1..5].copy_from_slice(&DICT_SIZE.to_le_bytes());
+    header[5..].copy_from_slice(&(data.len() as u64).to_le_bytes());
+    w.write_all(&header)?;
+
+    let mut enc = Encoder::new(&mut w);
+    enc.encode(data)?;
+    enc.rc.flush()
+}
+
+struct RangeEncoder<W> {
+    out: W,
+    buf: Vec<u8>,
+    low: u64,
+    range: u32,
+    cache: u8,
+    cache_size: u64,
+}
+
+impl<W: Write> RangeEncoder<W> {
+    fn new(out: W) -> Self {
+        Self {
+            out,
+            buf: Vec::with_capacity(64 * 1024),
+            low: 0,
+            range: u32::MAX,
+            cache: 0,
+            cache_size: 1,
+        }
+    }
+
+    fn shift_low(&mut self) -> io::Result<()> {
+        if (self.low as u32) < 0xFF00_0000 || (self.low >> 32) != 0 {
+            let carry = (self.low >> 32) as u8;
+            let mut temp = self.cache;
+            loop {
+                self.buf.push(temp.wrapping_add(carry));
+                temp = 0xFF;
+                self.cache_size -= 1;
+                if self.cache_size == 0 {
+                    break;
+                }
+            }
+            self.cache = (self.low >> 24) as u8;
+            if self.buf.len() >= 64 * 1024 {
+                self.out.write_all(&self.buf)?;
+                self.buf.clear();
+            }
+        }
+        self.cache_size += 1;
+        self.low = (self.low & 0x00FF_FFFF) << 8;
+        Ok(())
+    }
+
+    fn bit(&mut self, prob: &mut Prob, bit: u32) -> io::Result<()> {
+        let bound = (self.range >> 11) * u32::from(*prob);
+        if bit == 0 {
+            self.range = bound;
+            *prob += ((BIT_MODEL_TOTAL - u32::from(*prob)) >> MOVE_BITS) as Prob;
+        } else {
+            self.low += u64::from(bound);
+            self.range -= bound;
+            *prob -= *prob >> MOVE_BITS;
+        }
+        while self.range < TOP {
+            self.range <<= 8;
+            self.shift_low()?;
+        }
+        Ok(())
+    }
+
+    fn direct_bits(&mut self, value: u32, count: u32) -> io::Result<()> {
+        for i in (0..count).rev() {
+            self.range >>= 1;
+            if (value >> i) & 1 != 0 {
+                self.low += u64::from(self.range);
+            }
+            if self.range < TOP {
+                self.range <<= 8;
+                self.shift_low()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Most significant bit first, through a tree of `1 << bits` probabilities.
+    fn tree(&mut self, probs: &mut [Prob], bits: u32, symbol: u32) -> io::Result<()> {
+        let mut m = 1;
+        for i in (0..bits).rev() {
+            let bit = (symbol >> i) & 1;
+            self.bit(&mut probs[m], bit)?;
+            m = (m << 1) | bit as usize;
+        }
+        Ok(())
+    }
+
+    /// Least significant bit first.
+    fn reverse_tree(&mut self, probs: &mut [Prob], bits: u32, mut symbol: u32) -> io::Result<()> {
+        let mut m = 1;
+        for _ in 0..bits {
+            let bit = symbol & 1;
+            self.bit(&mut probs[m], bit)?;
+            m = (m << 1) | bit as usize;
+            symbol >>= 1;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        for _ in 0..5 {
+            self.shift_low()?;
+        }
+        self.out.write_all(&self.buf)?;
+        self.buf.clear();
+        self.out.flush()
+    }
+}
+
+struct LenEncoder {
+    choice: Prob,
+    choice2: Prob,
+    low: [[Prob; 8]; 1 << PB],
+    mid: [[Prob; 8]; 1 << PB],
+    high: [Prob; 256],
+}
+
+impl LenEncoder {
+    fn new() -> Self {
+        Self {
+            choice: PROB_INIT,
+            choice2: PROB_INIT,
+            low: [[PROB_INIT; 8]; 1 << PB],
+            mid: [[PROB_INIT; 8]; 1 << PB],
+            high: [PROB_INIT; 256],
+        }
+    }
+
+    fn encode<W: Write>(
+        &mut self,
+        rc: &mut RangeEncoder<W>,
+        len: usize,
+        pos_state: usize,
+    ) -> io::Result<()> {
+        let sym = (len - MIN_MATCH) as u32;
+        if sym < 8 {
+            rc.bit(&mut self.choice, 0)?;
+            rc.tree(&mut self.low[pos_state], 3, sym)
+        } else if sym < 16 {
+            rc.bit(&mut self.choice, 1)?;
+            rc.bit(&mut self.choice2, 0)?;
+            rc.tree(&mut self.mid[pos_state], 3, sym - 8)
+        } else {
+            rc.bit(&mut self.choice, 1)?;
+            rc.bit(&mut self.choice2, 1)?;
+            rc.tree(&mut self.high, 8, sym - 16)
+        }
+    }
+}
+
+struct Encoder<W> {
+    rc: RangeEncoder<W>,
+    state: usize,
+    rep0: u32,
+    is_match: [[Prob; 1 << PB]; 12],
+    is_rep: [Prob; 12],
+    literal: Vec<Prob>,
+    len: LenEncoder,
+    pos_slot: [[Prob; 64]; 4],
+    /// Reverse trees of the short distance footers, laid out back to back as in
+    /// the reference encoder; one longer so the first tree's unused root fits.
+    pos_special: [Prob; FULL_DISTANCES - END_POS_MODEL as usize + 1],
+    align: [Prob; 1 << ALIGN_BITS],
+}
+
+impl<W: Write> Encoder<W> {
+    fn new(out: W) -> Self {
+        Self {
+            rc: RangeEncoder::new(out),
+            state: 0,
+            rep0: 0,
+            is_match: [[PROB_INIT; 1 << PB]; 12],
+            is_rep: [PROB_INIT; 12],
+            literal: vec![PROB_INIT; 0x300 << LC],
+            len: LenEncoder::new(),
+            pos_slot: [[PROB_INIT; 64]; 4],
+            pos_special: [PROB_INIT; FULL_DISTANCES - END_POS_MODEL as usize + 1],
+            align: [PROB_INIT; 1 << ALIGN_BITS],
+        }
+    }
+
+    fn encode(&mut self, data: &[u8]) -> io::Result<()> {
+        let mut finder = MatchFinder::new(data.len());
+        let mut pos = 0;
+        while pos < data.len() {
+            let (len, dist) = finder.longest(data, pos);
+            if len >= FIND_MIN {
+                self.encode_match(pos, len, dist)?;
+                for p in pos..pos + len {
+                    finder.insert(data, p);
+                }
+                pos += len;
+            } else {
+                self.encode_literal(data, pos)?;
+                finder.insert(data, pos);
+                pos += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn encode_literal(&mut self, data: &[u8], pos: usize) -> io::Result<()> {
+        let pos_state = pos & POS_MASK;
+        self.rc.bit(&mut self.is_match[self.state][pos_state], 0)?;
+
+        let prev = if pos > 0 { data[pos - 1] } else { 0 };
+        let base = 0x300 * (usize::from(prev) >> (8 - LC));
+        let probs = &mut self.literal[base..base + 0x300];
+        let mut symbol = u32::from(data[pos]) | 0x100;
+        if self.state < 7 {
+            while symbol < 0x10000 {
+                self.rc
+                    .bit(&mut probs[(symbol >> 8) as usize], (symbol >> 7) & 1)?;
+                symbol <<= 1;
+            }
+        } else {
+            // After a match the byte at the last distance steers the model
+            let mut match_byte = u32::from(data[pos - self.rep0 as usize - 1]);
+            let mut offs = 0x100;
+            while symbol < 0x10000 {
+                match_byte <<= 1;
+                let i = offs + (match_byte & offs) + (symbol >> 8);
+                self.rc.bit(&mut probs[i as usize], (symbol >> 7) & 1)?;
+                symbol <<= 1;
+                offs &= !(match_byte ^ symbol);
+            }
+        }
+
+        self.state = match self.state {
+            0..=3 => 0,
+            4..=9 => self.state - 3,
+            _ => self.state - 6,
+        };
+        Ok(())
+    }
+
+    /// A match of `len` bytes, `dist + 1` bytes back.
+    fn encode_match(&mut self, pos: usize, len: usize, dist: u32) -> io::Result<()> {
+        let pos_state = pos & POS_MASK;
+        self.rc.bit(&mut self.is_match[self.state][pos_state], 1)?;
+        self.rc.bit(&mut self.is_rep[self.state], 0)?;
+        self.len.encode(&mut self.rc, len, pos_state)?;
+
+        let len_state = (len - MIN_MATCH).min(3);
+        let slot = pos_slot(dist);
+        self.rc.tree(&mut self.pos_slot[len_state], 6, slot)?;
+        if slot >= 4 {
+            let footer_bits = (slot >> 1) - 1;
+            let base = (2 | (slot & 1)) << footer_bits;
+            let reduced = dist - base;
+            if slot < END_POS_MODEL {
+                let start = (base - slot) as usize;
+                self.rc
+                    .reverse_tree(&mut self.pos_special[start..], footer_bits, reduced)?;
+            } else {
+                self.rc
+                    .direct_bits(reduced >> ALIGN_BITS, footer_bits - ALIGN_BITS)?;
+                self.rc.reverse_tree(
+                    &mut self.align,
+                    ALIGN_BITS,
+                    reduced & ((1 << ALIGN_BITS) - 1),
+                )?;
+            }
+        }
+
+        self.rep0 = dist;
+        self.state = if self.state < 7 { 7 } else { 10 };
+        Ok(())
+    }
+}
+
+/// Slot of a 0-based distance: its two top bits, and how many follow them.
+fn pos_slot(dist: u32) -> u32 {
+    if dist < 4 {
+        return dist;
+    }
+    let bits = 31 - dist.leading_zeros();
+    (bits << 1) | ((dist >> (bits - 1)) & 1)
+}
+
+/// Hash chains over 3-byte prefixes, within the dictionary window.
+struct MatchFinder {
+    head: Vec<u32>,
+    prev: Vec<u32>,
+}
+
+impl MatchFinder {
+    fn new(len: usize) -> Self {
+        Self {
+            head: vec![u32::MAX; 1 << HASH_BITS],
+            prev: vec![u32::MAX; len.min(DICT_SIZE as usize)],
+        }
+    }
+
+    fn hash(data: &[u8], pos: usize) -> usize {
+        let v =
+            u32::from(data[pos]) | u32::from(data[pos + 1]) << 8 | u32::from(data[pos + 2]) << 16;
+        (v.wrapping_mul(0x9E37_79B1) >> (32 - HASH_BITS)) as usize
+    }
+
+    fn insert(&mut self, data: &[u8], pos: usize) {
+        if pos + FIND_MIN > data.len() {
+            return;
+        }
+        let h = Self::hash(data, pos);
+        let slot = pos % self.prev.len();
+        self.prev[slot] = self.head[h];
+        self.head[h] = pos as u32;
+    }
+
+    /// Longest earlier match at `pos` as `(len, dist)`; `len` is 0 if none.
+    fn longest(&self, data: &[u8], pos: usize) -> (usize, u32) {
+        if pos + FIND_MIN > data.len() {
+            return (0, 0);
+        }
+        let max = (data.len() - pos).min(MAX_MATCH);
+        let mut best = (0, 0);
+        let mut candidate = self.head[Self::hash(data, pos)];
+        for _ in 0..CHAIN_DEPTH {
+            if candidate == u32::MAX {
+                break;
+            }
+            let from = candidate as usize;
+            let dist = pos - from;
+            if dist == 0 || dist > self.prev.len() || dist > DICT_SIZE as usize {
+                break;
+            }
+            let len = data[from..]
+                .iter()
+                .zip(&data[pos..pos + max])
+                .take_while(|(a, b)| a == b)
+                .count();
+            if len > best.0 {
+                best = (len, (dist - 1) as u32);
+                if len == max {
+                    break;
+                }
+            }
+            candidate = self.prev[from % self.prev.len()];
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A straightforward decoder after the LZMA specification, rep matches and
+    /// all, to check the encoder against.
+    struct RangeDecoder<'a> {
+        data: &'a [u8],
+        range: u32,
+        code: u32,
+    }
+
+    impl<'a> RangeDecoder<'a> {
+        fn new(data: &'a [u8]) -> Self {
+            assert_eq!(data[0], 0);
+            Self {
+                code: u32::from_be_bytes(data[1..5].try_into().unwrap()),
+                data: &data[5..],
+                range: u32::MAX,
+            }
+        }
+
+        fn normalize(&mut self) {
+            if self.range < TOP {
+                self.range <<= 8;
+                self.code = self.code << 8 | u32::from(self.data[0]);
+                self.data = &self.data[1..];
+            }
+        }
+
+        fn bit(&mut self, prob: &mut Prob) -> u32 {
+            let bound = (self.range >> 11) * u32::from(*prob);
+            let bit = if self.code < bound {
+                self.range = bound;
+                *prob += ((BIT_MODEL_TOTAL - u32::from(*prob)) >> MOVE_BITS) as Prob;
+                0
+            } else {
+                self.range -= bound;
+                self.code -= bound;
+                *prob -= *prob >> MOVE_BITS;
+                1
+            };
+            self.normalize();
+            bit
+        }
+
+        fn direct_bits(&mut self, count: u32) -> u32 {
+            let mut value = 0;
+            for _ in 0..count {
+                self.range >>= 1;
+                let bit = u32::from(self.code >= self.range);
+                if bit == 1 {
+                    self.code -= self.range;
+                }
+                value = value << 1 | bit;
+                self.normalize();
+            }
+            value
+        }
+
+        fn tree(&mut self, probs: &mut [Prob], bits: u32) -> u32 {
+            let mut m = 1;
+            for _ in 0..bits {
+                m = m << 1 | self.bit(&mut probs[m as usize]);
+            }
+            m - (1 << bits)
+        }
+
+        fn reverse_tree(&mut self, probs: &mut [Prob], bits: u32) -> u32 {
+            let mut m = 1;
+            let mut symbol = 0;
+            for i in 0..bits {
+                let bit = self.bit(&mut probs[m]);
+                m = m << 1 | bit as usize;
+                symbol |= bit << i;
+            }
+            symbol
+        }
+    }
+
+    struct LenDecoder {
+        choice: Prob,
+        choice2: Prob,
+        low: [[Prob; 8]; 16],
+        mid: [[Prob; 8]; 16],
+        high: [Prob; 256],
+    }
+
+    impl LenDecoder {
+        fn new() -> Self {
+            Self {
+                choice: PROB_INIT,
+                choice2: PROB_INIT,
+                low: [[PROB_INIT; 8]; 16],
+                mid: [[PROB_INIT; 8]; 16],
+                high: [PROB_INIT; 256],
+            }
+        }
+
+        /// The length minus [`MIN_MATCH`].
+        fn decode(&mut self, rc: &mut RangeDecoder, pos_state: usize) -> usize {
+            let len = if rc.bit(&mut self.choice) == 0 {
+                rc.tree(&mut self.low[pos_state], 3)
+            } else if rc.bit(&mut self.choice2) == 0 {
+                8 + rc.tree(&mut self.mid[pos_state], 3)
+            } else {
+                16 + rc.tree(&mut self.high, 8)
+            };
+            len as usize
+        }
+    }
+
+    fn decompress(stream: &[u8]) -> Vec<u8> {
+        let mut d = u32::from(stream[0]);
+        let lc = d % 9;
+        d /= 9;
+        let lp = d % 5;
+        let pb = d / 5;
+        let size = u64::from_le_bytes(stream[5..13].try_into().unwrap()) as usize;
+        let mut rc = RangeDecoder::new(&stream[13..]);
+
+        let mut literal = vec![PROB_INIT; 0x300 << (lc + lp)];
+        let mut is_match = [PROB_INIT; 12 << 4];
+        let mut is_rep = [PROB_INIT; 12];
+        let mut is_rep_g0 = [PROB_INIT; 12];
+        let mut is_rep_g1 = [PROB_INIT; 12];
+        let mut is_rep_g2 = [PROB_INIT; 12];
+        let mut is_rep0_long = [PROB_INIT; 12 << 4];
+        let mut pos_slot = [[PROB_INIT; 64]; 4];
+        let mut pos_special = [PROB_INIT; 1 + FULL_DISTANCES - END_POS_MODEL as usize];
+        let mut align = [PROB_INIT; 1 << ALIGN_BITS];
+        let mut len_decoder = LenDecoder::new();
+        let mut rep_len_decoder = LenDecoder::new();
+
+        let mut out: Vec<u8> = Vec::with_capacity(size);
+        let mut state = 0;
+        let mut reps = [0usize; 4];
+        while out.len() < size {
+            let pos_state = out.len() & ((1 << pb) - 1);
+            if rc.bit(&mut is_match[state << 4 | pos_state]) == 0 {
+                let prev = usize::from(out.last().copied().unwrap_or(0));
+                let lit_state = (out.len() & ((1 << lp) - 1)) << lc | prev >> (8 - lc);
+                let probs = &mut literal[0x300 * lit_state..][..0x300];
+                let mut symbol = 1;
+                if state >= 7 {
+                    let mut match_byte = usize::from(out[out.len() - reps[0] - 1]);
+                    while symbol < 0x100 {
+                        let match_bit = (match_byte >> 7) & 1;
+                        match_byte <<= 1;
+                        let bit = rc.bit(&mut probs[((1 + match_bit) << 8) + symbol]) as usize;
+                        symbol = symbol << 1 | bit;
+                        if match_bit != bit {
+                            break;
+                        }
+                    }
+                }
+                while symbol < 0x100 {
+                    symbol = symbol << 1 | rc.bit(&mut probs[symbol]) as usize;
+                }
+                out.push((symbol - 0x100) as u8);
+                state = match state {
+                    0..=3 => 0,
+                    4..=9 => state - 3,
+                    _ => state - 6,
+                };
+                continue;
+            }
+
+            let len;
+            if rc.bit(&mut is_rep[state]) != 0 {
+                if rc.bit(&mut is_rep_g0[state]) == 0 {
+                    if rc.bit(&mut is_rep0_long[state << 4 | pos_state]) == 0 {
+                        state = if state < 7 { 9 } else { 11 };
+                        out.push(out[out.len() - reps[0] - 1]);
+                        continue;
+                    }
+                } else {
+                    let which = if rc.bit(&mut is_rep_g1[state]) == 0 {
+                        1
+                    } else if rc.bit(&mut is_rep_g2[state]) == 0 {
+                        2
+                    } else {
+                        3
+                    };
+                    reps[..=which].rotate_right(1);
+                }
+                len = rep_len_decoder.decode(&mut rc, pos_state);
+                state = if state < 7 { 8 } else { 11 };
+            } else {
+                reps.rotate_right(1);
+                len = len_decoder.decode(&mut rc, pos_state);
+                state = if state < 7 { 7 } else { 10 };
+
+                let slot = rc.tree(&mut pos_slot[len.min(3)], 6);
+                reps[0] = if slot < 4 {
+                    slot
+                } else {
+                    let footer_bits = (slot >> 1) - 1;
+                    let base = (2 | (slot & 1)) << footer_bits;
+                    if slot < END_POS_MODEL {
+                        let start = (base - slot) as usize;
+                        base + rc.reverse_tree(&mut pos_special[start..], footer_bits)
+                    } else {
+                        base + (rc.direct_bits(footer_bits - ALIGN_BITS) << ALIGN_BITS)
+                            + rc.reverse_tree(&mut align, ALIGN_BITS)
+                    }
+                } as usize;
+            }
+            for _ in 0..len + MIN_MATCH {
+                out.push(out[out.len() - reps[0] - 1]);
+            }
+        }
+        assert_eq!(out.len(), size, "match ran past the declared size");
+        out
+    }
+
+    fn round_trip(data: &[u8]) {
+        let mut stream = Vec::new();
+        compress_lzma(data, &mut stream).unwrap();
+        assert_eq!(stream[0], 0x5D);
+        assert_eq!(decompress(&stream), data);
+    }
+
+    #[test]
+    fn round_trips_through_a_reference_decoder() {
+        round_trip(b"");
+        round_trip(b"a");
+        round_trip(b"abcabcabcabcabc, abcabc and abc");
+
+        let text = "function ENT:Initialize()\n\tself:SetModel(\"models/x.mdl\")\nend\n";
+        round_trip(text.repeat(200).as_bytes());
+        // Longer than the longest match
+        round_trip(&[7u8; 5000]);
+
+        // Incompressible bytes, then a far copy of them
+        let mut x = 0x2545_F491_u32;
+        let mut noise: Vec<u8> = (0..70_000)
+            .map(|_| {
+                x ^= x << 13;
+                x ^= x >> 17;
+                x ^= x << 5;
+                x as u8
+            })
+            .collect();
+        noise.extend_from_within(..20_000);
+        round_trip(&noise);
+    }
+
+    #[test]
+    fn compresses_repetitive_data() {
+        let data = b"resource.AddFile(\"materials/x.vmt\")\n".repeat(1000);
+        let mut stream = Vec::new();
+        compress_lzma(&data, &mut stream).unwrap();
+        assert!(stream.len() < data.len() / 20, "{} bytes", stream.len());
+    }
+
+    #[test]
+    fn pos_slots() {
+        let cases = [
+            (0, 0),
+            (3, 3),
+            (4, 4),
+            (5, 4),
+            (6, 5),
+            (7, 5),
+            (8, 6),
+            (12, 7),
+        ];
+        for (dist, slot) in cases {
+            assert_eq!(pos_slot(dist), slot, "{dist}");
+        }
+        assert_eq!(pos_slot(DICT_SIZE - 1), 47);
+    }
+}
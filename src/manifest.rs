@@ -0,0 +1,60 @@
+use crate::sha::{self, Sha1, Sha256};
+
+/// Content hashes of one entry, for integrity manifests and cross-archive dedupe.
+///
+/// Produced by [`GmaReader::manifest`](crate::GmaReader::manifest), or collected on
+/// [`GmaArchive::manifest`](crate::GmaArchive::manifest) when
+/// [`ReadOptions::set_entry_hashes`](crate::ReadOptions::set_entry_hashes) is on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ManifestEntry {
+    pub name: String,
+    /// Bytes actually hashed.
+    pub size: u64,
+    pub sha256: [u8; 32],
+    /// Only computed with [`ReadOptions::set_entry_sha1`](crate::ReadOptions::set_entry_sha1).
+    pub sha1: Option<[u8; 20]>,
+}
+
+impl ManifestEntry {
+    pub fn sha256_hex(&self) -> String {
+        sha::hex(&self.sha256)
+    }
+
+    pub fn sha1_hex(&self) -> Option<String> {
+        self.sha1.as_ref().map(|h| sha::hex(h))
+    }
+}
+
+/// Incremental hashing of one entry's content.
+pub(crate) struct EntryHasher {
+    sha256: Sha256,
+    sha1: Option<Sha1>,
+    size: u64,
+}
+
+impl EntryHasher {
+    pub(crate) fn new(sha1: bool) -> Self {
+        Self {
+            sha256: Sha256::new(),
+            sha1: sha1.then(Sha1::new),
+            size: 0,
+        }
+    }
+
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        self.sha256.update(data);
+        if let Some(h) = &mut self.sha1 {
+            h.update(data);
+        }
+        self.size += data.len() as u64;
+    }
+
+    pub(crate) fn finish(self, name: &str) -> ManifestEntry {
+        ManifestEntry {
+            name: name.to_owned(),
+            size: self.size,
+            sha256: self.sha256.finish(),
+            sha1: self.sha1.map(Sha1::finish),
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::BTreeMap;
+
+use crate::json::{self, Value};
+use crate::{AddonInfo, AddonTag, AddonType, GmaError, VERSION, check_type_and_tags};
+
+/// Everything that describes an addon rather than its files, in one place: the
+/// header fields, with the description split into the text, type and tags
+/// gmad packs into it as JSON.
+///
+/// Read from [`AddonInfo::metadata`] or
+/// [`Builder::metadata`](crate::Builder::metadata), applied with
+/// [`Builder::set_metadata`](crate::Builder::set_metadata).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AddonMetadata {
+    pub title: String,
+    /// The description text, without gmad's JSON wrapper.
+    pub description: String,
+    pub author: String,
+    /// The `"type"` in gmad's description (`"tool"`, `"map"`, ...); empty if
+    /// there is none.
+    pub addon_type: String,
+    pub tags: Vec<String>,
+    pub steam_id64: i64,
+    /// Header timestamp (Unix seconds); `None` stamps the time of writing.
+    pub timestamp: Option<u64>,
+    /// Format version, [`MIN_VERSION`](crate::MIN_VERSION) to [`VERSION`].
+    pub version: i8,
+}
+
+impl Default for AddonMetadata {
+    fn default() -> Self {
+        Self {
+            title: String::new(),
+            description: String::new(),
+            author: String::new(),
+            addon_type: String::new(),
+            tags: Vec::new(),
+            steam_id64: 0,
+            timestamp: None,
+            version: VERSION,
+        }
+    }
+}
+
+impl AddonMetadata {
+    /// The header's description field for this metadata: gmad's JSON object if
+    /// there's a type or tags, otherwise the text as is.
+    pub fn gma_description(&self) -> String {
+        if self.addon_type.is_empty() && self.tags.is_empty() {
+            self.description.clone()
+        } else {
+            gma_description(&self.description, &self.addon_type, &self.tags)
+        }
+    }
+
+    /// The type and tags, checked against the Workshop's rules (see
+    /// [`check_type_and_tags`]).
+    pub fn workshop_type_and_tags(&self) -> Result<(AddonType, Vec<AddonTag>), GmaError> {
+        check_type_and_tags(&self.addon_type, &self.tags)
+    }
+
+    /// Set the type and tags from their typed forms.
+    pub fn set_type_and_tags(&mut self, addon_type: AddonType, tags: &[AddonTag]) {
+        self.addon_type = addon_type.as_str().to_owned();
+        self.tags = tags.iter().map(|t| t.as_str().to_owned()).collect();
+    }
+
+    /// Fill in the description, type and tags from a header description field,
+    /// unwrapping gmad's JSON if it is one.
+    pub(crate) fn set_gma_description(&mut self, field: &str) {
+        let json = json::parse(field)
+            .ok()
+            .filter(|v| matches!(v, Value::Object(_)));
+        let Some(root) = json else {
+            self.description = field.to_owned();
+            return;
+        };
+        let string = |key| root.get(key).and_then(Value::as_str).unwrap_or_default();
+        self.description = string("description").to_owned();
+        self.addon_type = string("type").to_owned();
+        self.tags = root
+            .get("tags")
+            .and_then(Value::as_array)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|t| t.as_str().map(str::to_owned))
+            .collect();
+    }
+}
+
+/// gmad's JSON description: the text, type and tags.
+pub(crate) fn gma_description(description: &str, addon_type: &str, tags: &[String]) -> String {
+    let string = |s: &str| Value::String(s.to_owned());
+    let mut root = BTreeMap::new();
+    root.insert("description".to_owned(), string(description));
+    root.insert("type".to_owned(), string(addon_type));
+    root.insert(
+        "tags".to_owned(),
+        Value::Array(tags.iter().map(|t| string(t)).collect()),
+    );
+    Value::Object(root).to_string()
+}
+
+impl AddonInfo {
+    /// These header fields as [`AddonMetadata`], with gmad's JSON description
+    /// unpacked.
+    pub fn metadata(&self) -> AddonMetadata {
+        let mut m = AddonMetadata {
+            title: self.name.clone(),
+            author: self.author.clone(),
+            steam_id64: self.steam_id64,
+            timestamp: Some(self.timestamp),
+            version: self.version,
+            ..AddonMetadata::default()
+        };
+        m.set_gma_description(&self.description);
+        m
+    }
+}
@@ -0,0 +1,264 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::{EntryMeta, GMAFile, is_safe_path, is_whitelisted};
+
+/// Best-effort prediction of whether Garry's Mod will mount an archive, from
+/// its entry names and sizes.
+///
+/// Applies gmad's whitelist, the lowercase `/`-separated naming gmad produces,
+/// path safety, duplicate paths and an optional size limit. Passing is not a
+/// guarantee (the game can change its rules), but failing means it won't mount
+/// as packed.
+#[derive(Clone, Debug, Default)]
+pub struct MountCheck {
+    max_size: Option<u64>,
+}
+
+/// How bad a [`MountProblem`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Mounts, but something will likely not work as intended.
+    Warning,
+    /// The archive (or this entry) won't mount.
+    Error,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MountIssue {
+    /// Not on [`GMAD_WHITELIST`](crate::GMAD_WHITELIST).
+    NotWhitelisted,
+    /// Absolute, `..` or drive-letter path (see [`is_safe_path`]).
+    UnsafePath,
+    /// Has uppercase letters; not found on case-sensitive (Linux) servers.
+    NotLowercase,
+    /// Uses `\` as a separator.
+    Backslash,
+    /// Another entry has the same path, ignoring case; only one is visible.
+    Duplicate,
+    /// The archive is over the [`MountCheck::set_max_size`] limit.
+    TooLarge { size: u64, limit: u64 },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MountProblem {
+    /// The offending entry, or `None` for the archive as a whole.
+    pub name: Option<String>,
+    pub issue: MountIssue,
+    pub severity: Severity,
+}
+
+/// Verdict of a [`MountCheck`], with the reasons behind it.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct MountReport {
+    problems: Vec<MountProblem>,
+}
+
+impl MountCheck {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fail archives whose content adds up to more than `limit` bytes.
+    pub fn set_max_size(&mut self, limit: u64) {
+        self.max_size = Some(limit);
+    }
+
+    pub fn check_entries(&self, entries: &[EntryMeta]) -> MountReport {
+        self.check(entries.iter().map(|e| (e.name.as_str(), e.size)))
+    }
+
+    pub fn check_files(&self, files: &[GMAFile]) -> MountReport {
+        self.check(files.iter().map(|f| (f.name.as_str(), f.size)))
+    }
+
+    fn check<'a>(&self, entries: impl Iterator<Item = (&'a str, u64)>) -> MountReport {
+        let mut problems = Vec::new();
+        let mut seen = HashSet::new();
+        let mut total = 0u64;
+        for (name, size) in entries {
+            total = total.saturating_add(size);
+            let mut report = |issue, severity| {
+                problems.push(MountProblem {
+                    name: Some(name.to_owned()),
+                    issue,
+                    severity,
+                })
+            };
+            if !is_safe_path(name) {
+                report(MountIssue::UnsafePath, Severity::Error);
+                continue;
+            }
+            let normalized = name.replace('\\', "/").to_ascii_lowercase();
+            if !is_whitelisted(&normalized) {
+                report(MountIssue::NotWhitelisted, Severity::Error);
+            }
+            if name.contains('\\') {
+                report(MountIssue::Backslash, Severity::Warning);
+            }
+            if name.bytes().any(|b| b.is_ascii_uppercase()) {
+                report(MountIssue::NotLowercase, Severity::Warning);
+            }
+            if !seen.insert(normalized) {
+                report(MountIssue::Duplicate, Severity::Warning);
+            }
+        }
+        if let Some(limit) = self.max_size
+            && total > limit
+        {
+            problems.push(MountProblem {
+                name: None,
+                issue: MountIssue::TooLarge { size: total, limit },
+                severity: Severity::Error,
+            });
+        }
+        MountReport { problems }
+    }
+}
+
+impl MountReport {
+    /// `true` unless some problem is a [`Severity::Error`].
+    pub fn passes(&self) -> bool {
+        self.problems.iter().all(|p| p.severity < Severity::Error)
+    }
+
+    /// Every problem found, in entry order.
+    pub fn problems(&self) -> &[MountProblem] {
+        &self.problems
+    }
+}
+
+impl fmt::Display for MountIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MountIssue::NotWhitelisted => f.write_str("not on the whitelist"),
+            MountIssue::UnsafePath => f.write_str("unsafe path"),
+            MountIssue::NotLowercase => f.write_str("not lowercase"),
+            MountIssue::Backslash => f.write_str("uses backslashes"),
+            MountIssue::Duplicate => f.write_str("duplicate path"),
+            MountIssue::TooLarge { size, limit } => {
+                write!(f, "{size} bytes, over the {limit} byte limit")
+            }
+        }
+    }
+}
+
+impl fmt::Display for MountReport {
+    /// A `PASS` / `FAIL` line, then one line per problem.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(if self.passes() { "PASS" } else { "FAIL" })?;
+        for p in &self.problems {
+            let level = match p.severity {
+                Severity::Warning => "warning",
+                Severity::Error => "error",
+            };
+            match &p.name {
+                Some(name) => write!(f, "\n{level}: {name}: {}", p.issue)?,
+                None => write!(f, "\n{level}: {}", p.issue)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(names: &[&str]) -> MountReport {
+        let files: Vec<GMAFile> = names
+            .iter()
+            .map(|name| GMAFile {
+                name: name.to_string(),
+                size: 10,
+                ..GMAFile::default()
+            })
+            .collect();
+        MountCheck::new().check_files(&files)
+    }
+
+    fn issues(report: &MountReport) -> Vec<(&str, &MountIssue, Severity)> {
+        report
+            .problems()
+            .iter()
+            .map(|p| (p.name.as_deref().unwrap_or(""), &p.issue, p.severity))
+            .collect()
+    }
+
+    #[test]
+    fn clean_archive_passes() {
+        let report = check(&["lua/autorun/a.lua", "materials/x.vmt", "sound/y.wav"]);
+        assert!(report.passes());
+        assert!(report.problems().is_empty());
+        assert_eq!(report.to_string(), "PASS");
+    }
+
+    #[test]
+    fn each_issue_is_reported() {
+        use MountIssue::*;
+        use Severity::*;
+        for (name, issue, severity) in [
+            ("lua/a.exe", NotWhitelisted, Error),
+            ("../lua/a.lua", UnsafePath, Error),
+            ("C:/lua/a.lua", UnsafePath, Error),
+            ("lua/A.lua", NotLowercase, Warning),
+            ("lua\\b.lua", Backslash, Warning),
+        ] {
+            let report = check(&["lua/ok.lua", name]);
+            assert_eq!(issues(&report), [(name, &issue, severity)], "{name}");
+            assert_eq!(report.passes(), severity == Warning, "{name}");
+        }
+
+        let report = check(&["lua/a.lua", "lua/b.lua", "lua/a.lua"]);
+        assert_eq!(issues(&report), [("lua/a.lua", &Duplicate, Warning)]);
+        assert!(report.passes());
+    }
+
+    #[test]
+    fn size_limit_is_checked_on_the_total() {
+        let entries = [
+            EntryMeta {
+                name: "sound/a.wav".into(),
+                size: 60,
+                ..EntryMeta::default()
+            },
+            EntryMeta {
+                name: "sound/b.wav".into(),
+                size: 40,
+                ..EntryMeta::default()
+            },
+        ];
+        let mut check = MountCheck::new();
+        check.set_max_size(100);
+        assert!(check.check_entries(&entries).passes());
+
+        check.set_max_size(99);
+        let report = check.check_entries(&entries);
+        assert!(!report.passes());
+        assert_eq!(
+            report.problems(),
+            [MountProblem {
+                name: None,
+                issue: MountIssue::TooLarge {
+                    size: 100,
+                    limit: 99
+                },
+                severity: Severity::Error,
+            }]
+        );
+        assert_eq!(
+            report.to_string(),
+            "FAIL\nerror: 100 bytes, over the 99 byte limit"
+        );
+    }
+
+    #[test]
+    fn display_lists_every_problem() {
+        let report = check(&["lua/A.lua", "lua/a.exe"]);
+        assert_eq!(
+            report.to_string(),
+            "FAIL\nwarning: lua/A.lua: not lowercase\nerror: lua/a.exe: not on the whitelist"
+        );
+    }
+}
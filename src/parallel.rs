@@ -0,0 +1,47 @@
+//! Small scoped-thread helpers shared by the parallel extract/build paths.
+
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// Resolve a user-facing thread count; `0` means one per available core.
+pub(crate) fn thread_count(threads: usize) -> usize {
+    match threads {
+        0 => thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    }
+}
+
+/// Compute `f(i)` for every `i in 0..len` on up to `threads` workers.
+///
+/// Results come back in index order regardless of which worker finished first.
+pub(crate) fn map_indexed<T, F>(len: usize, threads: usize, f: F) -> Vec<T>
+where
+    T: Send + Sync,
+    F: Fn(usize) -> T + Sync,
+{
+    let workers = thread_count(threads).min(len);
+    if workers <= 1 {
+        return (0..len).map(f).collect();
+    }
+
+    let slots: Vec<OnceLock<T>> = (0..len).map(|_| OnceLock::new()).collect();
+    let next = AtomicUsize::new(0);
+    thread::scope(|s| {
+        for _ in 0..workers {
+            s.spawn(|| {
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(slot) = slots.get(i) else {
+                        break;
+                    };
+                    let _ = slot.set(f(i));
+                }
+            });
+        }
+    });
+    slots
+        .into_iter()
+        .map(|slot| slot.into_inner().expect("every slot is filled"))
+        .collect()
+}
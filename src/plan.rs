@@ -0,0 +1,195 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use crate::extract::{ReservedNames, copy_entry, entry_path};
+use crate::limits::Limits;
+use crate::quota::Usage;
+use crate::{EntryMeta, GmaError, list_entries};
+
+/// Which entries to pull out of which archives to satisfy a set of requested
+/// paths across a collection of addons.
+///
+/// Archives are given in mount priority order: when several contain the same
+/// path, the first one wins, as it would in game. Paths are matched ASCII
+/// case-insensitively.
+#[derive(Clone, Debug, Default)]
+pub struct ExtractionPlan {
+    archives: Vec<PlannedArchive>,
+    missing: Vec<String>,
+}
+
+/// The entries to read from one archive, sorted by offset.
+#[derive(Clone, Debug)]
+pub struct PlannedArchive {
+    pub path: PathBuf,
+    pub entries: Vec<EntryMeta>,
+    /// Each entry's position in the archive's metadata table, as errors
+    /// report it.
+    pub indices: Vec<usize>,
+}
+
+impl ExtractionPlan {
+    /// Parse the metadata table of each archive (content isn't read) and assign
+    /// every requested path to the highest-priority archive providing it.
+    pub fn new<P, S>(
+        archives: impl IntoIterator<Item = P>,
+        requested: impl IntoIterator<Item = S>,
+    ) -> Result<Self, GmaError>
+    where
+        P: AsRef<Path>,
+        S: AsRef<str>,
+    {
+        let mut order = Vec::new();
+        let mut pending = HashSet::new();
+        for name in requested {
+            let name = name.as_ref();
+            if pending.insert(name.to_ascii_lowercase()) {
+                order.push(name.to_owned());
+            }
+        }
+
+        let mut planned = Vec::new();
+        for path in archives {
+            if pending.is_empty() {
+                break;
+            }
+            let path = path.as_ref();
+            let mut entries = Vec::new();
+            for (index, e) in list_entries(BufReader::new(File::open(path)?))?
+                .into_iter()
+                .enumerate()
+            {
+                if !pending.remove(&e.name.to_ascii_lowercase()) {
+                    continue;
+                }
+                // Fail while planning rather than halfway through extraction.
+                entry_path(Path::new(""), &e.name, index, ReservedNames::default())?;
+                entries.push((index, e));
+            }
+            if entries.is_empty() {
+                continue;
+            }
+            entries.sort_by_key(|(_, e)| e.offset);
+            let (indices, entries) = entries.into_iter().unzip();
+            planned.push(PlannedArchive {
+                path: path.to_path_buf(),
+                entries,
+                indices,
+            });
+        }
+
+        order.retain(|name| pending.contains(&name.to_ascii_lowercase()));
+        Ok(Self {
+            archives: planned,
+            missing: order,
+        })
+    }
+
+    /// Archives that have to be opened, in priority order.
+    pub fn archives(&self) -> &[PlannedArchive] {
+        &self.archives
+    }
+
+    /// Requested paths no archive provides.
+    pub fn missing(&self) -> &[String] {
+        &self.missing
+    }
+
+    /// Total content bytes the plan will copy.
+    pub fn total_size(&self) -> u64 {
+        self.archives
+            .iter()
+            .flat_map(|a| &a.entries)
+            .map(|e| e.size)
+            .sum()
+    }
+
+    /// What the plan will write, per top-level directory.
+    pub fn usage(&self) -> Usage {
+        Usage::from_entries(self.archives.iter().flat_map(|a| &a.entries))
+    }
+
+    /// Copy every planned entry into `dir`, opening each archive once and
+    /// reading its entries front to back. Returns the written paths.
+    pub fn execute(&self, dir: impl AsRef<Path>) -> Result<Vec<PathBuf>, GmaError> {
+        self.execute_inner(dir.as_ref(), None)
+    }
+
+    /// Like [`execute`](Self::execute), staying within a shared [`Limits`] budget.
+    pub fn execute_limited(
+        &self,
+        dir: impl AsRef<Path>,
+        limits: &Limits,
+    ) -> Result<Vec<PathBuf>, GmaError> {
+        self.execute_inner(dir.as_ref(), Some(limits))
+    }
+
+    fn execute_inner(&self, dir: &Path, limits: Option<&Limits>) -> Result<Vec<PathBuf>, GmaError> {
+        let mut written = Vec::new();
+        for archive in &self.archives {
+            // Held for the whole archive: it covers the source and each output.
+            let _file = limits.map(Limits::acquire_file);
+            let mut src = BufReader::new(File::open(&archive.path)?);
+            for (meta, &index) in archive.entries.iter().zip(&archive.indices) {
+                let path = entry_path(dir, &meta.name, index, ReservedNames::default())?;
+                let _bytes = limits.map(|l| l.acquire_bytes(meta.size));
+                copy_entry(&mut src, meta, &path)?;
+                written.push(path);
+            }
+        }
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Builder;
+
+    #[test]
+    fn plans_by_priority_and_keeps_table_indices() {
+        let dir = std::env::temp_dir().join(format!("gma-lite-plan-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, files: &[(&str, &[u8])]| {
+            let mut b = Builder::new(name, 1);
+            for (path, content) in files {
+                b.file_from_bytes(*path, content.to_vec());
+            }
+            let path = dir.join(name);
+            b.write_to_path(&path).unwrap();
+            path
+        };
+        let high = write("high.gma", &[("lua/x.lua", b"x"), ("lua/a.lua", b"high")]);
+        let low = write(
+            "low.gma",
+            &[
+                ("lua/a.lua", b"low"),
+                ("lua/c.lua", b"c"),
+                ("lua/b.lua", b"b"),
+            ],
+        );
+
+        let plan = ExtractionPlan::new(
+            [&high, &low],
+            ["LUA/A.lua", "lua/b.lua", "lua/c.lua", "lua/none.lua"],
+        )
+        .unwrap();
+        assert_eq!(plan.missing(), ["lua/none.lua"]);
+        let [from_high, from_low] = plan.archives() else {
+            panic!("{plan:?}");
+        };
+        assert_eq!(from_high.indices, [1]);
+        let names: Vec<_> = from_low.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["lua/c.lua", "lua/b.lua"]);
+        assert_eq!(from_low.indices, [1, 2]);
+        assert_eq!(plan.total_size(), 6);
+
+        let out = dir.join("out");
+        let written = plan.execute(&out).unwrap();
+        assert_eq!(written.len(), 3);
+        assert_eq!(std::fs::read(out.join("lua/a.lua")).unwrap(), b"high");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::BTreeMap;
+
+use crate::{EntryMeta, GmaError};
+
+/// Bytes and files an extraction writes, broken down by top-level directory
+/// (`sound`, `materials`, `lua`, ...; ASCII-lowercased, `""` for files at the root).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Usage {
+    categories: BTreeMap<String, CategoryUsage>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CategoryUsage {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+impl Usage {
+    /// Account for every entry of a metadata table. Extraction writes each entry
+    /// in full (or fails), so this is exactly what extracting them produces.
+    pub fn from_entries<'a>(entries: impl IntoIterator<Item = &'a EntryMeta>) -> Self {
+        let mut usage = Self::default();
+        for e in entries {
+            usage.add(&e.name, e.size);
+        }
+        usage
+    }
+
+    /// Record one file of `size` bytes at `name`.
+    pub fn add(&mut self, name: &str, size: u64) {
+        let c = self.categories.entry(category(name)).or_default();
+        c.files += 1;
+        c.bytes += size;
+    }
+
+    /// Usage of one category (`None` if nothing went there).
+    pub fn get(&self, category: &str) -> Option<CategoryUsage> {
+        self.categories.get(&category.to_ascii_lowercase()).copied()
+    }
+
+    /// Every category with its usage, sorted by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, CategoryUsage)> {
+        self.categories.iter().map(|(k, v)| (k.as_str(), *v))
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.categories.values().map(|c| c.bytes).sum()
+    }
+
+    pub fn total_files(&self) -> usize {
+        self.categories.values().map(|c| c.files).sum()
+    }
+}
+
+/// Per-category byte limits for extraction, e.g. at most 1 GB under `sound/`.
+///
+/// Checked against the metadata table before anything is written.
+#[derive(Clone, Debug, Default)]
+pub struct Quotas {
+    limits: BTreeMap<String, u64>,
+}
+
+impl Quotas {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow at most `bytes` under the top-level directory `category`
+    /// (matched ASCII case-insensitively, `""` for files at the root).
+    pub fn set_limit(&mut self, category: impl AsRef<str>, bytes: u64) {
+        self.limits
+            .insert(category.as_ref().to_ascii_lowercase(), bytes);
+    }
+
+    /// Fail with [`GmaError::QuotaExceeded`] for the first category over its limit.
+    pub fn check(&self, usage: &Usage) -> Result<(), GmaError> {
+        for (category, &limit) in &self.limits {
+            let size = usage.get(category).map_or(0, |c| c.bytes);
+            if size > limit {
+                return Err(GmaError::QuotaExceeded {
+                    category: category.clone(),
+                    size,
+                    limit,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+fn category(name: &str) -> String {
+    let mut parts = name.split(['/', '\\']).filter(|p| !matches!(*p, "" | "."));
+    match (parts.next(), parts.next()) {
+        (Some(dir), Some(_)) => dir.to_ascii_lowercase(),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::{Builder, Extractor};
+
+    fn archive() -> Vec<u8> {
+        let mut b = Builder::new("t", 1);
+        b.file_from_bytes("lua/a.lua", vec![0; 10]);
+        b.file_from_bytes("sound/a.wav", vec![0; 600]);
+        b.file_from_bytes("Sound/b.wav", vec![0; 600]);
+        b.file_from_bytes("readme.txt", vec![0; 5]);
+        b.write_to_vec().unwrap()
+    }
+
+    #[test]
+    fn usage_is_grouped_by_top_level_directory() {
+        let mut usage = Usage::default();
+        for (name, size) in [
+            ("lua/a.lua", 10),
+            ("SOUND\\a.wav", 600),
+            ("./sound/b.wav", 600),
+        ] {
+            usage.add(name, size);
+        }
+        usage.add("readme.txt", 5);
+        assert_eq!(
+            usage.iter().collect::<Vec<_>>(),
+            [
+                ("", CategoryUsage { files: 1, bytes: 5 }),
+                (
+                    "lua",
+                    CategoryUsage {
+                        files: 1,
+                        bytes: 10
+                    }
+                ),
+                (
+                    "sound",
+                    CategoryUsage {
+                        files: 2,
+                        bytes: 1200
+                    }
+                ),
+            ]
+        );
+        assert_eq!((usage.total_files(), usage.total_bytes()), (4, 1215));
+    }
+
+    #[test]
+    fn over_limit_archive_is_rejected_before_writing() {
+        let dir = std::env::temp_dir().join(format!("gma-lite-quota-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let data = archive();
+
+        let mut quotas = Quotas::new();
+        quotas.set_limit("SOUND", 1000);
+        let mut ex = Extractor::new();
+        ex.set_quotas(quotas);
+        for parallel in [false, true] {
+            let err = if parallel {
+                ex.extract_parallel(|| Ok(std::io::Cursor::new(&data[..])), &dir)
+            } else {
+                ex.extract(&data[..], &dir)
+            }
+            .unwrap_err();
+            assert!(
+                matches!(
+                    &err,
+                    GmaError::QuotaExceeded { category, size: 1200, limit: 1000 } if category == "sound"
+                ),
+                "{err}"
+            );
+            // Not even the entry ahead of the sounds was written
+            assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        }
+
+        // Leaving out the second sound brings it under the limit
+        ex.set_only(&["lua/*", "sound/a.wav", "readme.txt"]);
+        assert_eq!(ex.extract(&data[..], &dir).unwrap().len(), 3);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,193 @@
+use std::fs::File;
+use std::io::{self, Read};
+
+use crate::reader::PREALLOC_LIMIT;
+use crate::{AddonInfo, EntryMeta, GmaError, GmaReader, ReadOptions, ReadWarning, same_path};
+
+/// Positional reads: fetch bytes at an absolute offset without moving a cursor.
+///
+/// Implement this for mmaps, object storage or HTTP range sources to open them
+/// with [`LazyArchive`].
+pub trait ReadAt {
+    /// Read up to `buf.len()` bytes starting at `offset`. Returns 0 at end of data.
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize>;
+
+    fn read_exact_at(&self, mut offset: u64, mut buf: &mut [u8]) -> io::Result<()> {
+        while !buf.is_empty() {
+            match self.read_at(offset, buf) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => {
+                    offset += n as u64;
+                    buf = &mut buf[n..];
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ReadAt for [u8] {
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        let start = usize::try_from(offset)
+            .unwrap_or(usize::MAX)
+            .min(self.len());
+        let n = buf.len().min(self.len() - start);
+        buf[..n].copy_from_slice(&self[start..start + n]);
+        Ok(n)
+    }
+}
+
+impl ReadAt for Vec<u8> {
+    #[inline]
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        self.as_slice().read_at(offset, buf)
+    }
+}
+
+impl ReadAt for File {
+    #[cfg(unix)]
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        std::os::unix::fs::FileExt::read_at(self, buf, offset)
+    }
+
+    #[cfg(windows)]
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        std::os::windows::fs::FileExt::seek_read(self, buf, offset)
+    }
+}
+
+impl<T: ReadAt + ?Sized> ReadAt for &T {
+    #[inline]
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        (**self).read_at(offset, buf)
+    }
+}
+
+/// Sequential [`Read`] over a [`ReadAt`] source, starting at some offset.
+pub struct ReadAtReader<'a, S: ?Sized> {
+    src: &'a S,
+    pos: u64,
+}
+
+impl<'a, S: ReadAt + ?Sized> ReadAtReader<'a, S> {
+    #[inline]
+    pub fn new(src: &'a S, pos: u64) -> Self {
+        Self { src, pos }
+    }
+}
+
+impl<S: ReadAt + ?Sized> Read for ReadAtReader<'_, S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.src.read_at(self.pos, buf)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+/// Archive over a [`ReadAt`] source: only the header and metadata are parsed
+/// up front, entry contents are fetched on demand.
+pub struct LazyArchive<S> {
+    src: S,
+    info: AddonInfo,
+    table: Vec<EntryMeta>,
+    warnings: Vec<ReadWarning>,
+}
+
+impl<S: ReadAt> LazyArchive<S> {
+    #[inline]
+    pub fn open(src: S) -> Result<Self, GmaError> {
+        Self::open_with(src, &ReadOptions::default())
+    }
+
+    pub fn open_with(src: S, opts: &ReadOptions) -> Result<Self, GmaError> {
+        let gr = GmaReader::with_options(ReadAtReader::new(&src, 0), opts)?;
+        let info = gr.info().clone();
+        let table = gr.entries().to_vec();
+        let warnings = gr.warnings().to_vec();
+        drop(gr);
+        Ok(Self {
+            src,
+            info,
+            table,
+            warnings,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.info.name
+    }
+
+    pub fn description(&self) -> &str {
+        &self.info.description
+    }
+
+    pub fn author(&self) -> &str {
+        &self.info.author
+    }
+
+    pub fn info(&self) -> &AddonInfo {
+        &self.info
+    }
+
+    pub fn entries(&self) -> &[EntryMeta] {
+        &self.table
+    }
+
+    pub fn warnings(&self) -> &[ReadWarning] {
+        &self.warnings
+    }
+
+    /// The entry at `name`, compared ASCII case-insensitively with `\\` and `/`
+    /// treated alike, as [`GmaArchive::get`](crate::GmaArchive::get) does. With
+    /// duplicates, the first one.
+    pub fn find(&self, name: &str) -> Option<&EntryMeta> {
+        self.table.iter().find(|e| same_path(&e.name, name))
+    }
+
+    /// Reader over the content of `entry`, which must come from [`entries`](Self::entries).
+    pub fn entry_reader(&self, entry: &EntryMeta) -> io::Take<ReadAtReader<'_, S>> {
+        ReadAtReader::new(&self.src, entry.offset).take(entry.size)
+    }
+
+    /// Fetch the full content of `entry`.
+    pub fn read_entry(&self, entry: &EntryMeta) -> Result<Vec<u8>, GmaError> {
+        let len =
+            usize::try_from(entry.size).map_err(|_| GmaError::SizeOutOfRange(entry.size as i64))?;
+        let mut content = Vec::with_capacity(len.min(PREALLOC_LIMIT));
+        self.entry_reader(entry).read_to_end(&mut content)?;
+        if content.len() != len {
+            return Err(entry.truncated(content.len() as u64));
+        }
+        Ok(content)
+    }
+
+    pub fn into_inner(self) -> S {
+        self.src
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Builder;
+
+    #[test]
+    fn find_ignores_case_and_separators() {
+        let mut b = Builder::new("t", 1);
+        b.file_from_bytes("lua/Autorun/A.lua", b"1".to_vec())
+            .file_from_bytes("lua/autorun/a.lua", b"2".to_vec());
+        let archive = LazyArchive::open(b.write_to_vec().unwrap()).unwrap();
+        for name in [
+            "lua/autorun/a.lua",
+            "LUA\\AUTORUN\\A.LUA",
+            "lua/Autorun/A.lua",
+        ] {
+            let entry = archive.find(name).unwrap();
+            assert_eq!(archive.read_entry(entry).unwrap(), b"1", "{name}");
+        }
+        assert!(archive.find("lua/autorun/a.lu").is_none());
+        assert!(archive.find("lua/autorun/b.lua").is_none());
+    }
+}
@@ -1,99 +1,1067 @@
+use std::collections::HashSet;
+use std::fmt;
 use std::io::{self, BufRead, BufReader, Read};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use crate::{GMAFile, GmaError, HEADER, VERSION};
+use crate::crc::Crc32;
+use crate::manifest::{EntryHasher, ManifestEntry};
+use crate::{
+    AddonInfo, Cancelled, EntryMeta, GMAFile, GmaArchive, GmaError, HEADER, MIN_VERSION,
+    ParseField, TrailerKind, VERSION, fold_path_byte, is_safe_path,
+};
+
+/// Upper bound on buffer space reserved up front for a single entry's content.
+pub(crate) const PREALLOC_LIMIT: usize = 1 << 20;
+
+/// Decodes the raw bytes of an entry name into a `String`.
+pub type NameDecoder = fn(&[u8]) -> String;
+
+/// What to do when the metadata table lists the same path more than once.
+///
+/// Paths are compared ASCII case-insensitively with `\\` and `/` treated alike,
+/// matching how the game mounts them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    /// Keep every entry silently (the format allows it).
+    #[default]
+    Allow,
+    /// Keep every entry, but record a [`ReadWarning::DuplicateEntry`].
+    Warn,
+    /// Fail with [`GmaError::DuplicateEntry`].
+    Error,
+}
+
+/// Which non-zero trailing markers a reader accepts. Whatever it refuses fails
+/// with [`GmaError::TrailingMarkerMismatch`], classified by [`TrailerKind`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum TrailerPolicy {
+    /// Accept a whole-archive CRC, once it has been checked against a hash of
+    /// the stream (taken for this policy whatever
+    /// [`ReadOptions::set_hash_archive`] says). Any other non-zero marker,
+    /// such as the `GMAD` magic of a concatenated archive, is refused.
+    #[default]
+    Lenient,
+    /// Accept only 0. With hashing on, a refused CRC is reported as
+    /// [`TrailerKind::ArchiveCrc`] so callers can decide to read it anyway.
+    Strict,
+}
+
+/// Non-fatal problems found while reading, collected on [`GmaArchive`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReadWarning {
+    /// The path was already listed by an earlier entry.
+    DuplicateEntry(String),
+    /// The stream ended inside this entry; its `content` holds the `read` bytes
+    /// that were present. Only produced with [`ReadOptions::set_recover_truncated`].
+    TruncatedEntry {
+        name: String,
+        read: u64,
+        expected: u64,
+    },
+    /// The archive's `addon.json` couldn't be parsed (`json` feature).
+    InvalidAddonJson(String),
+}
+
+impl fmt::Display for ReadWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadWarning::DuplicateEntry(name) => write!(f, "duplicate entry: {name}"),
+            ReadWarning::TruncatedEntry {
+                name,
+                read,
+                expected,
+            } => write!(f, "entry {name} truncated: got {read} of {expected} bytes"),
+            ReadWarning::InvalidAddonJson(why) => f.write_str(why),
+        }
+    }
+}
+
+/// Default for [`ReadOptions::set_max_name_len`].
+const DEFAULT_MAX_NAME_LEN: usize = 1024;
+
+/// Options controlling how [`read_with`] parses an archive.
+#[derive(Clone, Debug, Default)]
+pub struct ReadOptions {
+    name_decoder: Option<NameDecoder>,
+    duplicates: DuplicatePolicy,
+    stream_len: Option<u64>,
+    hash_archive: bool,
+    trailer_policy: TrailerPolicy,
+    buffer_capacity: Option<usize>,
+    recover_truncated: bool,
+    #[cfg(feature = "json")]
+    parse_addon_json: bool,
+    entry_hashes: bool,
+    entry_sha1: bool,
+    cancel: Option<Arc<AtomicBool>>,
+    max_entries: Option<usize>,
+    max_entry_size: Option<u64>,
+    max_content_size: Option<u64>,
+    max_name_len: Option<usize>,
+    max_header_string_len: Option<usize>,
+    max_required_content: Option<usize>,
+    strict_indices: bool,
+    reject_unsafe_paths: bool,
+    verify_crcs: bool,
+    require_utf8_names: bool,
+}
+
+impl ReadOptions {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Defaults with every defensive check switched on, for untrusted input:
+    ///
+    /// - duplicates, unsafe paths and non-UTF-8 names are errors
+    /// - file indices must be sequential
+    /// - every entry's CRC and any archive CRC trailer are verified
+    /// - at most 65 536 entries of up to 2 GiB each, 4 GiB in total
+    /// - entry names of at most 1024 bytes
+    /// - header strings of at most 64 KiB, and at most 256 required content items
+    pub fn hardened() -> Self {
+        let mut opts = Self::default();
+        opts.set_duplicate_policy(DuplicatePolicy::Error);
+        opts.set_strict_indices(true);
+        opts.set_reject_unsafe_paths(true);
+        opts.set_require_utf8_names(true);
+        opts.set_verify_crcs(true);
+        opts.set_hash_archive(true);
+        opts.set_max_entries(1 << 16);
+        opts.set_max_entry_size(1 << 31);
+        opts.set_max_content_size(1 << 32);
+        opts.set_max_name_len(1024);
+        opts.set_max_header_string_len(1 << 16);
+        opts.set_max_required_content(256);
+        opts
+    }
+
+    /// Decode entry names with `decoder` instead of lossy UTF-8.
+    ///
+    /// The raw bytes are always kept in [`GMAFile::name_bytes`].
+    pub fn set_name_decoder(&mut self, decoder: NameDecoder) {
+        self.name_decoder = Some(decoder);
+    }
+
+    /// Choose how repeated entry paths are handled. Defaults to [`DuplicatePolicy::Allow`].
+    pub fn set_duplicate_policy(&mut self, policy: DuplicatePolicy) {
+        self.duplicates = policy;
+    }
+
+    /// Declare the total length of the input, if known (e.g. a file's size).
+    ///
+    /// The metadata table is then checked against it entry by entry, so a
+    /// hostile table is rejected as soon as its sizes can't fit in the stream.
+    pub fn set_stream_len(&mut self, len: u64) {
+        self.stream_len = Some(len);
+    }
+
+    /// Hash the whole stream so a non-zero trailing marker can be recognised as
+    /// the whole-archive CRC gmad appends ([`GmaArchive::archive_crc`]).
+    /// [`TrailerPolicy::Lenient`] always does, to verify the CRC before
+    /// accepting it; under [`TrailerPolicy::Strict`] this only makes a refused
+    /// CRC come back as [`TrailerKind::ArchiveCrc`]. Costs one CRC-32 pass over
+    /// the input.
+    pub fn set_hash_archive(&mut self, enabled: bool) {
+        self.hash_archive = enabled;
+    }
+
+    /// Choose which trailing markers are accepted. Defaults to
+    /// [`TrailerPolicy::Lenient`].
+    pub fn set_trailer_policy(&mut self, policy: TrailerPolicy) {
+        self.trailer_policy = policy;
+    }
+
+    /// Capacity of the `BufReader` wrapped around plain readers.
+    ///
+    /// Ignored by [`read_buffered`] / [`GmaReader::from_buffered`].
+    pub fn set_buffer_capacity(&mut self, capacity: usize) {
+        self.buffer_capacity = Some(capacity);
+    }
+
+    /// When the stream ends inside an entry, return what was read so far instead
+    /// of failing: the partial entry is kept (with `content` shorter than `size`)
+    /// and reported as [`ReadWarning::TruncatedEntry`].
+    pub fn set_recover_truncated(&mut self, enabled: bool) {
+        self.recover_truncated = enabled;
+    }
+
+    /// Parse an `addon.json` entry, if present, into [`GmaArchive::addon_json`].
+    ///
+    /// A malformed file is reported as [`ReadWarning::InvalidAddonJson`].
+    #[cfg(feature = "json")]
+    pub fn set_parse_addon_json(&mut self, enabled: bool) {
+        self.parse_addon_json = enabled;
+    }
+
+    /// Hash every loaded entry with SHA-256 while reading, collected in
+    /// [`GmaArchive::manifest`]. Entries skipped by a filter aren't hashed.
+    pub fn set_entry_hashes(&mut self, enabled: bool) {
+        self.entry_hashes = enabled;
+    }
+
+    /// Also compute SHA-1 for manifest entries (see [`set_entry_hashes`](Self::set_entry_hashes)
+    /// and [`GmaReader::manifest`]).
+    pub fn set_entry_sha1(&mut self, enabled: bool) {
+        self.entry_sha1 = enabled;
+    }
+
+    /// Require the metadata table's file indices to run 1, 2, 3, ... as gmad
+    /// writes them; anything else fails with [`GmaError::InvalidFileIndex`].
+    pub fn set_strict_indices(&mut self, enabled: bool) {
+        self.strict_indices = enabled;
+    }
+
+    /// Fail with [`GmaError::UnsafePath`] on names that fail [`is_safe_path`]
+    /// (absolute, `..`, drive letters). Extraction always checks this.
+    pub fn set_reject_unsafe_paths(&mut self, enabled: bool) {
+        self.reject_unsafe_paths = enabled;
+    }
+
+    /// Check each loaded entry's content against its declared CRC-32 and fail
+    /// with [`GmaError::CrcMismatch`] if they differ. Entries declaring 0 (no
+    /// CRC) and entries skipped by a filter aren't checked.
+    pub fn set_verify_crcs(&mut self, enabled: bool) {
+        self.verify_crcs = enabled;
+    }
+
+    /// Fail with [`GmaError::InvalidUtf8`] on entry names that aren't UTF-8,
+    /// instead of decoding them lossily. Ignored with a
+    /// [`set_name_decoder`](Self::set_name_decoder).
+    pub fn set_require_utf8_names(&mut self, enabled: bool) {
+        self.require_utf8_names = enabled;
+    }
+
+    /// Reject tables listing more than `limit` entries with [`GmaError::TooManyEntries`].
+    pub fn set_max_entries(&mut self, limit: usize) {
+        self.max_entries = Some(limit);
+    }
+
+    /// Reject entries declaring more than `limit` bytes with [`GmaError::EntryTooLarge`].
+    pub fn set_max_entry_size(&mut self, limit: u64) {
+        self.max_entry_size = Some(limit);
+    }
+
+    /// Reject tables whose declared sizes add up to more than `limit` bytes with
+    /// [`GmaError::ArchiveTooLarge`].
+    pub fn set_max_content_size(&mut self, limit: u64) {
+        self.max_content_size = Some(limit);
+    }
+
+    /// Longest entry name accepted, in bytes; longer ones fail with
+    /// [`GmaError::NameTooLong`] before being buffered. Defaults to 1024.
+    pub fn set_max_name_len(&mut self, limit: usize) {
+        self.max_name_len = Some(limit);
+    }
+
+    /// Longest addon name, description, author or required content item accepted,
+    /// in bytes; longer ones fail with [`GmaError::HeaderStringTooLong`] before
+    /// being buffered. Unlimited by default.
+    pub fn set_max_header_string_len(&mut self, limit: usize) {
+        self.max_header_string_len = Some(limit);
+    }
+
+    /// Reject headers listing more than `limit` required content items with
+    /// [`GmaError::TooManyRequiredContent`].
+    pub fn set_max_required_content(&mut self, limit: usize) {
+        self.max_required_content = Some(limit);
+    }
+
+    /// Abort with [`GmaError::Cancelled`] once `flag` is set, e.g. from a UI thread.
+    ///
+    /// Checked on every read from the underlying stream, so a huge entry is
+    /// interrupted mid-content rather than after it.
+    pub fn set_cancel_flag(&mut self, flag: Arc<AtomicBool>) {
+        self.cancel = Some(flag);
+    }
+
+    fn decode_name(&self, bytes: &[u8]) -> String {
+        match self.name_decoder {
+            Some(decode) => decode(bytes),
+            None => String::from_utf8_lossy(bytes).into_owned(),
+        }
+    }
+}
 
 /// Read a GMA from any `Read`. Returns the list of entries with names and contents.
+#[inline]
 pub fn read<R: Read>(reader: R) -> Result<Vec<GMAFile>, GmaError> {
-    let mut r = BufReader::new(reader);
+    read_with(reader, &ReadOptions::default()).map(GmaArchive::into_entries)
+}
+
+/// Like [`read`], but with explicit [`ReadOptions`]; also returns any warnings.
+#[inline]
+pub fn read_with<R: Read>(reader: R, opts: &ReadOptions) -> Result<GmaArchive, GmaError> {
+    read_selected(GmaReader::with_options(reader, opts)?, |_| true)
+}
+
+/// Like [`read_with`], for readers that already buffer (`BufReader`, `&[u8]`, ...),
+/// so no second buffer is layered on top.
+#[inline]
+pub fn read_buffered<R: BufRead>(reader: R, opts: &ReadOptions) -> Result<GmaArchive, GmaError> {
+    read_selected(GmaReader::from_buffered(reader, opts)?, |_| true)
+}
+
+/// Like [`read`], but only entries for which `filter` returns `true` are loaded;
+/// the content of the others is skipped without being allocated.
+#[inline]
+pub fn read_filtered<R, F>(reader: R, filter: F) -> Result<Vec<GMAFile>, GmaError>
+where
+    R: Read,
+    F: FnMut(&EntryMeta) -> bool,
+{
+    read_selected(GmaReader::new(reader)?, filter).map(GmaArchive::into_entries)
+}
+
+fn read_selected<R, F>(mut gr: GmaReader<R>, mut filter: F) -> Result<GmaArchive, GmaError>
+where
+    R: BufRead,
+    F: FnMut(&EntryMeta) -> bool,
+{
+    // Contents — read in the same order
+    let mut entries = Vec::new();
+    let mut manifest = Vec::new();
+    while let Some(mut entry) = gr.next_entry()? {
+        let meta = entry.meta();
+        if !filter(meta) {
+            continue; // skipped by the next `next_entry` call
+        }
+        let len =
+            usize::try_from(meta.size).map_err(|_| GmaError::SizeOutOfRange(meta.size as i64))?;
+        let meta = meta.clone();
+        // Grow with the data actually present rather than trusting the declared size.
+        let mut content = Vec::with_capacity(len.min(PREALLOC_LIMIT));
+        entry.read_to_end(&mut content)?;
+        let truncated = content.len() != len;
+        if truncated {
+            let read = content.len() as u64;
+            if !gr.opts.recover_truncated {
+                return Err(meta.truncated(read));
+            }
+            gr.warnings.push(ReadWarning::TruncatedEntry {
+                name: meta.name.clone(),
+                read,
+                expected: meta.size,
+            });
+        }
+        if gr.opts.verify_crcs && !truncated && meta.crc != 0 {
+            let mut crc = Crc32::new();
+            crc.update(&content);
+            let actual = crc.finish();
+            if actual != meta.crc {
+                return Err(GmaError::CrcMismatch {
+                    name: meta.name,
+                    declared: meta.crc,
+                    actual,
+                });
+            }
+        }
+        if gr.opts.entry_hashes {
+            let mut hasher = EntryHasher::new(gr.opts.entry_sha1);
+            hasher.update(&content);
+            manifest.push(hasher.finish(&meta.name));
+        }
+        entries.push(meta.with_content(content));
+        if truncated {
+            // The stream is over; nothing else can follow.
+            break;
+        }
+    }
+
+    let archive = GmaArchive::new(
+        gr.info,
+        entries,
+        gr.warnings,
+        manifest,
+        gr.archive_crc,
+        gr.trailer,
+    );
+    #[cfg(feature = "json")]
+    if gr.opts.parse_addon_json {
+        let mut warnings = archive.warnings;
+        let addon_json = parse_addon_json(&archive.entries, &mut warnings);
+        return Ok(GmaArchive {
+            warnings,
+            addon_json,
+            ..archive
+        });
+    }
+    Ok(archive)
+}
+
+/// The `addon.json` among `entries`, if there is one and it parses.
+#[cfg(feature = "json")]
+fn parse_addon_json(
+    entries: &[GMAFile],
+    warnings: &mut Vec<ReadWarning>,
+) -> Option<crate::AddonJson> {
+    let e = entries
+        .iter()
+        .find(|e| e.name.eq_ignore_ascii_case("addon.json"))?;
+    match crate::AddonJson::parse(&String::from_utf8_lossy(&e.content)) {
+        Ok(parsed) => Some(parsed),
+        Err(err) => {
+            warnings.push(ReadWarning::InvalidAddonJson(err.to_string()));
+            None
+        }
+    }
+}
+
+/// Streaming reader: parses the header and metadata table up front, then hands
+/// out entry contents one at a time without buffering them.
+///
+/// `R` is the buffered source; [`GmaReader::new`] wraps plain readers in a `BufReader`.
+pub struct GmaReader<R> {
+    r: Counter<R>,
+    info: AddonInfo,
+    table: Vec<EntryMeta>,
+    warnings: Vec<ReadWarning>,
+    next: usize,
+    /// End offset of the last entry handed out (or of the metadata table).
+    cursor_end: u64,
+    content_start: u64,
+    finished: bool,
+    archive_crc: Option<u32>,
+    trailer: u32,
+    opts: ReadOptions,
+}
+
+impl<R: Read> GmaReader<BufReader<R>> {
+    #[inline]
+    pub fn new(reader: R) -> Result<Self, GmaError> {
+        Self::with_options(reader, &ReadOptions::default())
+    }
+
+    pub fn with_options(reader: R, opts: &ReadOptions) -> Result<Self, GmaError> {
+        let buffered = match opts.buffer_capacity {
+            Some(cap) => BufReader::with_capacity(cap, reader),
+            None => BufReader::new(reader),
+        };
+        GmaReader::from_buffered(buffered, opts)
+    }
+}
+
+impl<R: BufRead> GmaReader<R> {
+    /// Use an already-buffered reader as is.
+    pub fn from_buffered(reader: R, opts: &ReadOptions) -> Result<Self, GmaError> {
+        let mut r = Counter::new(reader);
+        if opts.hash_archive || opts.trailer_policy == TrailerPolicy::Lenient {
+            r.crc = Some(Crc32::new());
+        }
+        r.cancel = opts.cancel.clone();
+        let info = read_header(&mut r, opts)?;
+        let mut warnings = Vec::new();
+        let table = read_entry_table(&mut r, opts, &mut warnings)?;
+        let content_start = r.pos;
+        Ok(Self {
+            r,
+            info,
+            table,
+            warnings,
+            next: 0,
+            cursor_end: content_start,
+            content_start,
+            finished: false,
+            archive_crc: None,
+            trailer: 0,
+            opts: opts.clone(),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.info.name
+    }
+
+    pub fn description(&self) -> &str {
+        &self.info.description
+    }
+
+    pub fn author(&self) -> &str {
+        &self.info.author
+    }
+
+    /// Every header field, including the ones the game ignores.
+    pub fn info(&self) -> &AddonInfo {
+        &self.info
+    }
+
+    /// The full metadata table.
+    pub fn entries(&self) -> &[EntryMeta] {
+        &self.table
+    }
+
+    /// The verified whole-archive CRC, once the trailer has been read.
+    pub fn archive_crc(&self) -> Option<u32> {
+        self.archive_crc
+    }
+
+    /// The trailing marker as read, once it has been: 0, or the verified
+    /// whole-archive CRC.
+    pub fn trailer(&self) -> u32 {
+        self.trailer
+    }
+
+    /// Non-fatal problems noticed while parsing the metadata.
+    pub fn warnings(&self) -> &[ReadWarning] {
+        &self.warnings
+    }
+
+    /// Sizes the archive declares, known before any content is read.
+    pub fn declared_size(&self) -> DeclaredSize {
+        DeclaredSize {
+            // Offsets were overflow-checked while parsing the table.
+            content: self.table.iter().map(|e| e.size).sum(),
+            // Header + metadata table, plus the trailing u32 marker
+            overhead: self.content_start + 4,
+        }
+    }
+
+    /// Advance to the next entry and return a reader over its content.
+    ///
+    /// Any unread content of the previous entry is skipped. Returns `None` once
+    /// every entry has been visited and the trailing marker checked.
+    pub fn next_entry(&mut self) -> Result<Option<EntryReader<'_, R>>, GmaError> {
+        let unread = self.cursor_end - self.r.pos;
+        let skipped = io::copy(&mut (&mut self.r).take(unread), &mut io::sink())?;
+        if skipped != unread {
+            // Only possible mid-entry: the table itself was fully parsed.
+            let prev = &self.table[self.next - 1];
+            return Err(prev.truncated(prev.size - (unread - skipped)));
+        }
+
+        let Some(meta) = self.table.get(self.next) else {
+            if !self.finished {
+                // Final trailing u32 zero
+                let archive_crc = self.r.crc.map(Crc32::finish);
+                let trailing = field(&mut self.r, ParseField::TrailingMarker, read_u32)?;
+                let kind = match trailing {
+                    0 => None,
+                    t if archive_crc == Some(t) => Some(TrailerKind::ArchiveCrc),
+                    t if t.to_le_bytes() == *HEADER => Some(TrailerKind::NextArchive),
+                    _ => Some(TrailerKind::Garbage),
+                };
+                let accepted = match (self.opts.trailer_policy, kind) {
+                    (_, None) => true,
+                    (TrailerPolicy::Strict, Some(_)) => false,
+                    (TrailerPolicy::Lenient, Some(TrailerKind::ArchiveCrc)) => {
+                        self.archive_crc = archive_crc;
+                        true
+                    }
+                    (TrailerPolicy::Lenient, Some(_)) => false,
+                };
+                if let (false, Some(kind)) = (accepted, kind) {
+                    return Err(GmaError::TrailingMarkerMismatch {
+                        found: trailing,
+                        kind,
+                    });
+                }
+                self.trailer = trailing;
+                self.finished = true;
+            }
+            return Ok(None);
+        };
+
+        self.next += 1;
+        self.cursor_end = meta.offset + meta.size;
+        Ok(Some(EntryReader {
+            meta,
+            content: (&mut self.r).take(meta.size),
+        }))
+    }
+
+    /// Stream every entry through CRC-32 and report it to `f`, without keeping contents.
+    pub fn audit<F: FnMut(&EntryAudit<'_>)>(mut self, mut f: F) -> Result<(), GmaError> {
+        let mut buf = [0u8; 8192];
+        while let Some(mut entry) = self.next_entry()? {
+            let mut crc = Crc32::new();
+            loop {
+                let n = entry.read(&mut buf)?;
+                if n == 0 {
+                    break;
+                }
+                crc.update(&buf[..n]);
+            }
+            let missing = entry.content.limit();
+            if missing != 0 {
+                return Err(entry.meta.truncated(entry.meta.size - missing));
+            }
+            f(&EntryAudit {
+                name: &entry.meta.name,
+                declared_size: entry.meta.size,
+                declared_crc: entry.meta.crc,
+                actual_crc: crc.finish(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Stream every entry through SHA-256 (plus SHA-1 if
+    /// [`ReadOptions::set_entry_sha1`] is on) without keeping contents.
+    pub fn manifest(mut self) -> Result<Vec<ManifestEntry>, GmaError> {
+        let mut buf = [0u8; 8192];
+        let mut manifest = Vec::with_capacity(self.table.len());
+        let sha1 = self.opts.entry_sha1;
+        while let Some(mut entry) = self.next_entry()? {
+            let mut hasher = EntryHasher::new(sha1);
+            loop {
+                let n = entry.read(&mut buf)?;
+                if n == 0 {
+                    break;
+                }
+                hasher.update(&buf[..n]);
+            }
+            let missing = entry.content.limit();
+            if missing != 0 {
+                return Err(entry.meta.truncated(entry.meta.size - missing));
+            }
+            manifest.push(hasher.finish(&entry.meta.name));
+        }
+        Ok(manifest)
+    }
+
+    /// Read every entry, reporting progress as [`ReadEvent`]s: a start and finish
+    /// per entry with a progress event for each chunk in between, so huge single
+    /// entries can show their own progress. Contents aren't kept.
+    pub fn events(self) -> ReadEvents<R> {
+        ReadEvents {
+            reader: self,
+            current: None,
+            buf: vec![0; 64 * 1024],
+            done: false,
+        }
+    }
+
+    /// Callback form of [`events`](Self::events).
+    pub fn for_each_event<F: FnMut(&ReadEvent)>(self, mut f: F) -> Result<(), GmaError> {
+        for event in self.events() {
+            f(&event?);
+        }
+        Ok(())
+    }
+}
+
+/// Progress of [`GmaReader::events`]; `index` is the entry's position in the table.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReadEvent {
+    EntryStarted {
+        index: usize,
+        name: String,
+        size: u64,
+    },
+    /// `read` of the entry's `size` bytes have been consumed.
+    EntryProgress { index: usize, read: u64, size: u64 },
+    /// The entry was read in full; `crc` is the CRC-32 of its content.
+    EntryFinished { index: usize, crc: u32 },
+}
 
+/// Iterator returned by [`GmaReader::events`]. Stops after the first error.
+pub struct ReadEvents<R> {
+    reader: GmaReader<R>,
+    current: Option<CurrentEntry>,
+    buf: Vec<u8>,
+    done: bool,
+}
+
+struct CurrentEntry {
+    index: usize,
+    read: u64,
+    size: u64,
+    crc: Crc32,
+}
+
+impl<R: BufRead> ReadEvents<R> {
+    fn step(&mut self) -> Result<Option<ReadEvent>, GmaError> {
+        let Some(cur) = &mut self.current else {
+            let Some(entry) = self.reader.next_entry()? else {
+                return Ok(None);
+            };
+            let (name, size) = (entry.meta().name.clone(), entry.meta().size);
+            let index = self.reader.next - 1;
+            self.current = Some(CurrentEntry {
+                index,
+                read: 0,
+                size,
+                crc: Crc32::new(),
+            });
+            return Ok(Some(ReadEvent::EntryStarted { index, name, size }));
+        };
+
+        if cur.read == cur.size {
+            let index = cur.index;
+            let crc = self.current.take().map_or(0, |c| c.crc.finish());
+            return Ok(Some(ReadEvent::EntryFinished { index, crc }));
+        }
+        let want = (cur.size - cur.read).min(self.buf.len() as u64) as usize;
+        let n = self.reader.r.read(&mut self.buf[..want])?;
+        if n == 0 {
+            return Err(self.reader.table[cur.index].truncated(cur.read));
+        }
+        cur.crc.update(&self.buf[..n]);
+        cur.read += n as u64;
+        Ok(Some(ReadEvent::EntryProgress {
+            index: cur.index,
+            read: cur.read,
+            size: cur.size,
+        }))
+    }
+}
+
+impl<R: BufRead> Iterator for ReadEvents<R> {
+    type Item = Result<ReadEvent, GmaError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let res = self.step().transpose();
+        self.done = !matches!(res, Some(Ok(_)));
+        res
+    }
+}
+
+/// Content of one entry, handed out by [`GmaReader::next_entry`].
+pub struct EntryReader<'a, R> {
+    meta: &'a EntryMeta,
+    content: io::Take<&'a mut Counter<R>>,
+}
+
+impl<R> EntryReader<'_, R> {
+    pub fn meta(&self) -> &EntryMeta {
+        self.meta
+    }
+}
+
+impl<R: BufRead> Read for EntryReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.content.read(buf)
+    }
+}
+
+/// One entry's integrity record, produced by [`GmaReader::audit`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EntryAudit<'a> {
+    pub name: &'a str,
+    pub declared_size: u64,
+    /// CRC-32 stored in the metadata table (0 if the packer didn't compute one).
+    pub declared_crc: u32,
+    /// CRC-32 of the content actually read.
+    pub actual_crc: u32,
+}
+
+impl EntryAudit<'_> {
+    #[inline]
+    pub fn crc_matches(&self) -> bool {
+        self.declared_crc == self.actual_crc
+    }
+}
+
+/// Byte counts declared by an archive's metadata. See [`declared_size`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct DeclaredSize {
+    /// Sum of all declared entry sizes.
+    pub content: u64,
+    /// Header, metadata table and trailing marker.
+    pub overhead: u64,
+}
+
+impl DeclaredSize {
+    /// Expected length of the whole archive.
+    #[inline]
+    pub fn total(&self) -> u64 {
+        self.content + self.overhead
+    }
+}
+
+/// Parse only the metadata table and return the sizes it declares.
+///
+/// Useful for quotas, preallocation and progress bars before reading content.
+pub fn declared_size<R: Read>(reader: R) -> Result<DeclaredSize, GmaError> {
+    Ok(GmaReader::new(reader)?.declared_size())
+}
+
+/// Parse only the metadata table and return each entry's name, size and offset.
+///
+/// Content is never read, so this is cheap even for huge archives.
+pub fn list_entries<R: Read>(reader: R) -> Result<Vec<EntryMeta>, GmaError> {
+    let mut r = Counter::new(BufReader::new(reader));
+    let opts = ReadOptions::default();
+    read_header(&mut r, &opts)?;
+    read_entry_table(&mut r, &opts, &mut Vec::new())
+}
+
+/// Parse only the metadata table and return the entry names.
+pub fn list_names<R: Read>(reader: R) -> Result<Vec<String>, GmaError> {
+    Ok(list_entries(reader)?.into_iter().map(|e| e.name).collect())
+}
+
+fn read_header<R: BufRead>(r: &mut Counter<R>, opts: &ReadOptions) -> Result<AddonInfo, GmaError> {
     // Header
-    let mut hdr = [0u8; 4];
-    r.read_exact(&mut hdr)?;
+    let hdr = field(r, ParseField::Magic, |r| {
+        let mut hdr = [0u8; 4];
+        r.read_exact(&mut hdr)?;
+        Ok(hdr)
+    })?;
     if &hdr != HEADER {
         return Err(GmaError::InvalidHeader(hdr));
     }
 
     // Version (int8)
-    let v = read_i8(&mut r)?;
-    if v != VERSION {
+    let v = field(r, ParseField::Version, read_i8)?;
+    if !(MIN_VERSION..=VERSION).contains(&v) {
         return Err(GmaError::InvalidVersion(v));
     }
 
-    // SteamID64 (i64) — discard
-    discard_exact(&mut r, 8)?;
+    let steam_id64 = field(r, ParseField::SteamId, read_i64)?;
+    let timestamp = field(r, ParseField::Timestamp, read_u64)?;
+    // Version 1 predates the required-content list
+    let mut required_content = Vec::new();
+    if v > 1 {
+        loop {
+            let item = read_header_string(r, opts, ParseField::RequiredContent)?;
+            if item.is_empty() {
+                break;
+            }
+            if let Some(limit) = opts.max_required_content
+                && required_content.len() >= limit
+            {
+                return Err(GmaError::TooManyRequiredContent { limit });
+            }
+            required_content.push(item);
+        }
+    }
+
+    // Addon name / description / author
+    let name = read_header_string(r, opts, ParseField::Name)?;
+    let description = read_header_string(r, opts, ParseField::Description)?;
+    let author = read_header_string(r, opts, ParseField::Author)?;
 
-    // Timestamp (u64) — discard
-    discard_exact(&mut r, 8)?;
+    let addon_version = field(r, ParseField::AddonVersion, read_u32)? as i32;
 
-    // Required content (u8) — discard
-    discard_exact(&mut r, 1)?;
+    Ok(AddonInfo {
+        version: v,
+        name,
+        description,
+        author,
+        steam_id64,
+        timestamp,
+        required_content,
+        addon_version,
+    })
+}
 
-    // Addon name / description / author — discard their values but still parse
-    read_c_string(&mut r)?; // name
-    read_c_string(&mut r)?; // description
-    read_c_string(&mut r)?; // author
+/// `name` as the game looks it up: ASCII lowercase, with `/` separators.
+fn path_key(name: &[u8]) -> Vec<u8> {
+    name.iter().map(|&b| fold_path_byte(b)).collect()
+}
 
-    // Addon version (i32) — discard
-    discard_exact(&mut r, 4)?;
+/// A header C string, bounded by [`ReadOptions::set_max_header_string_len`].
+fn read_header_string<R: BufRead>(
+    r: &mut Counter<R>,
+    opts: &ReadOptions,
+    at: ParseField,
+) -> Result<String, GmaError> {
+    let Some(limit) = opts.max_header_string_len else {
+        return field(r, at, read_c_string);
+    };
+    let bytes = field(r, at, |r| read_c_bytes_limited(r, limit))?
+        .ok_or(GmaError::HeaderStringTooLong { field: at, limit })?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
 
-    // Metadata loop
-    let mut entries_meta: Vec<(String, i64)> = Vec::with_capacity(10);
+/// Parse the metadata loop. Offsets are filled in once the table's end is known.
+fn read_entry_table<R: BufRead>(
+    r: &mut Counter<R>,
+    opts: &ReadOptions,
+    warnings: &mut Vec<ReadWarning>,
+) -> Result<Vec<EntryMeta>, GmaError> {
+    let mut table = Vec::with_capacity(10);
+    let mut seen = HashSet::new();
+    let mut declared = 0u64;
     loop {
-        let idx = read_u32(&mut r)?;
+        let index = table.len();
+        let idx = field(r, ParseField::FileIndex(index), read_u32)?;
         if idx == 0 {
             break;
         }
+        let expected = index as u32 + 1;
+        if opts.strict_indices && idx != expected {
+            return Err(GmaError::InvalidFileIndex { expected, got: idx });
+        }
+        if let Some(limit) = opts.max_entries
+            && index >= limit
+        {
+            return Err(GmaError::TooManyEntries { limit });
+        }
 
-        let name = read_c_string(&mut r)?;
-        let size = read_i64(&mut r)?;
-        if size < 0 {
-            return Err(GmaError::SizeOutOfRange(size));
+        let max_name = opts.max_name_len.unwrap_or(DEFAULT_MAX_NAME_LEN);
+        let name = field(r, ParseField::EntryName(index), |r| {
+            read_c_bytes_limited(r, max_name)
+        })?
+        .ok_or(GmaError::NameTooLong {
+            index,
+            limit: max_name,
+        })?;
+        let size = field(r, ParseField::EntrySize(index), |r| match read_i64(r)? {
+            size if size < 0 => Err(GmaError::SizeOutOfRange(size)),
+            size => Ok(size as u64),
+        })?;
+        if let Some(limit) = opts.max_entry_size
+            && size > limit
+        {
+            return Err(GmaError::EntryTooLarge {
+                name: opts.decode_name(&name),
+                size,
+                limit,
+            });
         }
 
-        // CRC32 (u32) — discard
-        discard_exact(&mut r, 4)?;
+        let crc = field(r, ParseField::EntryCrc(index), read_u32)?;
 
-        entries_meta.push((name, size));
-    }
+        // Everything declared so far, plus the table terminator and the trailing
+        // marker, must still fit in what's left of the stream.
+        declared = declared
+            .checked_add(size)
+            .ok_or(GmaError::SizeOutOfRange(size as i64))?;
+        if let Some(limit) = opts.max_content_size
+            && declared > limit
+        {
+            return Err(GmaError::ArchiveTooLarge {
+                size: declared,
+                limit,
+            });
+        }
+        if let Some(len) = opts.stream_len
+            && r.pos.saturating_add(declared).saturating_add(8) > len
+        {
+            return Err(GmaError::SizeOutOfRange(size as i64));
+        }
 
-    // Contents — read in the same order
-    let mut entries = Vec::with_capacity(entries_meta.len());
-    for (name, size) in entries_meta {
-        if size > usize::MAX as i64 {
-            return Err(GmaError::SizeOutOfRange(size));
+        if opts.duplicates != DuplicatePolicy::Allow && !seen.insert(path_key(&name)) {
+            let name = opts.decode_name(&name);
+            match opts.duplicates {
+                DuplicatePolicy::Error => return Err(GmaError::DuplicateEntry(name)),
+                _ => warnings.push(ReadWarning::DuplicateEntry(name)),
+            }
+        }
+
+        if opts.require_utf8_names
+            && opts.name_decoder.is_none()
+            && std::str::from_utf8(&name).is_err()
+        {
+            return Err(GmaError::InvalidUtf8 {
+                name_bytes: name,
+                index,
+            });
         }
 
-        let mut content = vec![0u8; size as usize];
-        r.read_exact(&mut content)?;
-        entries.push(GMAFile {
-            name,
+        let decoded = opts.decode_name(&name);
+        if opts.reject_unsafe_paths && !is_safe_path(&decoded) {
+            return Err(GmaError::UnsafePath {
+                name: decoded,
+                index,
+            });
+        }
+
+        table.push(EntryMeta {
+            name: decoded,
+            name_bytes: name,
             size,
-            content,
+            crc,
+            offset: 0,
         });
     }
 
-    // Final trailing u32 zero
-    let trailing = read_u32(&mut r)?;
-    if trailing != 0 {
-        return Err(GmaError::TrailingMarkerMismatch(trailing));
+    // Contents follow the table back to back
+    let mut offset = r.pos;
+    for meta in &mut table {
+        meta.offset = offset;
+        offset = offset
+            .checked_add(meta.size)
+            .ok_or(GmaError::SizeOutOfRange(meta.size as i64))?;
     }
 
-    Ok(entries)
+    Ok(table)
 }
 
-fn discard_exact<R: Read>(r: &mut R, n: u64) -> Result<(), GmaError> {
-    let copied = io::copy(&mut r.take(n), &mut io::sink())?;
-    if copied == n {
-        Ok(())
-    } else {
-        Err(io::Error::from(io::ErrorKind::UnexpectedEof).into())
+/// Tracks how many bytes have been consumed from the underlying stream.
+///
+/// Optionally also keeps a CRC-32 of everything consumed.
+struct Counter<R> {
+    inner: R,
+    pos: u64,
+    crc: Option<Crc32>,
+    cancel: Option<Arc<AtomicBool>>,
+}
+
+impl<R> Counter<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            pos: 0,
+            crc: None,
+            cancel: None,
+        }
+    }
+
+    fn check_cancel(&self) -> io::Result<()> {
+        match &self.cancel {
+            Some(flag) if flag.load(Ordering::Relaxed) => Err(io::Error::other(Cancelled)),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<R: Read> Read for Counter<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.check_cancel()?;
+        let n = self.inner.read(buf)?;
+        self.pos += n as u64;
+        if let Some(crc) = &mut self.crc {
+            crc.update(&buf[..n]);
+        }
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for Counter<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.check_cancel()?;
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos += amt as u64;
+        if let Some(crc) = &mut self.crc {
+            // Still buffered: `amt` never exceeds what the last `fill_buf` returned.
+            if let Ok(buf) = self.inner.fill_buf() {
+                crc.update(&buf[..amt]);
+            }
+        }
+        self.inner.consume(amt);
     }
 }
 
+/// Read the field starting at the current position, attaching where it was
+/// to any error (see [`GmaError::Parse`]).
+fn field<R, T>(
+    r: &mut Counter<R>,
+    field: ParseField,
+    read: impl FnOnce(&mut Counter<R>) -> Result<T, GmaError>,
+) -> Result<T, GmaError> {
+    let offset = r.pos;
+    read(r).map_err(|e| e.at(offset, field))
+}
+
 fn read_i8<R: Read>(r: &mut R) -> Result<i8, GmaError> {
+    Ok(read_u8(r)? as i8)
+}
+
+fn read_u8<R: Read>(r: &mut R) -> Result<u8, GmaError> {
     let mut b = [0u8; 1];
     r.read_exact(&mut b)?;
-    Ok(i8::from_le_bytes(b))
+    Ok(b[0])
 }
 
 fn read_i64<R: Read>(r: &mut R) -> Result<i64, GmaError> {
@@ -102,6 +1070,12 @@ fn read_i64<R: Read>(r: &mut R) -> Result<i64, GmaError> {
     Ok(i64::from_le_bytes(b))
 }
 
+fn read_u64<R: Read>(r: &mut R) -> Result<u64, GmaError> {
+    let mut b = [0u8; 8];
+    r.read_exact(&mut b)?;
+    Ok(u64::from_le_bytes(b))
+}
+
 fn read_u32<R: Read>(r: &mut R) -> Result<u32, GmaError> {
     let mut b = [0u8; 4];
     r.read_exact(&mut b)?;
@@ -109,6 +1083,11 @@ fn read_u32<R: Read>(r: &mut R) -> Result<u32, GmaError> {
 }
 
 fn read_c_string<R: BufRead>(r: &mut R) -> Result<String, GmaError> {
+    let buf = read_c_bytes(r)?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+fn read_c_bytes<R: BufRead>(r: &mut R) -> Result<Vec<u8>, GmaError> {
     let mut buf = Vec::with_capacity(32);
     let n = r.read_until(0, &mut buf)?; // includes the 0 delimiter if found
     if n == 0 || *buf.last().unwrap_or(&1) != 0 {
@@ -117,5 +1096,360 @@ fn read_c_string<R: BufRead>(r: &mut R) -> Result<String, GmaError> {
     }
     buf.pop(); // drop the '\0'
     // Per writer, strings shouldn't contain interior nulls; if present, they'd have truncated here.
-    Ok(String::from_utf8_lossy(&buf).into_owned())
+    Ok(buf)
+}
+
+/// Like [`read_c_bytes`], but `None` once `limit` bytes pass without a terminator.
+fn read_c_bytes_limited<R: BufRead>(r: &mut R, limit: usize) -> Result<Option<Vec<u8>>, GmaError> {
+    let mut buf = Vec::with_capacity(32);
+    let n = r.by_ref().take(limit as u64 + 1).read_until(0, &mut buf)?;
+    if buf.last() == Some(&0) {
+        buf.pop();
+        return Ok(Some(buf));
+    }
+    if n > limit {
+        return Ok(None);
+    }
+    Err(GmaError::MissingNullTerminator)
+}
+
+/// Decode Windows-1252 bytes, as found in names from old Windows-packed addons.
+///
+/// Usable as a [`NameDecoder`].
+pub fn decode_windows_1252(bytes: &[u8]) -> String {
+    // 0x80..=0x9F are the only bytes that differ from Latin-1.
+    const HIGH: [char; 32] = [
+        '\u{20AC}', '\u{0081}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}',
+        '\u{2021}', '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{008D}',
+        '\u{017D}', '\u{008F}', '\u{0090}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}',
+        '\u{2022}', '\u{2013}', '\u{2014}', '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}',
+        '\u{0153}', '\u{009D}', '\u{017E}', '\u{0178}',
+    ];
+    bytes
+        .iter()
+        .map(|&b| match b {
+            0x80..=0x9F => HIGH[(b - 0x80) as usize],
+            _ => b as char,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Builder;
+
+    fn archive_with_crc() -> Vec<u8> {
+        let mut b = Builder::new("crc", 1);
+        b.set_archive_crc(true)
+            .file_from_bytes("lua/a.lua", b"print(1)".to_vec())
+            .file_from_bytes("lua/b.lua", b"print(2)".to_vec());
+        b.write_to_vec().unwrap()
+    }
+
+    #[test]
+    fn archive_crc_round_trips_with_default_options() {
+        let data = archive_with_crc();
+        let trailer = u32::from_le_bytes(data[data.len() - 4..].try_into().unwrap());
+        assert_ne!(trailer, 0);
+
+        let archive = read_with(&data[..], &ReadOptions::default()).unwrap();
+        assert_eq!(archive.len(), 2);
+        assert_eq!(archive.trailer(), trailer);
+        assert_eq!(archive.archive_crc(), Some(trailer));
+    }
+
+    #[test]
+    fn corrupt_trailer_is_an_error_under_either_policy() {
+        let mut data = archive_with_crc();
+        let at = data.len() - 4;
+        data[at] ^= 1;
+        let plain = {
+            let mut plain = Builder::new("plain", 1).write_to_vec().unwrap();
+            let len = plain.len();
+            plain[len - 4..].copy_from_slice(&0xDEAD_BEEFu32.to_le_bytes());
+            plain
+        };
+        for policy in [TrailerPolicy::Lenient, TrailerPolicy::Strict] {
+            let mut opts = ReadOptions::default();
+            opts.set_trailer_policy(policy);
+            for data in [&data, &plain] {
+                let err = read_with(&data[..], &opts).unwrap_err();
+                assert!(
+                    matches!(
+                        err,
+                        GmaError::TrailingMarkerMismatch {
+                            kind: TrailerKind::Garbage,
+                            ..
+                        }
+                    ),
+                    "{policy:?}: {err}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn archive_crc_is_verified_when_hashing() {
+        let mut data = archive_with_crc();
+        let mut opts = ReadOptions::default();
+        opts.set_hash_archive(true);
+        let trailer = read_with(&data[..], &opts).unwrap().trailer();
+        assert_eq!(
+            read_with(&data[..], &opts).unwrap().archive_crc(),
+            Some(trailer)
+        );
+
+        // Flip a content byte: the stored CRC no longer matches
+        let at = data.len() - 5;
+        data[at] ^= 1;
+        let err = read_with(&data[..], &opts).unwrap_err();
+        assert!(matches!(
+            err,
+            GmaError::TrailingMarkerMismatch {
+                kind: TrailerKind::Garbage,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn strict_policy_reports_archive_crc() {
+        let data = archive_with_crc();
+        let mut opts = ReadOptions::default();
+        opts.set_trailer_policy(TrailerPolicy::Strict);
+        let err = read_with(&data[..], &opts).unwrap_err();
+        assert!(matches!(
+            err,
+            GmaError::TrailingMarkerMismatch {
+                kind: TrailerKind::Garbage,
+                ..
+            }
+        ));
+
+        opts.set_hash_archive(true);
+        let err = read_with(&data[..], &opts).unwrap_err();
+        assert!(matches!(
+            err,
+            GmaError::TrailingMarkerMismatch {
+                kind: TrailerKind::ArchiveCrc,
+                ..
+            }
+        ));
+
+        let plain = Builder::new("plain", 1).write_to_vec().unwrap();
+        assert!(read_with(&plain[..], &opts).is_ok());
+    }
+
+    #[test]
+    fn concatenated_archive_is_an_error() {
+        let mut data = Builder::new("a", 1).write_to_vec().unwrap();
+        let len = data.len();
+        data[len - 4..].copy_from_slice(HEADER);
+        let err = read(&data[..]).unwrap_err();
+        assert!(matches!(
+            err,
+            GmaError::TrailingMarkerMismatch {
+                kind: TrailerKind::NextArchive,
+                ..
+            }
+        ));
+    }
+
+    /// An empty version 3 archive with the given header strings.
+    fn header_only(required: &[&str], description: &str) -> Vec<u8> {
+        let mut data = HEADER.to_vec();
+        data.push(3);
+        data.extend_from_slice(&0i64.to_le_bytes());
+        data.extend_from_slice(&0u64.to_le_bytes());
+        for item in required.iter().chain([&""]) {
+            data.extend_from_slice(item.as_bytes());
+            data.push(0);
+        }
+        for s in ["name", description, "author"] {
+            data.extend_from_slice(s.as_bytes());
+            data.push(0);
+        }
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes()); // end of the entry table
+        data.extend_from_slice(&0u32.to_le_bytes()); // trailer
+        data
+    }
+
+    #[test]
+    fn hardened_accepts_a_plain_archive() {
+        let data = archive_with_crc();
+        let archive = read_with(&data[..], &ReadOptions::hardened()).unwrap();
+        assert_eq!(archive.len(), 2);
+        assert!(archive.archive_crc().is_some());
+    }
+
+    #[test]
+    fn header_strings_are_bounded() {
+        let long = "x".repeat(100);
+        let data = header_only(&[], &long);
+        assert!(read_with(&data[..], &ReadOptions::default()).is_ok());
+
+        let mut opts = ReadOptions::default();
+        opts.set_max_header_string_len(100);
+        assert!(read_with(&data[..], &opts).is_ok());
+        opts.set_max_header_string_len(99);
+        let err = read_with(&data[..], &opts).unwrap_err();
+        assert!(matches!(
+            err,
+            GmaError::HeaderStringTooLong {
+                field: ParseField::Description,
+                limit: 99
+            }
+        ));
+
+        let data = header_only(&[&long], "");
+        let err = read_with(&data[..], &opts).unwrap_err();
+        assert!(matches!(
+            err,
+            GmaError::HeaderStringTooLong {
+                field: ParseField::RequiredContent,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn required_content_is_bounded() {
+        let data = header_only(&["a", "b", "c"], "");
+        let mut opts = ReadOptions::default();
+        opts.set_max_required_content(3);
+        assert_eq!(
+            read_with(&data[..], &opts)
+                .unwrap()
+                .info()
+                .required_content
+                .len(),
+            3
+        );
+        opts.set_max_required_content(2);
+        let err = read_with(&data[..], &opts).unwrap_err();
+        assert!(matches!(err, GmaError::TooManyRequiredContent { limit: 2 }));
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn addon_json_is_parsed_on_request() {
+        let mut b = Builder::new("a", 1);
+        b.file_from_bytes(
+            "addon.json",
+            br#"{"title": "A", "type": "tool", "tags": ["fun"], "ignore": ["*.psd"]}"#.to_vec(),
+        );
+        let data = b.write_to_vec().unwrap();
+        let plain = read_with(&data[..], &ReadOptions::default()).unwrap();
+        assert!(plain.addon_json().is_none());
+
+        let mut opts = ReadOptions::default();
+        opts.set_parse_addon_json(true);
+        let archive = read_with(&data[..], &opts).unwrap();
+        let json = archive.addon_json().unwrap();
+        assert_eq!(json.title, "A");
+        assert_eq!(json.addon_type, "tool");
+        assert_eq!(json.tags, ["fun"]);
+        assert_eq!(json.ignore, ["*.psd"]);
+
+        let mut b = Builder::new("a", 1);
+        b.file_from_bytes("addon.json", b"{oops".to_vec());
+        let data = b.write_to_vec().unwrap();
+        let archive = read_with(&data[..], &opts).unwrap();
+        assert!(archive.addon_json().is_none());
+        assert!(matches!(
+            archive.warnings(),
+            [ReadWarning::InvalidAddonJson(_)]
+        ));
+    }
+
+    fn two_files() -> Vec<u8> {
+        let mut b = Builder::new("t", 1);
+        b.file_from_bytes("lua/a.lua", b"aaaa".to_vec())
+            .file_from_bytes("lua/b.lua", b"bbbbbbbb".to_vec());
+        b.write_to_vec().unwrap()
+    }
+
+    #[test]
+    fn offsets_point_at_the_content() {
+        let data = two_files();
+        let entries = list_entries(&data[..]).unwrap();
+        for (e, content) in entries.iter().zip([&b"aaaa"[..], b"bbbbbbbb"]) {
+            let at = e.offset as usize;
+            assert_eq!(&data[at..at + e.size as usize], content);
+        }
+        assert_eq!(entries[1].offset, entries[0].offset + 4);
+    }
+
+    #[test]
+    fn truncated_content_is_an_error_or_recovered() {
+        let data = two_files();
+        // Cut the trailer and the last three content bytes
+        let cut = &data[..data.len() - 7];
+        let err = read(cut).unwrap_err();
+        assert!(matches!(
+            err,
+            GmaError::TruncatedEntry { ref name, read: 5, expected: 8 } if name == "lua/b.lua"
+        ));
+
+        let mut opts = ReadOptions::default();
+        opts.set_recover_truncated(true);
+        let archive = read_with(cut, &opts).unwrap();
+        assert_eq!(archive.entries()[1].content, b"bbbbb");
+        assert_eq!(archive.entries()[1].size, 8);
+        assert!(matches!(
+            archive.warnings(),
+            [ReadWarning::TruncatedEntry {
+                read: 5,
+                expected: 8,
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn parse_errors_report_field_and_offset() {
+        let data = two_files();
+        // Version 3 header: magic, version, steam id, timestamp, then the
+        // (empty) required content list at byte 21
+        let err = read(&data[..23]).unwrap_err();
+        let GmaError::Parse { offset, field, .. } = err else {
+            panic!("{err:?}");
+        };
+        assert_eq!(field, ParseField::Name);
+        assert_eq!(offset, 22);
+
+        // Searched for as a whole, since the timestamp could hold an `l` byte
+        let table = data.windows(9).position(|w| w == b"lua/a.lua").unwrap();
+        let err = read(&data[..table + 3]).unwrap_err();
+        assert!(matches!(
+            err,
+            GmaError::Parse { field: ParseField::EntryName(0), offset, .. } if offset == table as u64
+        ));
+    }
+
+    #[test]
+    fn duplicates_follow_the_policy() {
+        let mut b = Builder::new("t", 1);
+        b.file_from_bytes("lua/a.lua", b"1".to_vec())
+            .file_from_bytes("lua/b.lua", b"2".to_vec())
+            .file_from_bytes("LUA\\A.lua", b"3".to_vec());
+        let data = b.write_to_vec().unwrap();
+
+        assert_eq!(read(&data[..]).unwrap().len(), 3);
+        let mut opts = ReadOptions::default();
+        opts.set_duplicate_policy(DuplicatePolicy::Warn);
+        let archive = read_with(&data[..], &opts).unwrap();
+        assert_eq!(archive.len(), 3);
+        assert!(matches!(
+            archive.warnings(),
+            [ReadWarning::DuplicateEntry(name)] if name == "LUA\\A.lua"
+        ));
+        opts.set_duplicate_policy(DuplicatePolicy::Error);
+        assert!(matches!(
+            read_with(&data[..], &opts),
+            Err(GmaError::DuplicateEntry(name)) if name == "LUA\\A.lua"
+        ));
+    }
 }
@@ -0,0 +1,78 @@
+use crate::wildcard;
+
+/// Rule that maps a source path onto the name it should have in the archive.
+///
+/// Registered with [`Builder::add_rewrite_rule`](crate::Builder::add_rewrite_rule).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RewriteRule {
+    /// Replace a leading `from` with `to`, e.g. `src/lua/` → `lua/`.
+    Prefix { from: String, to: String },
+    /// Wildcard match over the whole name; `$1`..`$9` in `replacement` refer to
+    /// what each `*` / `?` matched, `$$` is a literal `$`.
+    Pattern {
+        pattern: String,
+        replacement: String,
+    },
+}
+
+impl RewriteRule {
+    pub fn prefix(from: impl Into<String>, to: impl Into<String>) -> Self {
+        RewriteRule::Prefix {
+            from: from.into(),
+            to: to.into(),
+        }
+    }
+
+    pub fn pattern(pattern: impl Into<String>, replacement: impl Into<String>) -> Self {
+        RewriteRule::Pattern {
+            pattern: pattern.into(),
+            replacement: replacement.into(),
+        }
+    }
+
+    /// The rewritten name, or `None` if the rule doesn't apply to `name`.
+    pub fn apply(&self, name: &str) -> Option<String> {
+        match self {
+            RewriteRule::Prefix { from, to } => {
+                let rest = name.strip_prefix(from.as_str())?;
+                Some(format!("{to}{rest}"))
+            }
+            RewriteRule::Pattern {
+                pattern,
+                replacement,
+            } => {
+                let caps = wildcard::captures(pattern, name)?;
+                Some(expand(replacement, &caps))
+            }
+        }
+    }
+}
+
+/// Apply the first matching rule, leaving `name` unchanged if none match.
+pub(crate) fn rewrite(rules: &[RewriteRule], name: String) -> String {
+    rules.iter().find_map(|r| r.apply(&name)).unwrap_or(name)
+}
+
+fn expand(replacement: &str, caps: &[&str]) -> String {
+    let mut out = String::with_capacity(replacement.len());
+    let mut chars = replacement.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            out.push(c);
+            continue;
+        }
+        match chars.peek() {
+            Some('$') => {
+                chars.next();
+                out.push('$');
+            }
+            Some(d @ '1'..='9') => {
+                let i = *d as usize - '1' as usize;
+                chars.next();
+                out.push_str(caps.get(i).copied().unwrap_or(""));
+            }
+            _ => out.push('$'),
+        }
+    }
+    out
+}
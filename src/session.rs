@@ -0,0 +1,284 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Take};
+use std::path::{Path, PathBuf};
+
+use crate::{AddonInfo, ArchiveTransaction, Builder, GmaError, GmaReader, same_path};
+
+/// A batch of edits against an archive on disk, applied in a single rewrite.
+///
+/// Opening reads only the header and metadata table. Edits are kept in memory
+/// until [`commit`](Self::commit), which writes the new archive once, streaming
+/// untouched entries straight from the old file, and swaps it in through an
+/// [`ArchiveTransaction`], so a crash leaves either the old or the new archive.
+/// Entry names are matched ASCII case-insensitively, with `\\` and `/` alike.
+#[derive(Debug)]
+pub struct GmaSession {
+    path: PathBuf,
+    info: AddonInfo,
+    entries: Vec<SessionEntry>,
+    modified: bool,
+}
+
+#[derive(Debug)]
+struct SessionEntry {
+    name: String,
+    content: Content,
+}
+
+#[derive(Debug)]
+enum Content {
+    /// Still in the original file.
+    Original {
+        offset: u64,
+        size: u64,
+    },
+    New(Vec<u8>),
+}
+
+impl GmaSession {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, GmaError> {
+        let path = path.as_ref().to_path_buf();
+        let reader = GmaReader::new(BufReader::new(File::open(&path)?))?;
+        let entries = reader
+            .entries()
+            .iter()
+            .map(|e| SessionEntry {
+                name: e.name.clone(),
+                content: Content::Original {
+                    offset: e.offset,
+                    size: e.size,
+                },
+            })
+            .collect();
+        Ok(Self {
+            path,
+            info: reader.info().clone(),
+            entries,
+            modified: false,
+        })
+    }
+
+    /// Header fields as they will be written (apart from the timestamp).
+    pub fn info(&self) -> &AddonInfo {
+        &self.info
+    }
+
+    /// Entry names in their current order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|e| e.name.as_str())
+    }
+
+    /// Whether any edit was made since opening.
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    /// Add an entry, replacing the content of an existing one with the same name.
+    pub fn add(&mut self, name: impl Into<String>, content: Vec<u8>) {
+        let name = name.into();
+        self.modified = true;
+        match self.position(&name) {
+            Some(i) => self.entries[i].content = Content::New(content),
+            None => self.entries.push(SessionEntry {
+                name,
+                content: Content::New(content),
+            }),
+        }
+    }
+
+    /// Remove an entry; `false` if there was none by that name.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let Some(i) = self.position(name) else {
+            return false;
+        };
+        self.entries.remove(i);
+        self.modified = true;
+        true
+    }
+
+    /// Rename an entry, keeping its position. Fails with
+    /// [`GmaError::DuplicateEntry`] if another entry already has the new name;
+    /// `Ok(false)` if there is no entry called `from`.
+    pub fn rename(&mut self, from: &str, to: impl Into<String>) -> Result<bool, GmaError> {
+        let to = to.into();
+        let Some(i) = self.position(from) else {
+            return Ok(false);
+        };
+        if self.position(&to).is_some_and(|j| j != i) {
+            return Err(GmaError::DuplicateEntry(to));
+        }
+        self.entries[i].name = to;
+        self.modified = true;
+        Ok(true)
+    }
+
+    pub fn set_title(&mut self, title: impl Into<String>) {
+        self.info.name = title.into();
+        self.modified = true;
+    }
+
+    pub fn set_description(&mut self, description: impl Into<String>) {
+        self.info.description = description.into();
+        self.modified = true;
+    }
+
+    pub fn set_author(&mut self, author: impl Into<String>) {
+        self.info.author = author.into();
+        self.modified = true;
+    }
+
+    /// Write the edited archive over the original in one journaled rewrite.
+    /// Nothing is written if there were no edits.
+    pub fn commit(self) -> Result<(), GmaError> {
+        if !self.modified {
+            return Ok(());
+        }
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("not a file path: {}", self.path.display()),
+            )
+        };
+        let file_name = self
+            .path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(invalid)?;
+        let dir = match self.path.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p,
+            _ => Path::new("."),
+        };
+
+        let mut b = Builder::new_with_capacity(
+            self.info.name.clone(),
+            self.info.steam_id64,
+            self.entries.len(),
+        );
+        b.set_description(self.info.description.clone());
+        b.set_author(self.info.author.clone());
+        // Read versions are always writable
+        let _ = b.set_target_version(self.info.version);
+        b.set_addon_version(self.info.addon_version);
+        b.set_required_content(self.info.required_content.clone());
+        for e in self.entries {
+            match e.content {
+                Content::New(bytes) => b.file_from_bytes(e.name, bytes),
+                Content::Original { offset, size } => b.file_from_reader(
+                    e.name,
+                    size,
+                    OriginalContent {
+                        path: self.path.clone(),
+                        offset,
+                        size,
+                        file: None,
+                    },
+                ),
+            };
+        }
+
+        let mut tx = ArchiveTransaction::new(dir)?;
+        tx.stage(file_name, &b)?;
+        tx.commit()
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.entries.iter().position(|e| same_path(&e.name, name))
+    }
+}
+
+/// An entry's bytes in the original archive, opened on first read so only one
+/// handle is open at a time while writing.
+struct OriginalContent {
+    path: PathBuf,
+    offset: u64,
+    size: u64,
+    file: Option<Take<File>>,
+}
+
+impl Read for OriginalContent {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.file.is_none() {
+            let mut f = File::open(&self.path)?;
+            f.seek(SeekFrom::Start(self.offset))?;
+            self.file = Some(f.take(self.size));
+        }
+        self.file.as_mut().map_or(Ok(0), |f| f.read(buf))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn edits_round_trip_through_commit() {
+        let dir = std::env::temp_dir().join(format!("gma-lite-session-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("addon.gma");
+        let big: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+        let mut b = Builder::new("old title", 7);
+        b.set_description("old description")
+            .file_from_bytes("lua/a.lua", b"print(1)".to_vec())
+            .file_from_bytes("lua/b.lua", big.clone())
+            .file_from_bytes("materials/c.vmt", b"vmt".to_vec());
+        b.write_to_path(&path).unwrap();
+
+        // Without edits, nothing is rewritten
+        let original = fs::read(&path).unwrap();
+        let session = GmaSession::open(&path).unwrap();
+        assert!(!session.is_modified());
+        assert_eq!(session.info().name, "old title");
+        session.commit().unwrap();
+        assert_eq!(fs::read(&path).unwrap(), original);
+
+        let mut session = GmaSession::open(&path).unwrap();
+        session.add("LUA\\A.lua", b"print(2)".to_vec());
+        session.add("lua/new.lua", b"new".to_vec());
+        assert!(session.remove("materials/c.vmt"));
+        assert!(!session.remove("materials/c.vmt"));
+        assert!(session.rename("lua/b.lua", "lua/big.lua").unwrap());
+        assert!(!session.rename("lua/missing.lua", "lua/x.lua").unwrap());
+        assert!(matches!(
+            session.rename("lua/big.lua", "LUA/NEW.lua"),
+            Err(GmaError::DuplicateEntry(name)) if name == "LUA/NEW.lua"
+        ));
+        session.set_title("new title");
+        session.set_description("new description");
+        session.set_author("someone");
+        assert!(session.is_modified());
+        assert_eq!(
+            session.names().collect::<Vec<_>>(),
+            ["lua/a.lua", "lua/big.lua", "lua/new.lua"]
+        );
+        // The untouched entry is still read from the old file when writing
+        assert!(matches!(
+            session.entries[1].content,
+            Content::Original { size: 100_000, .. }
+        ));
+        session.commit().unwrap();
+
+        let archive =
+            crate::read_with(fs::File::open(&path).unwrap(), &Default::default()).unwrap();
+        assert_eq!(archive.name(), "new title");
+        assert_eq!(archive.description(), "new description");
+        assert_eq!(archive.author(), "someone");
+        assert_eq!(archive.info().steam_id64, 7);
+        let files: Vec<_> = archive
+            .iter()
+            .map(|f| (f.name.as_str(), f.content.as_slice()))
+            .collect();
+        assert_eq!(
+            files,
+            [
+                ("lua/a.lua", &b"print(2)"[..]),
+                ("lua/big.lua", &big[..]),
+                ("lua/new.lua", b"new"),
+            ]
+        );
+        // The transaction left nothing else behind
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
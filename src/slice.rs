@@ -0,0 +1,322 @@
+//! Parsing straight from an in-memory archive, without `std::io`.
+//!
+//! This is what remains with `default-features = false` (`no_std` + `alloc`).
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::crc::Crc32;
+use crate::{
+    AddonInfo, EntryMeta, GMAFile, GmaError, HEADER, MIN_VERSION, ParseField, TrailerKind, VERSION,
+};
+
+/// An archive parsed in place from a byte slice; contents borrow from it.
+#[derive(Clone, Debug)]
+pub struct SliceArchive<'a> {
+    data: &'a [u8],
+    info: AddonInfo,
+    entries: Vec<EntryMeta>,
+    archive_crc: Option<u32>,
+}
+
+impl<'a> SliceArchive<'a> {
+    /// Parse the header and metadata table of the archive held in `data`.
+    ///
+    /// All declared content has to be present, followed by the trailing marker:
+    /// 0, or a CRC-32 of everything before it.
+    pub fn parse(data: &'a [u8]) -> Result<Self, GmaError> {
+        let mut c = Cursor { data, pos: 0 };
+
+        // Header
+        let hdr: [u8; 4] = c.field(ParseField::Magic, Cursor::array)?;
+        if &hdr != HEADER {
+            return Err(GmaError::InvalidHeader(hdr));
+        }
+
+        // Version (int8)
+        let v = c.field(ParseField::Version, Cursor::array::<1>)?[0] as i8;
+        if !(MIN_VERSION..=VERSION).contains(&v) {
+            return Err(GmaError::InvalidVersion(v));
+        }
+
+        let steam_id64 = i64::from_le_bytes(c.field(ParseField::SteamId, Cursor::array)?);
+        let timestamp = u64::from_le_bytes(c.field(ParseField::Timestamp, Cursor::array)?);
+        // Version 1 predates the required-content list
+        let mut required_content = Vec::new();
+        if v > 1 {
+            loop {
+                let item = c.field(ParseField::RequiredContent, Cursor::c_string)?;
+                if item.is_empty() {
+                    break;
+                }
+                required_content.push(item);
+            }
+        }
+        let name = c.field(ParseField::Name, Cursor::c_string)?;
+        let description = c.field(ParseField::Description, Cursor::c_string)?;
+        let author = c.field(ParseField::Author, Cursor::c_string)?;
+        let addon_version = i32::from_le_bytes(c.field(ParseField::AddonVersion, Cursor::array)?);
+        let info = AddonInfo {
+            version: v,
+            name,
+            description,
+            author,
+            steam_id64,
+            timestamp,
+            required_content,
+            addon_version,
+        };
+
+        let mut entries = Vec::new();
+        loop {
+            let index = entries.len();
+            let idx = u32::from_le_bytes(c.field(ParseField::FileIndex(index), Cursor::array)?);
+            if idx == 0 {
+                break;
+            }
+            let name_bytes = c
+                .field(ParseField::EntryName(index), Cursor::c_bytes)?
+                .to_vec();
+            let size = c.field(ParseField::EntrySize(index), |c| {
+                match i64::from_le_bytes(c.array()?) {
+                    size if size < 0 => Err(GmaError::SizeOutOfRange(size)),
+                    size => Ok(size as u64),
+                }
+            })?;
+            let crc = u32::from_le_bytes(c.field(ParseField::EntryCrc(index), Cursor::array)?);
+            entries.push(EntryMeta {
+                name: String::from_utf8_lossy(&name_bytes).into_owned(),
+                name_bytes,
+                size,
+                crc,
+                offset: 0,
+            });
+        }
+
+        // Contents follow the table back to back
+        let mut offset = c.pos as u64;
+        for meta in &mut entries {
+            meta.offset = offset;
+            let available = (data.len() as u64).saturating_sub(offset);
+            if meta.size > available {
+                return Err(meta.truncated(available));
+            }
+            offset += meta.size;
+        }
+
+        // Trailing u32 zero, or archive CRC
+        c.pos = offset as usize;
+        let trailing = u32::from_le_bytes(c.field(ParseField::TrailingMarker, Cursor::array)?);
+        let mut archive_crc = None;
+        if trailing != 0 {
+            let mut crc = Crc32::new();
+            crc.update(&data[..offset as usize]);
+            if crc.finish() != trailing {
+                let kind = if trailing.to_le_bytes() == *HEADER {
+                    TrailerKind::NextArchive
+                } else {
+                    TrailerKind::Garbage
+                };
+                return Err(GmaError::TrailingMarkerMismatch {
+                    found: trailing,
+                    kind,
+                });
+            }
+            archive_crc = Some(trailing);
+        }
+
+        Ok(Self {
+            data,
+            info,
+            entries,
+            archive_crc,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.info.name
+    }
+
+    pub fn description(&self) -> &str {
+        &self.info.description
+    }
+
+    pub fn author(&self) -> &str {
+        &self.info.author
+    }
+
+    pub fn info(&self) -> &AddonInfo {
+        &self.info
+    }
+
+    pub fn entries(&self) -> &[EntryMeta] {
+        &self.entries
+    }
+
+    /// The trailing whole-archive CRC-32, if there was one (it has been verified).
+    pub fn archive_crc(&self) -> Option<u32> {
+        self.archive_crc
+    }
+
+    /// Content of an entry of this archive (`None` for metadata from elsewhere
+    /// that points outside the slice).
+    pub fn content(&self, meta: &EntryMeta) -> Option<&'a [u8]> {
+        let start = usize::try_from(meta.offset).ok()?;
+        let len = usize::try_from(meta.size).ok()?;
+        self.data.get(start..start.checked_add(len)?)
+    }
+
+    /// Every entry as a [`GMAFile`] borrowing its content from the slice, so
+    /// nothing but the names is copied.
+    pub fn files(&self) -> impl Iterator<Item = GMAFile<&'a [u8]>> + '_ {
+        self.iter()
+            .map(|(e, content)| e.clone().with_content(content))
+    }
+
+    /// Every entry together with its content.
+    pub fn iter(&self) -> impl Iterator<Item = (&EntryMeta, &'a [u8])> {
+        let data = self.data;
+        self.entries.iter().map(move |e| {
+            // Bounds were checked by `parse`.
+            (e, &data[e.offset as usize..(e.offset + e.size) as usize])
+        })
+    }
+}
+
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    /// Read the field starting here, attaching where it was to any error.
+    fn field<T>(
+        &mut self,
+        field: ParseField,
+        read: impl FnOnce(&mut Self) -> Result<T, GmaError>,
+    ) -> Result<T, GmaError> {
+        let offset = self.pos as u64;
+        read(self).map_err(|e| e.at(offset, field))
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], GmaError> {
+        let end = self.pos.checked_add(n).ok_or(GmaError::UnexpectedEof)?;
+        let bytes = self
+            .data
+            .get(self.pos..end)
+            .ok_or(GmaError::UnexpectedEof)?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], GmaError> {
+        let mut out = [0u8; N];
+        out.copy_from_slice(self.take(N)?);
+        Ok(out)
+    }
+
+    fn c_bytes(&mut self) -> Result<&'a [u8], GmaError> {
+        let rest = &self.data[self.pos..];
+        let len = rest
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or(GmaError::MissingNullTerminator)?;
+        self.pos += len + 1;
+        Ok(&rest[..len])
+    }
+
+    fn c_string(&mut self) -> Result<String, GmaError> {
+        Ok(String::from_utf8_lossy(self.c_bytes()?).into_owned())
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::{Builder, read_with};
+
+    fn archive(archive_crc: bool) -> Vec<u8> {
+        let mut b = Builder::new("slice", 76561197960287930);
+        b.set_description("d")
+            .set_author("a")
+            .set_archive_crc(archive_crc)
+            .file_from_bytes("lua/a.lua", b"print(1)".to_vec())
+            .file_from_bytes("lua/empty.lua", Vec::new())
+            .file_from_bytes("materials/x.vtf", vec![7; 5000]);
+        b.write_to_vec().unwrap()
+    }
+
+    /// `SliceArchive::parse` and `read_with` agree on `data`, down to the error.
+    fn check_same(data: &[u8]) {
+        let slice = SliceArchive::parse(data);
+        let read = read_with(data, &Default::default());
+        match (slice, read) {
+            (Ok(slice), Ok(read)) => {
+                assert_eq!(slice.info(), read.info());
+                assert_eq!(slice.archive_crc(), read.archive_crc());
+                let files: Vec<GMAFile> = slice.files().map(GMAFile::into_owned).collect();
+                assert_eq!(files, read.entries());
+            }
+            // The reader reports running out of input as an io error
+            (
+                Err(GmaError::Parse { offset, field, .. }),
+                Err(GmaError::Parse {
+                    offset: o,
+                    field: f,
+                    ..
+                }),
+            ) => assert_eq!((offset, field), (o, f)),
+            (Err(a), Err(b)) => assert_eq!(a.to_string(), b.to_string()),
+            (a, b) => panic!("slice: {a:?}\nread: {b:?}"),
+        }
+    }
+
+    #[test]
+    fn matches_the_streaming_reader() {
+        for crc in [false, true] {
+            let data = archive(crc);
+            let parsed = SliceArchive::parse(&data).unwrap();
+            assert_eq!(parsed.entries().len(), 3);
+            assert_eq!(parsed.archive_crc().is_some(), crc);
+            let x = &parsed.entries()[2];
+            assert_eq!(parsed.content(x), Some(&[7u8; 5000][..]));
+            check_same(&data);
+        }
+    }
+
+    #[test]
+    fn matches_the_streaming_reader_on_bad_input() {
+        let data = archive(true);
+        let len = data.len();
+        // Cut inside the header, the table, an entry's content and the trailer
+        for cut in [3, 23, 60, len - 5000, len - 10, len - 2] {
+            let err = SliceArchive::parse(&data[..cut]).unwrap_err();
+            check_same(&data[..cut]);
+            if cut == len - 10 {
+                assert!(matches!(err, GmaError::TruncatedEntry { .. }), "{err}");
+            }
+        }
+
+        let mut wrong = data.clone();
+        wrong[len - 1] ^= 0x80;
+        assert!(matches!(
+            SliceArchive::parse(&wrong),
+            Err(GmaError::TrailingMarkerMismatch {
+                kind: TrailerKind::Garbage,
+                ..
+            })
+        ));
+        check_same(&wrong);
+
+        let mut next = data;
+        next[len - 4..].copy_from_slice(HEADER);
+        assert!(matches!(
+            SliceArchive::parse(&next),
+            Err(GmaError::TrailingMarkerMismatch {
+                kind: TrailerKind::NextArchive,
+                ..
+            })
+        ));
+        check_same(&next);
+    }
+}
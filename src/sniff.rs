@@ -0,0 +1,71 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::{HEADER, MIN_VERSION, VERSION};
+
+/// What the first bytes of a file look like. See [`sniff`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// "GMAD" magic followed by this version byte.
+    Gma {
+        version: i8,
+    },
+    /// An LZMA-alone stream, the way the Workshop serves addons; it has to be
+    /// decompressed before it can be read.
+    Lzma {
+        uncompressed_size: Option<u64>,
+    },
+    Unknown,
+}
+
+impl Format {
+    /// A version this crate can read.
+    #[inline]
+    pub fn is_gma(&self) -> bool {
+        matches!(*self, Format::Gma { version } if (MIN_VERSION..=VERSION).contains(&version))
+    }
+}
+
+/// Classify a stream from its first 13 bytes, without parsing further.
+pub fn sniff<R: Read>(reader: R) -> io::Result<Format> {
+    let mut head = Vec::with_capacity(13);
+    reader.take(13).read_to_end(&mut head)?;
+    Ok(classify(&head))
+}
+
+/// [`sniff`] the file at `path`.
+pub fn sniff_path(path: impl AsRef<Path>) -> io::Result<Format> {
+    sniff(File::open(path)?)
+}
+
+/// Whether `reader` starts like an archive this crate can read.
+///
+/// Read errors count as "no".
+pub fn is_gma<R: Read>(reader: R) -> bool {
+    sniff(reader).is_ok_and(|f| f.is_gma())
+}
+
+fn classify(head: &[u8]) -> Format {
+    if head.len() >= 5 && head[..4] == *HEADER {
+        return Format::Gma {
+            version: head[4] as i8,
+        };
+    }
+    // LZMA-alone: properties byte, dictionary size (u32), uncompressed size (u64,
+    // all ones when unknown).
+    if head.len() == 13 && head[0] < 9 * 5 * 5 {
+        let dict = u32::from_le_bytes(head[1..5].try_into().expect("4 bytes"));
+        let size = u64::from_le_bytes(head[5..13].try_into().expect("8 bytes"));
+        // Encoders only pick 2^n or 2^n + 2^(n-1) dictionaries.
+        let high = 1u32 << dict.checked_ilog2().unwrap_or(0);
+        let dict_ok =
+            (1 << 12..=1 << 30).contains(&dict) && (dict == high || dict == high + high / 2);
+        if dict_ok && size != 0 && (size == u64::MAX || size < 1 << 40) {
+            return Format::Lzma {
+                uncompressed_size: (size != u64::MAX).then_some(size),
+            };
+        }
+    }
+    Format::Unknown
+}
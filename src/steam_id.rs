@@ -0,0 +1,183 @@
+use alloc::format;
+use alloc::string::String;
+use core::fmt;
+use core::str::FromStr;
+
+use crate::GmaError;
+
+/// A 64-bit Steam ID, as stored in the archive header.
+///
+/// Parses the raw number, `STEAM_X:Y:Z`, `[U:1:W]` and
+/// `https://steamcommunity.com/profiles/<id64>` links; vanity `/id/` links
+/// need the Steam API and aren't accepted. Displays as the raw number.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct SteamId(pub u64);
+
+/// ID64 of individual account 0 in the public universe.
+const INDIVIDUAL_BASE: u64 = 0x0110_0001_0000_0000;
+
+impl SteamId {
+    /// The individual account with this 32-bit account id (the `W` of `[U:1:W]`).
+    pub fn from_account_id(account_id: u32) -> Self {
+        Self(INDIVIDUAL_BASE + account_id as u64)
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        if let Some(rest) = strip_prefix_ignore_case(s, "STEAM_") {
+            let mut parts = rest.split(':');
+            let universe: u8 = parts.next()?.parse().ok()?;
+            let y: u32 = parts.next()?.parse().ok()?;
+            let z: u32 = parts.next()?.parse().ok()?;
+            if parts.next().is_some() || universe > 5 || y > 1 {
+                return None;
+            }
+            return Some(Self::from_account_id(z.checked_mul(2)?.checked_add(y)?));
+        }
+        let bracketed = s.strip_prefix('[').and_then(|s| s.strip_suffix(']'));
+        if let Some(rest) = strip_prefix_ignore_case(bracketed.unwrap_or(s), "U:1:") {
+            return rest.parse().ok().map(Self::from_account_id);
+        }
+        if let Some(id) = profile_id(s) {
+            return parse_digits(id).map(Self);
+        }
+        parse_digits(s).map(Self)
+    }
+
+    /// The 32-bit account id; only meaningful for individual accounts.
+    pub fn account_id(self) -> u32 {
+        self.0 as u32
+    }
+
+    /// `STEAM_0:Y:Z`, the form Garry's Mod shows.
+    pub fn steam2(self) -> String {
+        let id = self.account_id();
+        format!("STEAM_0:{}:{}", id & 1, id >> 1)
+    }
+
+    /// `[U:1:W]`.
+    pub fn steam3(self) -> String {
+        format!("[U:1:{}]", self.account_id())
+    }
+
+    pub fn profile_url(self) -> String {
+        format!("https://steamcommunity.com/profiles/{}", self.0)
+    }
+}
+
+impl FromStr for SteamId {
+    type Err = GmaError;
+
+    fn from_str(s: &str) -> Result<Self, GmaError> {
+        Self::parse(s).ok_or_else(|| GmaError::InvalidSteamId(s.into()))
+    }
+}
+
+impl fmt::Display for SteamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+fn strip_prefix_ignore_case<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = s.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix)
+        .then(|| &s[prefix.len()..])
+}
+
+/// The id part of a `steamcommunity.com/profiles/<id>` link, with or without
+/// scheme, `www.` and trailing `/`.
+fn profile_id(s: &str) -> Option<&str> {
+    let s = s
+        .strip_prefix("https://")
+        .or_else(|| s.strip_prefix("http://"))
+        .unwrap_or(s);
+    let s = s.strip_prefix("www.").unwrap_or(s);
+    let id = s.strip_prefix("steamcommunity.com/profiles/")?;
+    Some(id.strip_suffix('/').unwrap_or(id))
+}
+
+fn parse_digits(s: &str) -> Option<u64> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GABEN: SteamId = SteamId(76561197960287930);
+
+    #[test]
+    fn parses_every_form() {
+        for s in [
+            "76561197960287930",
+            " 76561197960287930\n",
+            "STEAM_0:0:11101",
+            "STEAM_1:0:11101",
+            "steam_0:0:11101",
+            "[U:1:22202]",
+            "U:1:22202",
+            "u:1:22202",
+            "https://steamcommunity.com/profiles/76561197960287930",
+            "http://steamcommunity.com/profiles/76561197960287930/",
+            "https://www.steamcommunity.com/profiles/76561197960287930",
+            "steamcommunity.com/profiles/76561197960287930/",
+            "www.steamcommunity.com/profiles/76561197960287930",
+        ] {
+            assert_eq!(SteamId::parse(s), Some(GABEN), "{s:?}");
+        }
+        assert_eq!(
+            SteamId::parse("STEAM_0:1:2147483647").unwrap().account_id(),
+            u32::MAX
+        );
+    }
+
+    #[test]
+    fn rejects_other_input() {
+        for s in [
+            "",
+            "https://steamcommunity.com/id/gabelogannewell",
+            "steamcommunity.com/id/76561197960287930",
+            "https://steamcommunity.com/profiles/",
+            "https://steamcommunity.com/profiles/7656119796028793x",
+            "STEAM_0:2:11101",
+            "STEAM_6:1:11101",
+            "STEAM_0:1",
+            "STEAM_0:1:11101:1",
+            // Account ids past u32
+            "STEAM_0:1:2147483648",
+            "[U:1:4294967296]",
+            "[U:2:22202]",
+            // Past u64
+            "18446744073709551616",
+            "+76561197960287930",
+            "-1",
+        ] {
+            assert_eq!(SteamId::parse(s), None, "{s:?}");
+        }
+        assert!(matches!(
+            "nope".parse::<SteamId>(),
+            Err(GmaError::InvalidSteamId(s)) if s == "nope"
+        ));
+    }
+
+    #[test]
+    fn formats_round_trip() {
+        assert_eq!(GABEN.steam2(), "STEAM_0:0:11101");
+        assert_eq!(GABEN.steam3(), "[U:1:22202]");
+        assert_eq!(
+            GABEN.profile_url(),
+            "https://steamcommunity.com/profiles/76561197960287930"
+        );
+        assert_eq!(format!("{GABEN}"), "76561197960287930");
+        for id in [0, 1, 22203, u32::MAX].map(SteamId::from_account_id) {
+            assert_eq!(SteamId::parse(&id.steam2()), Some(id));
+            assert_eq!(SteamId::parse(&id.steam3()), Some(id));
+            assert_eq!(SteamId::parse(&id.profile_url()), Some(id));
+            assert_eq!(format!("{id}").parse::<SteamId>().unwrap(), id);
+        }
+    }
+}
@@ -0,0 +1,358 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::{Builder, GmaError};
+
+const JOURNAL: &str = ".gma-transaction";
+const DONE: &str = ".gma-transaction.done";
+
+/// Stages new or updated archives next to the live ones in a directory, then
+/// swaps them all in at once.
+///
+/// Staged files are written under temporary names and synced first. On commit,
+/// a journal is written, live files are moved aside, the staged ones renamed
+/// into place and obsolete ones moved aside too; only then is the update marked
+/// done and the old copies deleted. A failure rolls everything back, and after
+/// a crash [`recover`](Self::recover) either rolls back or finishes, so the
+/// directory never keeps a mix of old and new packs.
+///
+/// Only one transaction may run in a directory at a time.
+#[derive(Debug)]
+pub struct ArchiveTransaction {
+    dir: PathBuf,
+    staged: Vec<String>,
+    removed: Vec<String>,
+}
+
+impl ArchiveTransaction {
+    /// Start a transaction in `dir`, first recovering from any interrupted one.
+    pub fn new(dir: impl AsRef<Path>) -> Result<Self, GmaError> {
+        let dir = dir.as_ref().to_path_buf();
+        Self::recover(&dir)?;
+        Ok(Self {
+            dir,
+            staged: Vec::new(),
+            removed: Vec::new(),
+        })
+    }
+
+    /// Write `builder` as the new version of `file_name`.
+    pub fn stage(&mut self, file_name: &str, builder: &Builder) -> Result<(), GmaError> {
+        self.stage_with(file_name, |f| builder.write_to(f))
+    }
+
+    /// Copy an already-built archive in as the new version of `file_name`.
+    pub fn stage_from<R: Read>(&mut self, file_name: &str, mut reader: R) -> Result<(), GmaError> {
+        self.stage_with(file_name, |f| {
+            io::copy(&mut reader, f)?;
+            Ok(())
+        })
+    }
+
+    /// Delete `file_name` as part of the commit.
+    pub fn remove(&mut self, file_name: &str) -> Result<(), GmaError> {
+        check_name(file_name)?;
+        self.removed.push(file_name.to_owned());
+        Ok(())
+    }
+
+    fn stage_with<F>(&mut self, file_name: &str, write: F) -> Result<(), GmaError>
+    where
+        F: FnOnce(&mut BufWriter<&File>) -> Result<(), GmaError>,
+    {
+        check_name(file_name)?;
+        let path = self.dir.join(staged_name(file_name));
+        let file = File::create(&path)?;
+        let res = (|| {
+            let mut w = BufWriter::new(&file);
+            write(&mut w)?;
+            w.flush()?;
+            drop(w);
+            file.sync_all()?;
+            Ok(())
+        })();
+        if res.is_err() {
+            let _ = fs::remove_file(&path);
+        } else if !self.staged.iter().any(|s| s == file_name) {
+            self.staged.push(file_name.to_owned());
+        }
+        res
+    }
+
+    /// Swap every staged archive in and delete the removed ones, or change nothing.
+    pub fn commit(mut self) -> Result<(), GmaError> {
+        let res = self.apply();
+        if res.is_err() {
+            // Nothing is marked done yet, so this puts the old files back.
+            let _ = Self::recover(&self.dir);
+        }
+        self.staged.clear();
+        res
+    }
+
+    fn apply(&self) -> Result<(), GmaError> {
+        self.write_journal()?;
+        self.swap()?;
+        self.mark_done()?;
+        Self::recover(&self.dir)
+    }
+
+    /// Record what is about to change, so [`recover`](Self::recover) can undo it.
+    fn write_journal(&self) -> io::Result<()> {
+        let dir = &self.dir;
+        let mut journal = String::new();
+        for name in &self.staged {
+            let op = if dir.join(name).exists() {
+                "replace"
+            } else {
+                "new"
+            };
+            journal.push_str(&format!("{op} {name}\n"));
+        }
+        for name in &self.removed {
+            journal.push_str(&format!("remove {name}\n"));
+        }
+        write_synced(&dir.join(JOURNAL), journal.as_bytes())?;
+        sync_dir(dir)
+    }
+
+    /// Move live files aside and the staged ones into place.
+    fn swap(&self) -> io::Result<()> {
+        let dir = &self.dir;
+        for name in &self.staged {
+            let live = dir.join(name);
+            if live.exists() {
+                fs::rename(&live, dir.join(old_name(name)))?;
+            }
+            fs::rename(dir.join(staged_name(name)), &live)?;
+        }
+        for name in &self.removed {
+            let live = dir.join(name);
+            if live.exists() {
+                fs::rename(&live, dir.join(old_name(name)))?;
+            }
+        }
+        sync_dir(dir)
+    }
+
+    /// The commit point: from here on, recovery finishes instead of undoing.
+    fn mark_done(&self) -> io::Result<()> {
+        write_synced(&self.dir.join(DONE), b"")?;
+        sync_dir(&self.dir)
+    }
+
+    /// Finish or undo a transaction interrupted in `dir`; a no-op if there is none.
+    pub fn recover(dir: impl AsRef<Path>) -> Result<(), GmaError> {
+        let dir = dir.as_ref();
+        let journal = match File::open(dir.join(JOURNAL)) {
+            Ok(f) => f,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        let done = dir.join(DONE).exists();
+        for line in BufReader::new(journal).lines() {
+            let line = line?;
+            let Some((op, name)) = line.split_once(' ') else {
+                continue;
+            };
+            let (live, old, staged) = (
+                dir.join(name),
+                dir.join(old_name(name)),
+                dir.join(staged_name(name)),
+            );
+            if done {
+                remove_if_exists(&old)?;
+                continue;
+            }
+            match op {
+                // Not placed yet if the staged file is still there.
+                "new" if !staged.exists() => remove_if_exists(&live)?,
+                "replace" | "remove" if old.exists() => fs::rename(&old, &live)?,
+                _ => {}
+            }
+            remove_if_exists(&staged)?;
+        }
+        sync_dir(dir)?;
+        remove_if_exists(&dir.join(DONE))?;
+        remove_if_exists(&dir.join(JOURNAL))?;
+        sync_dir(dir)?;
+        Ok(())
+    }
+}
+
+impl Drop for ArchiveTransaction {
+    /// Discard staged files that were never committed.
+    fn drop(&mut self) {
+        for name in &self.staged {
+            let _ = fs::remove_file(self.dir.join(staged_name(name)));
+        }
+    }
+}
+
+fn check_name(name: &str) -> Result<(), GmaError> {
+    let bad =
+        name.is_empty() || name.starts_with('.') || name.contains(['/', '\\', '\n', '\r', ':']);
+    if bad {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("not a plain file name: {name:?}"),
+        )
+        .into());
+    }
+    Ok(())
+}
+
+fn staged_name(name: &str) -> String {
+    format!(".{name}.staged")
+}
+
+fn old_name(name: &str) -> String {
+    format!(".{name}.old")
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+fn write_synced(path: &Path, data: &[u8]) -> io::Result<()> {
+    let mut f = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)?;
+    f.write_all(data)?;
+    f.sync_all()
+}
+
+/// Make renames in `dir` durable. Directories can't be opened for syncing on
+/// every platform, so this is best effort there.
+pub(crate) fn sync_dir(dir: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    File::open(dir)?.sync_all()?;
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Files = [(&'static str, &'static str); 3];
+
+    const BEFORE: Files = [("a.gma", "old a"), ("b.gma", "old b"), ("c.gma", "old c")];
+    const AFTER: Files = [("a.gma", "new a"), ("b.gma", "old b"), ("d.gma", "new d")];
+
+    /// A directory holding the [`BEFORE`] files.
+    fn setup(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("gma-lite-tx-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (name, content) in BEFORE {
+            fs::write(dir.join(name), content).unwrap();
+        }
+        dir
+    }
+
+    /// Replace `a.gma`, add `d.gma` and remove `c.gma`, giving [`AFTER`].
+    fn staged(dir: &Path) -> ArchiveTransaction {
+        let mut tx = ArchiveTransaction::new(dir).unwrap();
+        tx.stage_from("a.gma", &b"new a"[..]).unwrap();
+        tx.stage_from("d.gma", &b"new d"[..]).unwrap();
+        tx.remove("c.gma").unwrap();
+        tx
+    }
+
+    /// Every file in `dir`, hidden ones included, with its content.
+    fn contents(dir: &Path) -> Vec<(String, String)> {
+        let mut files: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| {
+                let path = e.unwrap().path();
+                let name = path.file_name().unwrap().to_str().unwrap().to_owned();
+                (name, fs::read_to_string(&path).unwrap())
+            })
+            .collect();
+        files.sort();
+        files
+    }
+
+    fn expect(dir: &Path, files: Files) {
+        let files: Vec<_> = files
+            .iter()
+            .map(|(n, c)| (n.to_string(), c.to_string()))
+            .collect();
+        assert_eq!(contents(dir), files);
+    }
+
+    #[test]
+    fn commit_swaps_everything_in() {
+        let dir = setup("commit");
+        staged(&dir).commit().unwrap();
+        expect(&dir, AFTER);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn dropping_discards_staged_files() {
+        let dir = setup("drop");
+        drop(staged(&dir));
+        expect(&dir, BEFORE);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn failed_rename_rolls_back() {
+        let dir = setup("rollback");
+        let tx = staged(&dir);
+        // a.gma is swapped in before d.gma's rename fails
+        fs::remove_file(dir.join(staged_name("d.gma"))).unwrap();
+        assert!(tx.commit().is_err());
+        expect(&dir, BEFORE);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Run `apply` up to `phase`: 1 writes the journal, 2 stops half-way
+    /// through the swap, 3 finishes it and 4 marks the transaction done.
+    fn run_until(tx: &ArchiveTransaction, phase: usize) {
+        tx.write_journal().unwrap();
+        if phase == 2 {
+            fs::rename(tx.dir.join("a.gma"), tx.dir.join(old_name("a.gma"))).unwrap();
+            fs::rename(tx.dir.join(staged_name("a.gma")), tx.dir.join("a.gma")).unwrap();
+        }
+        if phase >= 3 {
+            tx.swap().unwrap();
+        }
+        if phase >= 4 {
+            tx.mark_done().unwrap();
+        }
+    }
+
+    #[test]
+    fn recovery_after_each_phase_leaves_one_set() {
+        // Only a crash after the commit point keeps the new files
+        for (phase, expected) in [
+            (0, BEFORE),
+            (1, BEFORE),
+            (2, BEFORE),
+            (3, BEFORE),
+            (4, AFTER),
+        ] {
+            let dir = setup(&format!("phase{phase}"));
+            let tx = staged(&dir);
+            run_until(&tx, phase);
+            // A crash: nothing gets to clean up
+            std::mem::forget(tx);
+            ArchiveTransaction::recover(&dir).unwrap();
+            expect(&dir, expected);
+            // Recovering again, or starting over, changes nothing
+            ArchiveTransaction::new(&dir).unwrap();
+            expect(&dir, expected);
+            fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+}
@@ -0,0 +1,281 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::{GMAFile, is_safe_path, is_whitelisted};
+
+/// Entries added with byte-identical content, as reported by
+/// [`Builder::validate`](crate::Builder::validate).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DuplicateContent {
+    /// The entries' names, in insertion order.
+    pub names: Vec<String>,
+    /// Size of the shared content.
+    pub size: u64,
+}
+
+impl DuplicateContent {
+    /// Bytes the archive would save by storing the content once.
+    pub fn wasted(&self) -> u64 {
+        self.size * (self.names.len() as u64 - 1)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ValidationIssue {
+    /// The archive has no entries.
+    Empty,
+    /// The entry's name is empty.
+    EmptyName,
+    /// Another entry has the same path, ignoring case and separators; only one
+    /// would be visible once mounted.
+    DuplicateName,
+    /// Absolute, `..` or drive-letter path (see [`is_safe_path`]).
+    UnsafePath,
+    /// Not on [`GMAD_WHITELIST`](crate::GMAD_WHITELIST).
+    NotWhitelisted,
+    /// The entry alone is over the [`set_max_size`](crate::Builder::set_max_size)
+    /// limit.
+    EntryTooLarge { size: u64, limit: u64 },
+    /// The archive is over the `set_max_size` limit.
+    TooLarge { size: u64, limit: u64 },
+    /// A header string (`"name"`, `"description"`, `"author"` or
+    /// `"required content"`) or the entry's name contains a NUL byte, which
+    /// can't be written.
+    NulInString { field: &'static str },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationProblem {
+    /// The offending entry, or `None` for the header or archive as a whole.
+    pub name: Option<String>,
+    pub issue: ValidationIssue,
+}
+
+/// Everything [`Builder::validate`](crate::Builder::validate) found.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct ValidationReport {
+    problems: Vec<ValidationProblem>,
+    duplicate_content: Vec<DuplicateContent>,
+}
+
+impl ValidationReport {
+    /// `true` if there are no problems. Duplicate content doesn't count.
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    /// Every problem found: header strings first, then entries in insertion
+    /// order, then the archive as a whole.
+    pub fn problems(&self) -> &[ValidationProblem] {
+        &self.problems
+    }
+
+    /// Groups of non-empty entries with byte-identical content, which mount
+    /// fine but take space.
+    pub fn duplicate_content(&self) -> &[DuplicateContent] {
+        &self.duplicate_content
+    }
+}
+
+/// Header strings by field, the entries, the size limit and predicted size.
+pub(crate) fn check<'a>(
+    strings: impl Iterator<Item = (&'static str, &'a str)>,
+    entries: &[GMAFile],
+    limit: Option<u64>,
+    size: u64,
+    duplicate_content: Vec<DuplicateContent>,
+) -> ValidationReport {
+    let mut problems = Vec::new();
+    for (field, s) in strings {
+        if s.contains('\0') {
+            problems.push(ValidationProblem {
+                name: None,
+                issue: ValidationIssue::NulInString { field },
+            });
+        }
+    }
+
+    let mut seen = HashSet::with_capacity(entries.len());
+    for e in entries {
+        let mut report = |issue| {
+            problems.push(ValidationProblem {
+                name: Some(e.name.clone()),
+                issue,
+            })
+        };
+        if e.name.is_empty() {
+            report(ValidationIssue::EmptyName);
+            continue;
+        }
+        if e.name.contains('\0') {
+            report(ValidationIssue::NulInString {
+                field: "entry name",
+            });
+        }
+        let normalized = e.name.replace('\\', "/").to_ascii_lowercase();
+        if !seen.insert(normalized.clone()) {
+            report(ValidationIssue::DuplicateName);
+        }
+        if !is_safe_path(&e.name) {
+            report(ValidationIssue::UnsafePath);
+        } else if !is_whitelisted(&normalized) {
+            report(ValidationIssue::NotWhitelisted);
+        }
+        if let Some(limit) = limit
+            && e.size > limit
+        {
+            report(ValidationIssue::EntryTooLarge {
+                size: e.size,
+                limit,
+            });
+        }
+    }
+
+    let mut report = |issue| problems.push(ValidationProblem { name: None, issue });
+    if entries.is_empty() {
+        report(ValidationIssue::Empty);
+    }
+    if let Some(limit) = limit
+        && size > limit
+    {
+        report(ValidationIssue::TooLarge { size, limit });
+    }
+    ValidationReport {
+        problems,
+        duplicate_content,
+    }
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationIssue::Empty => f.write_str("no entries"),
+            ValidationIssue::EmptyName => f.write_str("empty name"),
+            ValidationIssue::DuplicateName => f.write_str("duplicate path"),
+            ValidationIssue::UnsafePath => f.write_str("unsafe path"),
+            ValidationIssue::NotWhitelisted => f.write_str("not on the whitelist"),
+            ValidationIssue::EntryTooLarge { size, limit } => {
+                write!(f, "{size} byte entry, over the {limit} byte limit")
+            }
+            ValidationIssue::TooLarge { size, limit } => {
+                write!(f, "{size} bytes, over the {limit} byte limit")
+            }
+            ValidationIssue::NulInString { field } => write!(f, "NUL byte in the {field}"),
+        }
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    /// One line per problem, then one per group of duplicate content.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        let mut line = |f: &mut fmt::Formatter<'_>| {
+            let sep = if first { "" } else { "\n" };
+            first = false;
+            f.write_str(sep)
+        };
+        for p in &self.problems {
+            line(f)?;
+            match &p.name {
+                Some(name) => write!(f, "{name}: {}", p.issue)?,
+                None => write!(f, "{}", p.issue)?,
+            }
+        }
+        for d in &self.duplicate_content {
+            line(f)?;
+            write!(
+                f,
+                "same content ({} bytes wasted): {}",
+                d.wasted(),
+                d.names.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Builder;
+
+    #[test]
+    fn clean_builder_is_ok() {
+        let mut b = Builder::new("t", 1);
+        b.file_from_bytes("lua/a.lua", b"1".to_vec())
+            .file_from_bytes("lua/b.lua", b"2".to_vec());
+        let report = b.validate();
+        assert!(report.is_ok(), "{report}");
+        assert!(report.duplicate_content().is_empty());
+        assert_eq!(report.to_string(), "");
+    }
+
+    #[test]
+    fn every_problem_is_collected() {
+        use ValidationIssue::*;
+        let mut b = Builder::new("bad\0title", 1);
+        b.set_max_size(150)
+            .file_from_bytes("", b"x".to_vec())
+            .file_from_bytes("lua/a.lua", b"same".to_vec())
+            .file_from_bytes("LUA\\A.lua", b"other".to_vec())
+            .file_from_bytes("../x.lua", b"1".to_vec())
+            .file_from_bytes("lua/a.exe", b"2".to_vec())
+            .file_from_bytes("lua/n\0.lua", b"same".to_vec())
+            .file_from_bytes("materials/big.vtf", vec![0; 160]);
+        let report = b.validate();
+        assert!(!report.is_ok());
+        let problems: Vec<_> = report
+            .problems()
+            .iter()
+            .map(|p| (p.name.as_deref(), &p.issue))
+            .collect();
+        let size = b.predicted_len();
+        assert_eq!(
+            problems,
+            [
+                (None, &NulInString { field: "name" }),
+                (Some(""), &EmptyName),
+                (Some("LUA\\A.lua"), &DuplicateName),
+                (Some("../x.lua"), &UnsafePath),
+                (Some("lua/a.exe"), &NotWhitelisted),
+                (
+                    Some("lua/n\0.lua"),
+                    &NulInString {
+                        field: "entry name"
+                    }
+                ),
+                (
+                    Some("materials/big.vtf"),
+                    &EntryTooLarge {
+                        size: 160,
+                        limit: 150
+                    }
+                ),
+                (None, &TooLarge { size, limit: 150 }),
+            ]
+        );
+        assert_eq!(
+            report.duplicate_content(),
+            [DuplicateContent {
+                names: vec!["lua/a.lua".into(), "lua/n\0.lua".into()],
+                size: 4,
+            }]
+        );
+        assert_eq!(report.duplicate_content()[0].wasted(), 4);
+        let text = report.to_string();
+        assert_eq!(text.lines().count(), 9, "{text}");
+        assert!(
+            text.starts_with("NUL byte in the name\n: empty name\n"),
+            "{text}"
+        );
+        assert!(text.ends_with("same content (4 bytes wasted): lua/a.lua, lua/n\0.lua"));
+    }
+
+    #[test]
+    fn empty_builder_is_reported() {
+        let report = Builder::new("t", 1).validate();
+        let issues: Vec<_> = report.problems().iter().map(|p| &p.issue).collect();
+        assert_eq!(issues, [&ValidationIssue::Empty]);
+        assert_eq!(report.to_string(), "no entries");
+    }
+}
@@ -0,0 +1,76 @@
+use crate::wildcard;
+
+/// Paths gmad (and the game, when mounting) accepts in an addon. Anything else
+/// is refused. Patterns use gmad's wildcard syntax, where `*` crosses `/`.
+pub const GMAD_WHITELIST: &[&str] = &[
+    "lua/*.lua",
+    "scenes/*.vcd",
+    "particles/*.pcf",
+    "resource/fonts/*.ttf",
+    "scripts/vehicles/*.txt",
+    "resource/localization/*/*.properties",
+    "maps/*.bsp",
+    "maps/*.lmp",
+    "maps/*.nav",
+    "maps/*.ain",
+    "maps/thumb/*.png",
+    "sound/*.wav",
+    "sound/*.mp3",
+    "sound/*.ogg",
+    "materials/*.vmt",
+    "materials/*.vtf",
+    "materials/*.png",
+    "materials/*.jpg",
+    "materials/*.jpeg",
+    "materials/colorcorrection/*.raw",
+    "models/*.mdl",
+    "models/*.phy",
+    "models/*.ani",
+    "models/*.vvd",
+    "models/*.vtx",
+    "gamemodes/*/*.txt",
+    "gamemodes/*/*.fgd",
+    "gamemodes/*/logo.png",
+    "gamemodes/*/icon24.png",
+    "gamemodes/*/gamemode/*.lua",
+    "gamemodes/*/entities/effects/*.lua",
+    "gamemodes/*/entities/weapons/*.lua",
+    "gamemodes/*/entities/entities/*.lua",
+    "gamemodes/*/backgrounds/*.png",
+    "gamemodes/*/backgrounds/*.jpg",
+    "gamemodes/*/backgrounds/*.jpeg",
+    "gamemodes/*/content/models/*.mdl",
+    "gamemodes/*/content/models/*.phy",
+    "gamemodes/*/content/models/*.ani",
+    "gamemodes/*/content/models/*.vvd",
+    "gamemodes/*/content/models/*.vtx",
+    "gamemodes/*/content/materials/*.vmt",
+    "gamemodes/*/content/materials/*.vtf",
+    "gamemodes/*/content/materials/*.png",
+    "gamemodes/*/content/materials/*.jpg",
+    "gamemodes/*/content/materials/*.jpeg",
+    "gamemodes/*/content/materials/colorcorrection/*.raw",
+    "gamemodes/*/content/scenes/*.vcd",
+    "gamemodes/*/content/particles/*.pcf",
+    "gamemodes/*/content/resource/fonts/*.ttf",
+    "gamemodes/*/content/scripts/vehicles/*.txt",
+    "gamemodes/*/content/resource/localization/*/*.properties",
+    "gamemodes/*/content/maps/*.bsp",
+    "gamemodes/*/content/maps/*.nav",
+    "gamemodes/*/content/maps/*.ain",
+    "gamemodes/*/content/maps/thumb/*.png",
+    "gamemodes/*/content/sound/*.wav",
+    "gamemodes/*/content/sound/*.mp3",
+    "gamemodes/*/content/sound/*.ogg",
+    "data_static/*.txt",
+    "data_static/*.dat",
+    "data_static/*.json",
+    "data_static/*.xml",
+    "data_static/*.csv",
+];
+
+/// Whether `path` matches [`GMAD_WHITELIST`]. Like gmad, this expects the
+/// lowercase, `/`-separated form; other spellings don't match.
+pub fn is_whitelisted(path: &str) -> bool {
+    GMAD_WHITELIST.iter().any(|p| wildcard::matches(p, path))
+}
@@ -0,0 +1,124 @@
+//! Minimal gmad-style wildcards: `*` matches any run of characters (including
+//! `/`), `?` matches exactly one.
+
+/// Whether `text` matches `pattern` in full.
+pub(crate) fn matches(pattern: &str, text: &str) -> bool {
+    spans(pattern, text).is_some()
+}
+
+/// Match `text` against `pattern`, returning what each `*` / `?` matched, in order.
+///
+/// Each `*` matches as little as possible.
+pub(crate) fn captures<'t>(pattern: &str, text: &'t str) -> Option<Vec<&'t str>> {
+    let spans = spans(pattern, text)?;
+    Some(
+        spans
+            .into_iter()
+            .map(|(from, to)| &text[from..to])
+            .collect(),
+    )
+}
+
+/// Byte ranges of `text` matched by each `*` / `?` of `pattern`.
+///
+/// The usual two-pointer matcher: on a mismatch only the most recent `*` takes
+/// one more character, so matching is O(pattern × text) rather than
+/// exponential. Extending an earlier `*` never helps once a later one has
+/// matched, which also keeps every `*` as short as possible.
+fn spans(pattern: &str, text: &str) -> Option<Vec<(usize, usize)>> {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<(usize, char)> = text.char_indices().collect();
+    let byte = |i: usize| t.get(i).map_or(text.len(), |&(b, _)| b);
+
+    // Ranges in chars of `text`, converted to bytes at the end
+    let mut spans = Vec::new();
+    // The last `*`: its position in the pattern, where its capture starts and
+    // ends, and how many captures came before it
+    let mut star: Option<(usize, usize, usize, usize)> = None;
+    let (mut pi, mut ti) = (0, 0);
+    while ti < t.len() {
+        match p.get(pi) {
+            Some('*') => {
+                star = Some((pi, ti, ti, spans.len()));
+                spans.push((ti, ti));
+                pi += 1;
+            }
+            Some('?') => {
+                spans.push((ti, ti + 1));
+                pi += 1;
+                ti += 1;
+            }
+            Some(&c) if c == t[ti].1 => {
+                pi += 1;
+                ti += 1;
+            }
+            _ => {
+                let (sp, start, end, before) = star?;
+                star = Some((sp, start, end + 1, before));
+                spans.truncate(before);
+                spans.push((start, end + 1));
+                pi = sp + 1;
+                ti = end + 1;
+            }
+        }
+    }
+    for &c in &p[pi..] {
+        if c != '*' {
+            return None;
+        }
+        spans.push((ti, ti));
+    }
+    Some(
+        spans
+            .into_iter()
+            .map(|(from, to)| (byte(from), byte(to)))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_like_gmad() {
+        assert!(matches("lua/*.lua", "lua/autorun/x.lua"));
+        assert!(matches("lua/*.lua", "lua/.lua"));
+        assert!(!matches("lua/*.lua", "lua/x.luac"));
+        assert!(matches("*", ""));
+        assert!(matches("", ""));
+        assert!(!matches("", "x"));
+        assert!(matches("a?c", "abc"));
+        assert!(!matches("a?c", "ac"));
+        assert!(matches("*a*b", "xaxxb"));
+        assert!(!matches("*a*b", "xaxxba"));
+        assert!(matches("**", "abc"));
+        assert!(!matches("abc?", "abc"));
+    }
+
+    #[test]
+    fn captures_are_shortest_first() {
+        assert_eq!(captures("*/*", "a/b/c"), Some(vec!["a", "b/c"]));
+        assert_eq!(captures("*.*", "a.b.c"), Some(vec!["a", "b.c"]));
+        assert_eq!(captures("a*?", "abcd"), Some(vec!["bc", "d"]));
+        assert_eq!(captures("a*?d", "abcd"), Some(vec!["b", "c"]));
+        assert_eq!(captures("x*", "x"), Some(vec![""]));
+        assert_eq!(captures("*b*", "abab"), Some(vec!["a", "ab"]));
+        assert_eq!(captures("*x", "abc"), None);
+    }
+
+    #[test]
+    fn handles_multibyte_text() {
+        assert_eq!(captures("?/*", "é/ü.lua"), Some(vec!["é", "ü.lua"]));
+        assert!(matches("sound/*é*.wav", "sound/caf/é/x.wav"));
+    }
+
+    #[test]
+    fn backtracking_is_not_exponential() {
+        let text = "a".repeat(2000);
+        let pattern = format!("{}b", "a*".repeat(40));
+        assert!(!matches(&pattern, &text));
+        let pattern = "*a".repeat(40);
+        assert!(matches(&pattern, &text));
+    }
+}
@@ -0,0 +1,162 @@
+use alloc::format;
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+use crate::GmaError;
+
+/// Most tags an addon can have.
+pub const MAX_TAGS: usize = 2;
+
+/// The addon types the Workshop accepts; an addon has exactly one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum AddonType {
+    Gamemode,
+    Map,
+    Weapon,
+    Vehicle,
+    Npc,
+    Entity,
+    Tool,
+    Effects,
+    Model,
+    ServerContent,
+}
+
+/// The tags the Workshop accepts; an addon has at most [`MAX_TAGS`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum AddonTag {
+    Fun,
+    Roleplay,
+    Scenic,
+    Movie,
+    Realism,
+    Cartoon,
+    Water,
+    Comic,
+    Build,
+}
+
+impl AddonType {
+    pub const ALL: [AddonType; 10] = [
+        AddonType::Gamemode,
+        AddonType::Map,
+        AddonType::Weapon,
+        AddonType::Vehicle,
+        AddonType::Npc,
+        AddonType::Entity,
+        AddonType::Tool,
+        AddonType::Effects,
+        AddonType::Model,
+        AddonType::ServerContent,
+    ];
+
+    /// The name used in `addon.json` and the description, e.g. `"servercontent"`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AddonType::Gamemode => "gamemode",
+            AddonType::Map => "map",
+            AddonType::Weapon => "weapon",
+            AddonType::Vehicle => "vehicle",
+            AddonType::Npc => "npc",
+            AddonType::Entity => "entity",
+            AddonType::Tool => "tool",
+            AddonType::Effects => "effects",
+            AddonType::Model => "model",
+            AddonType::ServerContent => "servercontent",
+        }
+    }
+}
+
+impl AddonTag {
+    pub const ALL: [AddonTag; 9] = [
+        AddonTag::Fun,
+        AddonTag::Roleplay,
+        AddonTag::Scenic,
+        AddonTag::Movie,
+        AddonTag::Realism,
+        AddonTag::Cartoon,
+        AddonTag::Water,
+        AddonTag::Comic,
+        AddonTag::Build,
+    ];
+
+    /// The name used in `addon.json` and the description, e.g. `"roleplay"`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AddonTag::Fun => "fun",
+            AddonTag::Roleplay => "roleplay",
+            AddonTag::Scenic => "scenic",
+            AddonTag::Movie => "movie",
+            AddonTag::Realism => "realism",
+            AddonTag::Cartoon => "cartoon",
+            AddonTag::Water => "water",
+            AddonTag::Comic => "comic",
+            AddonTag::Build => "build",
+        }
+    }
+}
+
+/// Check a type and tags against the rules gmad enforces: one known type, and
+/// at most [`MAX_TAGS`] known, distinct tags. Names are compared ASCII
+/// case-insensitively.
+pub fn check_type_and_tags<S: AsRef<str>>(
+    addon_type: &str,
+    tags: &[S],
+) -> Result<(AddonType, Vec<AddonTag>), GmaError> {
+    if addon_type.is_empty() {
+        return Err(GmaError::InvalidMetadata("no addon type is set".into()));
+    }
+    let addon_type = addon_type.parse()?;
+    if tags.len() > MAX_TAGS {
+        return Err(GmaError::InvalidMetadata(format!(
+            "{} tags, at most {MAX_TAGS} are allowed",
+            tags.len()
+        )));
+    }
+    let mut parsed: Vec<AddonTag> = Vec::with_capacity(tags.len());
+    for tag in tags {
+        let tag = tag.as_ref().parse()?;
+        if parsed.contains(&tag) {
+            return Err(GmaError::InvalidMetadata(format!(
+                "tag \"{tag}\" is repeated"
+            )));
+        }
+        parsed.push(tag);
+    }
+    Ok((addon_type, parsed))
+}
+
+impl FromStr for AddonType {
+    type Err = GmaError;
+
+    fn from_str(s: &str) -> Result<Self, GmaError> {
+        Self::ALL
+            .into_iter()
+            .find(|t| t.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| GmaError::InvalidMetadata(format!("unknown addon type \"{s}\"")))
+    }
+}
+
+impl FromStr for AddonTag {
+    type Err = GmaError;
+
+    fn from_str(s: &str) -> Result<Self, GmaError> {
+        Self::ALL
+            .into_iter()
+            .find(|t| t.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| GmaError::InvalidMetadata(format!("unknown tag \"{s}\"")))
+    }
+}
+
+impl fmt::Display for AddonType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Display for AddonTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
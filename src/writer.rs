@@ -0,0 +1,364 @@
+use std::io::{self, Seek, SeekFrom, Write};
+
+use crate::builder::Header;
+use crate::{AddonInfo, GmaError, MIN_VERSION, VERSION};
+
+/// Writes an archive as it goes, so contents never have to be held in memory
+/// the way [`Builder`](crate::Builder) holds them.
+///
+/// The format puts the metadata table before any content, so every entry's
+/// name and size is declared up front and [`new`](Self::new) writes the header
+/// and table right away. Contents then follow one entry at a time, in declared
+/// order, through [`begin_entry`](Self::begin_entry). Entry CRCs are written
+/// as 0.
+///
+/// On a seekable writer, [`new_seekable`](Self::new_seekable) also accepts
+/// entries of unknown size, patched into the table by
+/// [`finish_seekable`](Self::finish_seekable).
+pub struct GmaWriter<W: Write> {
+    inner: W,
+    /// Declared name and size of each entry; `None` if the size is unknown.
+    entries: Vec<(String, Option<u64>)>,
+    /// Position of the archive in `inner`.
+    start: u64,
+    /// Length of the header and table.
+    head_len: u64,
+    /// Entries begun so far; the last one may still be in progress.
+    begun: usize,
+    /// Whether the last begun entry is still being written.
+    open: bool,
+    /// Bytes written to the last begun entry.
+    written: u64,
+    /// Sizes of unknown-size entries, by index, once written.
+    patches: Vec<(usize, u64)>,
+}
+
+/// Content of one entry, from [`GmaWriter::begin_entry`]. Writing past the
+/// declared size fails.
+pub struct EntryWriter<'a, W: Write> {
+    writer: &'a mut GmaWriter<W>,
+}
+
+impl<W: Write> GmaWriter<W> {
+    /// Write the header from `info` (timestamp included, as given) and a table
+    /// listing `entries` as name and size.
+    pub fn new<N: Into<String>>(
+        inner: W,
+        info: &AddonInfo,
+        entries: impl IntoIterator<Item = (N, u64)>,
+    ) -> Result<Self, GmaError> {
+        let entries = entries.into_iter().map(|(n, s)| (n.into(), Some(s)));
+        Self::start(inner, info, entries.collect(), 0)
+    }
+
+    fn start(
+        mut inner: W,
+        info: &AddonInfo,
+        entries: Vec<(String, Option<u64>)>,
+        start: u64,
+    ) -> Result<Self, GmaError> {
+        if !(MIN_VERSION..=VERSION).contains(&info.version) {
+            return Err(GmaError::InvalidVersion(info.version));
+        }
+        // File indices are u32 and 0 ends the table
+        if entries.len() > u32::MAX as usize {
+            return Err(GmaError::TooManyEntries {
+                limit: u32::MAX as usize,
+            });
+        }
+        let header = Header {
+            version: info.version,
+            steam_id64: info.steam_id64,
+            timestamp: info.timestamp,
+            required_content: &info.required_content,
+            name: &info.name,
+            description: &info.description,
+            author: &info.author,
+            addon_version: info.addon_version,
+        };
+        let table = entries.iter().map(|(n, s)| (n.as_str(), s.unwrap_or(0), 0));
+        let head = header.encode(table)?;
+        inner.write_all(&head)?;
+        Ok(Self {
+            inner,
+            entries,
+            start,
+            head_len: head.len() as u64,
+            begun: 0,
+            open: false,
+            written: 0,
+            patches: Vec::new(),
+        })
+    }
+
+    /// Start the content of the next declared entry, which must be `name` with
+    /// `size` bytes. The previous entry must be complete.
+    pub fn begin_entry(&mut self, name: &str, size: u64) -> Result<EntryWriter<'_, W>, GmaError> {
+        self.begin(name, Some(size))
+    }
+
+    /// Like [`begin_entry`](Self::begin_entry), for an entry declared without a
+    /// size; it takes however many bytes are written.
+    pub fn begin_unsized_entry(&mut self, name: &str) -> Result<EntryWriter<'_, W>, GmaError> {
+        self.begin(name, None)
+    }
+
+    fn begin(&mut self, name: &str, size: Option<u64>) -> Result<EntryWriter<'_, W>, GmaError> {
+        self.close_current()?;
+        let Some((next, next_size)) = self.entries.get(self.begun) else {
+            return Err(invalid_input(format!("{name} was not declared")));
+        };
+        if next != name || *next_size != size {
+            return Err(invalid_input(format!(
+                "expected {next} ({}) next, got {name} ({})",
+                describe_size(*next_size),
+                describe_size(size)
+            )));
+        }
+        self.begun += 1;
+        self.open = true;
+        self.written = 0;
+        Ok(EntryWriter { writer: self })
+    }
+
+    /// Check every entry was written in full, write the trailing marker and
+    /// hand back the writer.
+    ///
+    /// Entries of unknown size need [`finish_seekable`](Self::finish_seekable).
+    pub fn finish(mut self) -> Result<W, GmaError> {
+        self.close_current()?;
+        if !self.patches.is_empty() {
+            return Err(invalid_input(
+                "entries of unknown size need finish_seekable".to_owned(),
+            ));
+        }
+        self.write_trailer()?;
+        Ok(self.inner)
+    }
+
+    fn write_trailer(&mut self) -> Result<(), GmaError> {
+        self.close_current()?;
+        if let Some((name, _)) = self.entries.get(self.begun) {
+            return Err(invalid_input(format!("{name} was never written")));
+        }
+        self.inner.write_all(&0u32.to_le_bytes())?;
+        self.inner.flush()?;
+        Ok(())
+    }
+
+    /// End the entry in progress; fails if it's short of its declared size.
+    fn close_current(&mut self) -> Result<(), GmaError> {
+        if !std::mem::take(&mut self.open) {
+            return Ok(());
+        }
+        let i = self.begun - 1;
+        match self.entries[i] {
+            (_, None) => self.patches.push((i, self.written)),
+            (ref name, Some(size)) if self.written != size => {
+                return Err(GmaError::TruncatedEntry {
+                    name: name.clone(),
+                    read: self.written,
+                    expected: size,
+                });
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write + Seek> GmaWriter<W> {
+    /// Like [`new`](Self::new), but entries may be declared with a size of
+    /// `None` and written with [`begin_unsized_entry`](Self::begin_unsized_entry),
+    /// e.g. straight from a compressor. The archive starts at the writer's
+    /// current position.
+    pub fn new_seekable<N: Into<String>>(
+        mut inner: W,
+        info: &AddonInfo,
+        entries: impl IntoIterator<Item = (N, Option<u64>)>,
+    ) -> Result<Self, GmaError> {
+        let start = inner.stream_position()?;
+        let entries = entries.into_iter().map(|(n, s)| (n.into(), s));
+        Self::start(inner, info, entries.collect(), start)
+    }
+
+    /// Like [`finish`](Self::finish), then seek back to fill in the sizes of
+    /// entries declared without one. The writer is left at the archive's end.
+    pub fn finish_seekable(mut self) -> Result<W, GmaError> {
+        self.write_trailer()?;
+        let end = self.inner.stream_position()?;
+        // The table starts after the header; each record is index, name, size, crc
+        let table_len: u64 = self.entries.iter().map(|(n, _)| record_len(n)).sum::<u64>() + 4;
+        let mut offsets = Vec::with_capacity(self.entries.len());
+        let mut pos = self.start + self.head_len - table_len;
+        for (name, _) in &self.entries {
+            offsets.push(pos + 4 + name.len() as u64 + 1);
+            pos += record_len(name);
+        }
+        for &(i, size) in &self.patches {
+            self.inner.seek(SeekFrom::Start(offsets[i]))?;
+            self.inner.write_all(&(size as i64).to_le_bytes())?;
+        }
+        self.inner.seek(SeekFrom::Start(end))?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for EntryWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let w = &mut *self.writer;
+        let remaining = w.entries[w.begun - 1].1.unwrap_or(u64::MAX) - w.written;
+        if remaining == 0 && !buf.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "entry content exceeds its declared size",
+            ));
+        }
+        let len = buf.len().min(remaining.try_into().unwrap_or(usize::MAX));
+        let n = w.inner.write(&buf[..len])?;
+        w.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.inner.flush()
+    }
+}
+
+/// Bytes taken by one entry's record in the metadata table.
+fn record_len(name: &str) -> u64 {
+    4 + name.len() as u64 + 1 + 8 + 4
+}
+
+fn describe_size(size: Option<u64>) -> String {
+    match size {
+        Some(size) => format!("{size} bytes"),
+        None => "unknown size".to_owned(),
+    }
+}
+
+fn invalid_input(msg: String) -> GmaError {
+    io::Error::new(io::ErrorKind::InvalidInput, msg).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{Builder, read};
+
+    const FILES: [(&str, &[u8]); 3] = [
+        ("lua/a.lua", b"print(1)"),
+        ("lua/empty.lua", b""),
+        ("materials/b.vmt", b"\"LightmappedGeneric\" {}"),
+    ];
+
+    /// What `Builder` writes for [`FILES`], and the header it was given.
+    fn expected() -> (Vec<u8>, AddonInfo) {
+        let mut b = Builder::new("streamed", 7);
+        b.set_description("d")
+            .set_author("a")
+            .set_timestamp(1_700_000_000)
+            .set_entry_crcs(false);
+        for (name, content) in FILES {
+            b.file_from_bytes(name, content.to_vec());
+        }
+        let mut out = Vec::new();
+        b.write_to(&mut out).unwrap();
+        let info = crate::read_with(&out[..], &Default::default())
+            .unwrap()
+            .info()
+            .clone();
+        (out, info)
+    }
+
+    #[test]
+    fn matches_builder_output() {
+        let (expected, info) = expected();
+        let mut w = GmaWriter::new(
+            Vec::new(),
+            &info,
+            FILES.iter().map(|(n, c)| (*n, c.len() as u64)),
+        )
+        .unwrap();
+        for (name, content) in FILES {
+            let mut e = w.begin_entry(name, content.len() as u64).unwrap();
+            io::copy(&mut &content[..], &mut e).unwrap();
+        }
+        assert_eq!(w.finish().unwrap(), expected);
+    }
+
+    #[test]
+    fn enforces_the_declared_table() {
+        let (_, info) = expected();
+        let declared = [("lua/a.lua", 3u64), ("lua/b.lua", 1)];
+
+        let mut w = GmaWriter::new(Vec::new(), &info, declared).unwrap();
+        assert!(w.begin_entry("lua/b.lua", 1).is_err());
+        assert!(w.begin_entry("lua/a.lua", 4).is_err());
+        let mut e = w.begin_entry("lua/a.lua", 3).unwrap();
+        assert!(e.write_all(b"abcd").is_err());
+
+        let mut w = GmaWriter::new(Vec::new(), &info, declared).unwrap();
+        w.begin_entry("lua/a.lua", 3)
+            .unwrap()
+            .write_all(b"ab")
+            .unwrap();
+        assert!(matches!(
+            w.begin_entry("lua/b.lua", 1),
+            Err(GmaError::TruncatedEntry {
+                read: 2,
+                expected: 3,
+                ..
+            })
+        ));
+
+        let mut w = GmaWriter::new(Vec::new(), &info, declared).unwrap();
+        w.begin_entry("lua/a.lua", 3)
+            .unwrap()
+            .write_all(b"abc")
+            .unwrap();
+        assert!(w.finish().is_err());
+    }
+
+    #[test]
+    fn seekable_writer_patches_unknown_sizes() {
+        let (expected, info) = expected();
+        // Only the first entry's size is known up front
+        let declared: Vec<_> = FILES
+            .iter()
+            .enumerate()
+            .map(|(i, (n, c))| (*n, (i == 0).then_some(c.len() as u64)))
+            .collect();
+
+        // After unrelated bytes, so the patch offsets must include the start
+        let mut out = Cursor::new(b"prefix".to_vec());
+        out.seek(SeekFrom::End(0)).unwrap();
+        let mut w = GmaWriter::new_seekable(&mut out, &info, declared.clone()).unwrap();
+        for ((name, content), (_, size)) in FILES.iter().zip(&declared) {
+            let mut e = match size {
+                Some(size) => w.begin_entry(name, *size).unwrap(),
+                None => w.begin_unsized_entry(name).unwrap(),
+            };
+            e.write_all(content).unwrap();
+        }
+        let end = w.finish_seekable().unwrap().stream_position().unwrap();
+        let out = out.into_inner();
+        assert_eq!(end, out.len() as u64);
+        assert_eq!(&out[6..], expected);
+
+        let files = read(&out[6..]).unwrap();
+        assert_eq!(files[2].content, FILES[2].1);
+
+        // finish can't patch
+        let mut out = Cursor::new(Vec::new());
+        let mut w = GmaWriter::new_seekable(&mut out, &info, [("lua/a.lua", None)]).unwrap();
+        w.begin_unsized_entry("lua/a.lua")
+            .unwrap()
+            .write_all(b"x")
+            .unwrap();
+        assert!(w.finish().is_err());
+    }
+}